@@ -0,0 +1,113 @@
+//! Compares the `Encoder` with the previous encoding scheme, which built a new buffer at every
+//! nesting level and copied it into the parent one.
+//!
+//! Run with `cargo bench --bench encoder`.
+
+// The crate is a binary, so the modules under benchmark are included directly, along with
+// their unit tests and the parts the benchmark does not use.
+#![allow(dead_code, unused_imports)]
+
+#[path = "../src/types/mod.rs"]
+mod types;
+
+#[path = "../src/bencode/encoders/mod.rs"]
+mod encoders;
+
+use std::collections::BTreeMap;
+use std::io;
+use std::rc::Rc;
+
+use criterion::{black_box, criterion_group, criterion_main, Criterion, Throughput};
+
+use types::{ByteString, DataType};
+
+const FILES_COUNT: usize = 10_000;
+const PIECES_COUNT: usize = 50_000;
+
+/// An info dict of a multi-file torrent with nested directories.
+fn multi_file_info() -> DataType {
+    let files: Vec<DataType> = (0..FILES_COUNT)
+        .map(|i| {
+            DataType::Dict(BTreeMap::from_iter([
+                ("length".into(), DataType::Integer(1_000_000 + i as i64)),
+                (
+                    "path".into(),
+                    DataType::List(vec![
+                        DataType::ByteString(format!("dir{}", i / 100).as_str().into()),
+                        DataType::ByteString(format!("subdir{}", i / 10).as_str().into()),
+                        DataType::ByteString(format!("file{i}.bin").as_str().into()),
+                    ]),
+                ),
+            ]))
+        })
+        .collect();
+    DataType::Dict(BTreeMap::from_iter([
+        ("files".into(), DataType::List(files)),
+        ("name".into(), DataType::ByteString("root".into())),
+        ("piece length".into(), DataType::Integer(262_144)),
+        (
+            "pieces".into(),
+            DataType::ByteString(ByteString::from(vec![0xAB; 20 * PIECES_COUNT].as_slice())),
+        ),
+    ]))
+}
+
+fn nested_buffers(value: &DataType) -> Rc<[u8]> {
+    let mut result: Vec<u8> = vec![];
+    match value {
+        DataType::Integer(num) => {
+            result.push(b'i');
+            result.extend_from_slice(num.to_string().as_bytes());
+            result.push(b'e');
+        }
+        DataType::ByteString(byte_str) => result.extend_from_slice(&nested_byte_string(byte_str)),
+        DataType::List(list) => {
+            result.push(b'l');
+            for element in list {
+                result.extend_from_slice(&nested_buffers(element));
+            }
+            result.push(b'e');
+        }
+        DataType::Dict(dict) => {
+            result.push(b'd');
+            for (key, value) in dict {
+                result.extend_from_slice(&nested_byte_string(key));
+                result.extend_from_slice(&nested_buffers(value));
+            }
+            result.push(b'e');
+        }
+    }
+    result.into()
+}
+
+fn nested_byte_string(value: &ByteString) -> Rc<[u8]> {
+    let mut result: Vec<u8> = vec![];
+    result.extend_from_slice(value.get_data().len().to_string().as_bytes());
+    result.push(b':');
+    result.extend_from_slice(value.get_data());
+    result.into()
+}
+
+fn bench_multi_file_info(c: &mut Criterion) {
+    let info: DataType = multi_file_info();
+    assert_eq!(nested_buffers(&info), encoders::bencode(&info));
+
+    let mut group = c.benchmark_group("multi_file_info");
+    group.throughput(Throughput::Bytes(encoders::encoded_len(&info) as u64));
+    group.bench_function("nested_buffers", |b| {
+        b.iter(|| nested_buffers(black_box(&info)))
+    });
+    group.bench_function("bencode", |b| {
+        b.iter(|| encoders::bencode(black_box(&info)))
+    });
+    group.bench_function("encode_to_vec", |b| {
+        b.iter(|| encoders::encode_to_vec(black_box(&info)))
+    });
+    group.bench_function("encoder_into_sink", |b| {
+        b.iter(|| encoders::Encoder::new(io::sink()).encode(black_box(&info)))
+    });
+    group.finish();
+}
+
+criterion_group!(benches, bench_multi_file_info);
+criterion_main!(benches);
@@ -0,0 +1,45 @@
+//! Feeds arbitrary bytes to the bencode decoders, which must return an error instead of
+//! panicking on malformed input.
+//!
+//! Run with `cargo fuzz run decode` from the repository root.
+
+#![no_main]
+// The crate is a binary, so the modules under test are included directly, along with the parts
+// the fuzz target does not use.
+#![allow(dead_code, unused_imports)]
+
+#[path = "../../src/bencode/mod.rs"]
+mod bencode;
+#[path = "../../src/error.rs"]
+mod error;
+#[path = "../../src/magnet/mod.rs"]
+mod magnet;
+#[path = "../../src/torrent/mod.rs"]
+mod torrent;
+#[path = "../../src/types/mod.rs"]
+mod types;
+
+use libfuzzer_sys::fuzz_target;
+
+use bencode::decoders::{self, DecodeOptions, DecodeStatus, StreamDecoder};
+use bencode::encoders;
+
+fuzz_target!(|data: &[u8]| {
+    if let Ok((value, length)) = decoders::decode(data) {
+        // Anything the lenient decoder accepts has to survive an encoding round trip.
+        let encoded: Vec<u8> = encoders::encode_to_vec(&value);
+        let (decoded, _) = decoders::decode(&encoded).expect("re-encoded value must decode");
+        assert_eq!(decoded, value);
+        assert!(length <= data.len());
+    }
+    let _ = decoders::decode_with_options(data, &DecodeOptions::strict());
+    let _ = decoders::decode_dict_with_spans(data);
+
+    let mut stream = StreamDecoder::new();
+    for chunk in data.chunks(7) {
+        match stream.feed(chunk) {
+            Ok(DecodeStatus::NeedMoreData) => {}
+            Ok(DecodeStatus::Complete(_)) | Err(_) => break,
+        }
+    }
+});
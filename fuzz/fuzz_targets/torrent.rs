@@ -0,0 +1,28 @@
+//! Feeds arbitrary bytes to the metainfo parser, which must reject malformed torrent files
+//! instead of panicking.
+//!
+//! Run with `cargo fuzz run torrent` from the repository root.
+
+#![no_main]
+// The crate is a binary, so the modules under test are included directly, along with the parts
+// the fuzz target does not use.
+#![allow(dead_code, unused_imports)]
+
+#[path = "../../src/bencode/mod.rs"]
+mod bencode;
+#[path = "../../src/error.rs"]
+mod error;
+#[path = "../../src/magnet/mod.rs"]
+mod magnet;
+#[path = "../../src/torrent/mod.rs"]
+mod torrent;
+#[path = "../../src/types/mod.rs"]
+mod types;
+
+use libfuzzer_sys::fuzz_target;
+
+use torrent::Torrent;
+
+fuzz_target!(|data: &[u8]| {
+    let _ = Torrent::try_from(data);
+});
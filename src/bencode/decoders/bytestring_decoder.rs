@@ -2,6 +2,7 @@ use std::num::ParseIntError;
 
 use thiserror::Error;
 
+use crate::bencode::decoders::DecodeOptions;
 use crate::types::ByteString;
 
 #[derive(Error, Debug, PartialEq)]
@@ -16,24 +17,42 @@ pub enum ByteStringDecodeError {
     },
     #[error("Invalid length.")]
     InvalidLength(#[from] ParseIntError),
-    #[error("The input does not have a colon (':').")]
-    ColonNotFound,
-    #[error("Expected {expected:?} bytes after the colon (':') but found {found:?}")]
-    ContentTooShort { expected: usize, found: usize },
+    #[error("The length of the byte string has leading zeroes.")]
+    LeadingZeroInLength,
+    #[error("The input does not have a colon (':') before {position}.")]
+    ColonNotFound { position: usize },
+    #[error("The length {length} exceeds the limit of {limit} bytes.")]
+    LengthLimitExceeded { length: usize, limit: usize },
+    #[error(
+        "Expected {expected:?} bytes after the colon (':') but found {found:?} before {position}"
+    )]
+    ContentTooShort {
+        expected: usize,
+        found: usize,
+        position: usize,
+    },
 }
 
 pub fn decode_byte_string(bencoded: &[u8]) -> Result<(ByteString, usize), ByteStringDecodeError> {
+    decode_byte_string_ref(bencoded, &DecodeOptions::default())
+        .map(|(data, bytes_processed)| (ByteString::from(data), bytes_processed))
+}
+
+/// Decodes a bencoded string without copying it: the returned slice borrows from the input.
+pub fn decode_byte_string_ref<'a>(
+    bencoded: &'a [u8],
+    options: &DecodeOptions,
+) -> Result<(&'a [u8], usize), ByteStringDecodeError> {
     if bencoded.is_empty() {
         return Err(ByteStringDecodeError::EmptyInput);
     }
 
-    let mut length_str: String = String::new();
     let mut pos: usize = 0;
     let mut colon_found: bool = false;
     while pos < bencoded.len() {
         match bencoded[pos] {
             b':' => colon_found = true,
-            b'0'..=b'9' => length_str.push(bencoded[pos] as char),
+            b'0'..=b'9' => {}
             other => Err(ByteStringDecodeError::UnexpectedByte {
                 unexpected_byte: other,
                 unexpected_byte_ascii: other as char,
@@ -47,21 +66,34 @@ pub fn decode_byte_string(bencoded: &[u8]) -> Result<(ByteString, usize), ByteSt
     }
 
     if !colon_found {
-        return Err(ByteStringDecodeError::ColonNotFound);
+        return Err(ByteStringDecodeError::ColonNotFound { position: pos });
+    }
+
+    // The length consists of ASCII digits only, so it is valid UTF-8.
+    let length_str: &str = std::str::from_utf8(&bencoded[..pos - 1]).unwrap_or_default();
+    if options.strict && length_str.starts_with('0') && length_str.len() > 1 {
+        return Err(ByteStringDecodeError::LeadingZeroInLength);
     }
 
     let length: usize = length_str.parse::<usize>()?;
 
-    let n: usize = pos + length;
+    if length > options.max_string_length {
+        return Err(ByteStringDecodeError::LengthLimitExceeded {
+            length,
+            limit: options.max_string_length,
+        });
+    }
+
+    let n: usize = pos.saturating_add(length);
     if n > bencoded.len() {
         Err(ByteStringDecodeError::ContentTooShort {
             expected: length,
             found: bencoded.len() - pos,
+            position: bencoded.len(),
         })?
     }
 
-    let data: &[u8] = &bencoded[pos..n];
-    Ok((ByteString::new(&data.into()), n))
+    Ok((&bencoded[pos..n], n))
 }
 
 #[cfg(test)]
@@ -96,6 +128,53 @@ mod tests {
     //     assert!(matches!(result.unwrap_err(), ByteStringDecodeError::InvalidLength(_)));
     // }
 
+    #[test]
+    fn test_leading_zero_in_length() {
+        let (decoded, bytes_read) = decode_byte_string("03:abc".as_bytes()).unwrap();
+        assert_eq!(bytes_read, 6);
+        assert_eq!(decoded.get_data().as_ref(), "abc".as_bytes());
+
+        let result = decode_byte_string_ref("03:abc".as_bytes(), &DecodeOptions::strict());
+        assert_eq!(
+            result.unwrap_err(),
+            ByteStringDecodeError::LeadingZeroInLength
+        );
+
+        let result = decode_byte_string_ref("0:".as_bytes(), &DecodeOptions::strict());
+        assert!(result.is_ok());
+    }
+
+    #[test]
+    fn test_length_limit() {
+        let options = DecodeOptions {
+            max_string_length: 4,
+            ..Default::default()
+        };
+        assert!(decode_byte_string_ref("4:abcd".as_bytes(), &options).is_ok());
+        assert_eq!(
+            decode_byte_string_ref("5:abcde".as_bytes(), &options).unwrap_err(),
+            ByteStringDecodeError::LengthLimitExceeded {
+                length: 5,
+                limit: 4
+            }
+        );
+    }
+
+    #[test]
+    fn test_huge_length() {
+        let result = decode_byte_string_ref(
+            "18446744073709551615:a".as_bytes(),
+            &DecodeOptions {
+                max_string_length: usize::MAX,
+                ..Default::default()
+            },
+        );
+        assert!(matches!(
+            result.unwrap_err(),
+            ByteStringDecodeError::ContentTooShort { .. }
+        ));
+    }
+
     #[test]
     fn test_content_too_short() {
         let result = decode_byte_string("6:world".as_bytes());
@@ -104,7 +183,8 @@ mod tests {
             result.unwrap_err(),
             ByteStringDecodeError::ContentTooShort {
                 expected: 6,
-                found: 5
+                found: 5,
+                position: 7
             }
         );
     }
@@ -155,13 +235,19 @@ mod tests {
     fn test_ascii_colon_not_found() {
         let result = decode_byte_string("12345".as_bytes());
         assert!(result.is_err());
-        assert_eq!(result.unwrap_err(), ByteStringDecodeError::ColonNotFound);
+        assert_eq!(
+            result.unwrap_err(),
+            ByteStringDecodeError::ColonNotFound { position: 5 }
+        );
     }
 
     #[test]
     fn test_bytes_colon_not_found() {
         let result = decode_byte_string(&[b'1', b'2', b'3', b'4', b'5']);
         assert!(result.is_err());
-        assert_eq!(result.unwrap_err(), ByteStringDecodeError::ColonNotFound);
+        assert_eq!(
+            result.unwrap_err(),
+            ByteStringDecodeError::ColonNotFound { position: 5 }
+        );
     }
 }
@@ -1,22 +1,209 @@
 use crate::bencode::decoders;
-use crate::types::DataType;
+use crate::bencode::decoders::DecodeOptions;
+use crate::types::{DataType, DataTypeRef};
 
 pub fn decode(bencoded: &[u8]) -> Result<(DataType, usize), decoders::DecodeError> {
+    decode_with_options(bencoded, &DecodeOptions::default())
+}
+
+pub fn decode_with_options(
+    bencoded: &[u8],
+    options: &DecodeOptions,
+) -> Result<(DataType, usize), decoders::DecodeError> {
+    decode_ref(bencoded, options)
+        .map(|(value, bytes_processed)| (value.into_owned(), bytes_processed))
+}
+
+/// Decodes the value at the start of the input without copying its byte strings. In strict mode
+/// the input must consist of exactly one canonically encoded value.
+pub fn decode_ref<'a>(
+    bencoded: &'a [u8],
+    options: &DecodeOptions,
+) -> Result<(DataTypeRef<'a>, usize), decoders::DecodeError> {
+    check_input_length(bencoded, options)?;
+    let (value, bytes_processed) = decode_value(bencoded, options, 0)?;
+    if options.strict && bytes_processed < bencoded.len() {
+        return Err(decoders::DecodeError::TrailingData {
+            position: bytes_processed,
+        });
+    }
+    Ok((value, bytes_processed))
+}
+
+/// Checks the length of the whole input against the limit, before any of it is decoded.
+pub(crate) fn check_input_length(
+    bencoded: &[u8],
+    options: &DecodeOptions,
+) -> Result<(), decoders::DecodeError> {
+    if bencoded.len() > options.max_input_length {
+        return Err(decoders::DecodeError::InputTooLarge {
+            length: bencoded.len(),
+            limit: options.max_input_length,
+        });
+    }
+    Ok(())
+}
+
+/// Decodes the value at the start of the input, which may be followed by other data (e.g. the
+/// next element of the enclosing list). `depth` is the number of lists and dicts enclosing it.
+pub(crate) fn decode_value<'a>(
+    bencoded: &'a [u8],
+    options: &DecodeOptions,
+    depth: usize,
+) -> Result<(DataTypeRef<'a>, usize), decoders::DecodeError> {
     if let [first, ..] = bencoded {
-        return match first {
-            b'0'..=b'9' => decoders::decode_byte_string(bencoded)
-                .map(|(val, len)| Ok((DataType::ByteString(val), len)))?,
+        if matches!(first, b'l' | b'd') && depth >= options.max_depth {
+            return Err(decoders::DecodeError::DepthLimitExceeded {
+                limit: options.max_depth,
+            });
+        }
+        match first {
+            b'0'..=b'9' => decoders::decode_byte_string_ref(bencoded, options)
+                .map(|(val, len)| Ok((DataTypeRef::ByteString(val), len)))?,
             b'i' => decoders::decode_i64(bencoded)
-                .map(|(val, len)| Ok((DataType::Integer(val), len)))?,
-            b'l' => decoders::decode_list(bencoded)
-                .map(|(val, len)| Ok((DataType::List(val.to_vec()), len)))?,
-            b'd' => {
-                decoders::decode_dict(bencoded).map(|(val, len)| Ok((DataType::Dict(val), len)))?
-            }
-            other => Err(decoders::DecodeError::Other("TODO".to_owned())),
-            // other => Err(DecodeError::InvalidEntity(format!("Unexpected byte value '{other}' (ASCII: '{}') for the start of a bencoded entity: expected it to be either '0'-'9' (which indicates the start of the length of a bencoded string), or 'i' (bencoded integer), or 'l' (bencoded list), or 'd' (bencoded dict).", *other as char))),
-        };
+                .map(|(val, len)| Ok((DataTypeRef::Integer(val), len)))?,
+            b'l' => decoders::decode_list_at_depth(bencoded, options, depth)
+                .map(|(val, len)| Ok((DataTypeRef::List(val), len)))?,
+            b'd' => decoders::decode_dict_at_depth(bencoded, options, depth)
+                .map(|(val, len)| Ok((DataTypeRef::Dict(val), len)))?,
+            other => Err(decoders::DecodeError::InvalidStart {
+                found: *other,
+                found_as_ascii: *other as char,
+            }),
+        }
     } else {
-        return Err(decoders::DecodeError::EmptyInput);
+        Err(decoders::DecodeError::EmptyInput)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn decode_strict(bencoded: &[u8]) -> Result<(DataType, usize), DecodeError> {
+        decode_with_options(bencoded, &DecodeOptions::strict())
+    }
+    use crate::bencode::decoders::{
+        ByteStringDecodeError, DecodeError, DictDecodeError, ListDecodeError,
+    };
+
+    #[test]
+    fn test_decode_ref_borrows_from_input() {
+        let bencoded = "d6:piecesl3:abcee".as_bytes();
+        let (value, bytes_processed) = decode_ref(bencoded, &DecodeOptions::default()).unwrap();
+        assert_eq!(bytes_processed, bencoded.len());
+        let piece: &[u8] = value.get("pieces").unwrap().as_list().unwrap()[0]
+            .as_bytes()
+            .unwrap();
+        assert_eq!(piece, "abc".as_bytes());
+        assert!(std::ptr::eq(piece.as_ptr(), bencoded[12..].as_ptr()));
+
+        assert_eq!(value.into_owned(), decode(bencoded).unwrap().0);
+    }
+
+    #[test]
+    fn test_depth_limit() {
+        let options = DecodeOptions {
+            max_depth: 2,
+            ..Default::default()
+        };
+        assert!(decode_with_options("lli1eee".as_bytes(), &options).is_ok());
+        let result = decode_with_options("ld1:alee".as_bytes(), &options);
+        assert_eq!(
+            result.unwrap_err(),
+            DecodeError::ListDecodeError(ListDecodeError::ElementDecodeError {
+                position: 1,
+                index: 0,
+                source: Box::new(DecodeError::DictDecodeError(
+                    DictDecodeError::ValueDecodeError {
+                        position: 4,
+                        key: "a".into(),
+                        source: Box::new(DecodeError::DepthLimitExceeded { limit: 2 })
+                    }
+                )),
+            })
+        );
+    }
+
+    #[test]
+    fn test_deeply_nested_input_does_not_overflow_the_stack() {
+        let bencoded = [vec![b'l'; 100_000], vec![b'e'; 100_000]].concat();
+        let result = decode(&bencoded);
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_entries_limit() {
+        let options = DecodeOptions {
+            max_entries: 2,
+            ..Default::default()
+        };
+        assert!(decode_with_options("li1ei2ee".as_bytes(), &options).is_ok());
+        assert_eq!(
+            decode_with_options("li1ei2ei3ee".as_bytes(), &options).unwrap_err(),
+            DecodeError::ListDecodeError(ListDecodeError::TooManyElements { limit: 2 })
+        );
+        assert_eq!(
+            decode_with_options("d1:ai1e1:bi2e1:ci3ee".as_bytes(), &options).unwrap_err(),
+            DecodeError::DictDecodeError(DictDecodeError::TooManyEntries { limit: 2 })
+        );
+    }
+
+    #[test]
+    fn test_input_length_limit() {
+        let options = DecodeOptions {
+            max_input_length: 4,
+            ..Default::default()
+        };
+        assert!(decode_with_options("i12e".as_bytes(), &options).is_ok());
+        assert_eq!(
+            decode_with_options("i123e".as_bytes(), &options).unwrap_err(),
+            DecodeError::InputTooLarge {
+                length: 5,
+                limit: 4
+            }
+        );
+    }
+
+    #[test]
+    fn test_trailing_data() {
+        let (_, bytes_processed) = decode("i1ei2e".as_bytes()).unwrap();
+        assert_eq!(bytes_processed, 3);
+
+        let result = decode_strict("i1ei2e".as_bytes());
+        assert_eq!(
+            result.unwrap_err(),
+            DecodeError::TrailingData { position: 3 }
+        );
+    }
+
+    #[test]
+    fn test_strict_canonical_input() {
+        let result = decode_strict("d1:ali1e2:bce1:bd1:x0:ee".as_bytes());
+        assert!(result.is_ok());
+    }
+
+    #[test]
+    fn test_strict_nested_violation() {
+        let result = decode_strict("ld1:bi1e1:ai2eee".as_bytes());
+        assert_eq!(
+            result.unwrap_err(),
+            DecodeError::ListDecodeError(ListDecodeError::ElementDecodeError {
+                position: 1,
+                index: 0,
+                source: Box::new(DecodeError::DictDecodeError(
+                    DictDecodeError::UnsortedKeys { position: 7 }
+                )),
+            })
+        );
+    }
+
+    #[test]
+    fn test_strict_leading_zero_in_length() {
+        assert!(decode("03:abc".as_bytes()).is_ok());
+        assert_eq!(
+            decode_strict("03:abc".as_bytes()).unwrap_err(),
+            DecodeError::ByteStringDecodeError(ByteStringDecodeError::LeadingZeroInLength)
+        );
     }
 }
@@ -1,8 +1,9 @@
-use std::{collections::BTreeMap, string::FromUtf8Error};
+use std::{cmp::Ordering, collections::BTreeMap, ops::Range};
 use thiserror::Error;
 
 use crate::bencode::decoders;
-use crate::types::DataType;
+use crate::bencode::decoders::DecodeOptions;
+use crate::types::{ByteString, DataType, DataTypeRef};
 
 #[derive(Error, Debug, PartialEq)]
 pub enum DictDecodeError {
@@ -12,19 +13,74 @@ pub enum DictDecodeError {
         "Bencoded dicts must start with 'd' but got '{found:?}' (ASCII: '{found_as_ascii:?}')."
     )]
     StartNotFound { found: u8, found_as_ascii: char },
-    #[error("The end of dict ('e') not found.")]
-    EndNotFound,
-    #[error("Could not decode bencoded key.")]
-    KeyDecodeError(#[from] decoders::ByteStringDecodeError),
-    #[error("Could not parse bencoded key as UTF-8.")]
-    KeyUtf8ParseError(#[from] FromUtf8Error),
-    #[error("Could not decode value at {position}")]
-    ValueDecodeError { position: usize },
+    #[error("The end of dict ('e') not found at {position}.")]
+    EndNotFound { position: usize },
+    #[error("Could not decode the key at {position}.")]
+    KeyDecodeError {
+        position: usize,
+        #[source]
+        source: decoders::ByteStringDecodeError,
+    },
+    #[error("Could not decode the value of '{key}' at {position}.")]
+    ValueDecodeError {
+        position: usize,
+        key: ByteString,
+        #[source]
+        source: Box<decoders::DecodeError>,
+    },
+    #[error("The dict has more than {limit} entries.")]
+    TooManyEntries { limit: usize },
+    #[error("The key at {position} is not greater than the previous one.")]
+    UnsortedKeys { position: usize },
+    #[error("The key at {position} is a duplicate of the previous one.")]
+    DuplicateKey { position: usize },
 }
 
-pub fn decode_dict(
+/// A decoded dict whose values are paired with the byte spans they were decoded from.
+pub type SpannedDict = BTreeMap<ByteString, (DataType, Range<usize>)>;
+
+/// Decodes a dict nested in `depth` other lists or dicts, whose keys and values borrow their
+/// byte strings from the input.
+///
+/// In strict mode the keys must be unique and sorted by their raw bytes; otherwise the last of
+/// the duplicate keys wins.
+pub(crate) fn decode_dict_at_depth<'a>(
+    bencoded: &'a [u8],
+    options: &DecodeOptions,
+    depth: usize,
+) -> Result<(BTreeMap<&'a [u8], DataTypeRef<'a>>, usize), DictDecodeError> {
+    let mut dict: BTreeMap<&'a [u8], DataTypeRef<'a>> = BTreeMap::new();
+    let bytes_processed = decode_dict_entries(bencoded, options, depth, |key, value, _| {
+        dict.insert(key, value);
+    })?;
+    Ok((dict, bytes_processed))
+}
+
+/// Decodes a bencoded dict, keeping for every value the exact byte span it was decoded from.
+///
+/// The spans are relative to the start of `bencoded`, so `&bencoded[span]` is the original
+/// encoding of the value, regardless of whether it is canonical.
+pub fn decode_dict_with_spans(
     bencoded: &[u8],
-) -> Result<(BTreeMap<String, DataType>, usize), DictDecodeError> {
+) -> Result<(SpannedDict, usize), decoders::DecodeError> {
+    let mut dict: SpannedDict = BTreeMap::new();
+    let options = DecodeOptions::default();
+    decoders::check_input_length(bencoded, &options)?;
+    let bytes_processed = decode_dict_entries(bencoded, &options, 0, |key, value, span| {
+        dict.insert(ByteString::from(key), (value.into_owned(), span));
+    })?;
+    Ok((dict, bytes_processed))
+}
+
+fn decode_dict_entries<'a, F>(
+    bencoded: &'a [u8],
+    options: &DecodeOptions,
+    depth: usize,
+    mut on_entry: F,
+) -> Result<usize, DictDecodeError>
+where
+    F: FnMut(&'a [u8], DataTypeRef<'a>, Range<usize>),
+{
     if let [start, ..] = bencoded {
         if *start != b'd' {
             return Err(DictDecodeError::StartNotFound {
@@ -33,9 +89,10 @@ pub fn decode_dict(
             });
         }
 
-        let mut dict: BTreeMap<String, DataType> = BTreeMap::new();
         let mut pos: usize = 1;
         let mut end_of_dict_found = false;
+        let mut previous_key: Option<&[u8]> = None;
+        let mut entries_count: usize = 0;
         while pos < bencoded.len() {
             match bencoded[pos] {
                 b'e' => {
@@ -44,34 +101,62 @@ pub fn decode_dict(
                     break;
                 }
                 b'0'..=b'9' => {
+                    if entries_count == options.max_entries {
+                        return Err(DictDecodeError::TooManyEntries {
+                            limit: options.max_entries,
+                        });
+                    }
+                    entries_count += 1;
                     let (key, key_bytes_processed) =
-                        decoders::decode_byte_string(&bencoded[pos..])?;
-                    let key_str = String::from_utf8(key.get_data().to_vec())?;
+                        decoders::decode_byte_string_ref(&bencoded[pos..], options).map_err(
+                            |err| DictDecodeError::KeyDecodeError {
+                                position: pos,
+                                source: err,
+                            },
+                        )?;
+                    if options.strict {
+                        match previous_key.map(|previous| previous.cmp(key)) {
+                            Some(Ordering::Equal) => {
+                                return Err(DictDecodeError::DuplicateKey { position: pos })
+                            }
+                            Some(Ordering::Greater) => {
+                                return Err(DictDecodeError::UnsortedKeys { position: pos })
+                            }
+                            _ => previous_key = Some(key),
+                        }
+                    }
                     pos += key_bytes_processed;
-                    let (value, value_bytes_processed) = decoders::decode(&bencoded[pos..])
-                        .map_err(|err| DictDecodeError::ValueDecodeError { position: pos })?;
+                    let (value, value_bytes_processed) =
+                        decoders::decode_value(&bencoded[pos..], options, depth + 1).map_err(
+                            |err| DictDecodeError::ValueDecodeError {
+                                position: pos,
+                                key: ByteString::from(key),
+                                source: Box::new(err),
+                            },
+                        )?;
+                    on_entry(key, value, pos..pos + value_bytes_processed);
                     pos += value_bytes_processed;
-                    dict.insert(key_str, value);
                 }
                 other => {
-                    return Err(DictDecodeError::KeyDecodeError(
-                        decoders::ByteStringDecodeError::UnexpectedByte {
+                    return Err(DictDecodeError::KeyDecodeError {
+                        position: pos,
+                        source: decoders::ByteStringDecodeError::UnexpectedByte {
                             unexpected_byte: other,
                             unexpected_byte_ascii: other as char,
-                            position: pos,
+                            position: 0,
                         },
-                    ))
+                    })
                 }
             }
         }
 
         if !end_of_dict_found {
-            return Err(DictDecodeError::EndNotFound);
+            return Err(DictDecodeError::EndNotFound { position: pos });
         }
 
-        Ok((dict, pos))
+        Ok(pos)
     } else {
-        return Err(DictDecodeError::EmptyInput);
+        Err(DictDecodeError::EmptyInput)
     }
 }
 
@@ -79,7 +164,24 @@ pub fn decode_dict(
 mod tests {
 
     use super::*;
-    use crate::types::ByteString;
+
+    fn decode_dict(
+        bencoded: &[u8],
+    ) -> Result<(BTreeMap<ByteString, DataType>, usize), DictDecodeError> {
+        let mut dict: BTreeMap<ByteString, DataType> = BTreeMap::new();
+        let options = DecodeOptions::default();
+        let bytes_processed = decode_dict_entries(bencoded, &options, 0, |key, value, _| {
+            dict.insert(ByteString::from(key), value.into_owned());
+        })?;
+        Ok((dict, bytes_processed))
+    }
+
+    fn decode_dict_ref<'a>(
+        bencoded: &'a [u8],
+        options: &DecodeOptions,
+    ) -> Result<(BTreeMap<&'a [u8], DataTypeRef<'a>>, usize), DictDecodeError> {
+        decode_dict_at_depth(bencoded, options, 0)
+    }
 
     #[test]
     fn test_empty_dict() {
@@ -169,4 +271,67 @@ mod tests {
             ])
         );
     }
+
+    #[test]
+    fn test_dict_with_spans() {
+        let bencoded: &[u8] = "d1:bi1e1:ali1ei2eee".as_bytes();
+        let (result, bytes_processed) = decode_dict_with_spans(bencoded).unwrap();
+        assert_eq!(bytes_processed, 19);
+        let (value, span) = result.get("a".as_bytes()).unwrap();
+        assert_eq!(
+            *value,
+            DataType::List(vec![DataType::Integer(1), DataType::Integer(2)])
+        );
+        assert_eq!(&bencoded[span.clone()], "li1ei2ee".as_bytes());
+        let (value, span) = result.get("b".as_bytes()).unwrap();
+        assert_eq!(*value, DataType::Integer(1));
+        assert_eq!(span.clone(), 4..7);
+    }
+
+    #[test]
+    fn test_dict_binary_key() {
+        let (result, bytes_processed) = decode_dict(&[
+            b'd', b'2', b':', 0xFF, 0x00, b'i', b'1', b'e', b'1', b':', b'a', b'i', b'2', b'e',
+            b'e',
+        ])
+        .unwrap();
+        assert_eq!(bytes_processed, 15);
+        assert_eq!(
+            result,
+            BTreeMap::from_iter([
+                (
+                    ByteString::from([0xFF, 0x00].as_slice()),
+                    DataType::Integer(1)
+                ),
+                ("a".into(), DataType::Integer(2)),
+            ])
+        );
+    }
+
+    #[test]
+    fn test_strict_unsorted_keys() {
+        let (result, _) = decode_dict("d1:bi1e1:ai2ee".as_bytes()).unwrap();
+        assert_eq!(result.len(), 2);
+
+        let result = decode_dict_ref("d1:bi1e1:ai2ee".as_bytes(), &DecodeOptions::strict());
+        assert_eq!(
+            result.unwrap_err(),
+            DictDecodeError::UnsortedKeys { position: 7 }
+        );
+    }
+
+    #[test]
+    fn test_strict_duplicate_key() {
+        let (result, _) = decode_dict("d1:ai1e1:ai2ee".as_bytes()).unwrap();
+        assert_eq!(
+            result,
+            BTreeMap::from_iter([("a".into(), DataType::Integer(2))])
+        );
+
+        let result = decode_dict_ref("d1:ai1e1:ai2ee".as_bytes(), &DecodeOptions::strict());
+        assert_eq!(
+            result.unwrap_err(),
+            DictDecodeError::DuplicateKey { position: 7 }
+        );
+    }
 }
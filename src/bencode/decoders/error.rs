@@ -1,22 +1,177 @@
 use thiserror::Error;
 
-use crate::bencode::decoders::bytestring_decoder;
-use crate::bencode::decoders::dict_decoder;
-use crate::bencode::decoders::i64_decoder;
-use crate::bencode::decoders::list_decoder;
+use crate::bencode::decoders::bytestring_decoder::{self, ByteStringDecodeError};
+use crate::bencode::decoders::dict_decoder::{self, DictDecodeError};
+use crate::bencode::decoders::i64_decoder::{self, Int64DecodeError};
+use crate::bencode::decoders::list_decoder::{self, ListDecodeError};
+use crate::types::ByteString;
 
 #[derive(Error, Debug, PartialEq)]
 pub enum DecodeError {
     #[error("The input is empty.")]
     EmptyInput,
-    #[error("ByteString decode error")]
+    #[error(transparent)]
     ByteStringDecodeError(#[from] bytestring_decoder::ByteStringDecodeError),
-    #[error("Int64 decode error")]
+    #[error(transparent)]
     Int64DecodeError(#[from] i64_decoder::Int64DecodeError),
-    #[error("List decode error")]
+    #[error(transparent)]
     ListDecodeError(#[from] list_decoder::ListDecodeError),
-    #[error("Dict decode error")]
+    #[error(transparent)]
     DictDecodeError(#[from] dict_decoder::DictDecodeError),
-    #[error("Other")]
-    Other(String),
+    #[error("Unexpected byte value {found:?} (ASCII: {found_as_ascii:?}) for the start of a bencoded value: expected '0'-'9' (byte string), 'i' (integer), 'l' (list) or 'd' (dict).")]
+    InvalidStart { found: u8, found_as_ascii: char },
+    #[error("Unexpected data after the end of the bencoded value at {position}.")]
+    TrailingData { position: usize },
+    #[error("The input is {length} bytes long, which exceeds the limit of {limit} bytes.")]
+    InputTooLarge { length: usize, limit: usize },
+    #[error("The lists and dicts are nested deeper than the limit of {limit} levels.")]
+    DepthLimitExceeded { limit: usize },
+}
+
+enum PathSegment<'a> {
+    Key(&'a ByteString),
+    Index(usize),
+}
+
+impl DecodeError {
+    /// The offset of the failure from the start of the decoded input.
+    pub fn position(&self) -> usize {
+        let (position, _, root_cause) = self.locate();
+        position + root_cause.local_position()
+    }
+
+    /// The path of the value that could not be decoded, e.g. `info.files[3].path[0]`, or an empty
+    /// string if it is the top-level value.
+    pub fn path(&self) -> String {
+        let (_, path, _) = self.locate();
+        let mut result = String::new();
+        for segment in path {
+            match segment {
+                PathSegment::Key(key) if result.is_empty() => result.push_str(&key.to_string()),
+                PathSegment::Key(key) => result.push_str(&format!(".{key}")),
+                PathSegment::Index(index) => result.push_str(&format!("[{index}]")),
+            }
+        }
+        result
+    }
+
+    /// The innermost error, i.e. the one that is not caused by the decoding of a nested value.
+    pub fn root_cause(&self) -> &DecodeError {
+        let (_, _, root_cause) = self.locate();
+        root_cause
+    }
+
+    /// Follows the chain of nested list element and dict value errors, returning the offset of
+    /// the innermost value, the path to it, and the error it failed with.
+    fn locate(&self) -> (usize, Vec<PathSegment<'_>>, &DecodeError) {
+        let mut position: usize = 0;
+        let mut path: Vec<PathSegment<'_>> = vec![];
+        let mut error: &DecodeError = self;
+        loop {
+            match error {
+                DecodeError::ListDecodeError(ListDecodeError::ElementDecodeError {
+                    position: element_position,
+                    index,
+                    source,
+                }) => {
+                    position += element_position;
+                    path.push(PathSegment::Index(*index));
+                    error = source;
+                }
+                DecodeError::DictDecodeError(DictDecodeError::ValueDecodeError {
+                    position: value_position,
+                    key,
+                    source,
+                }) => {
+                    position += value_position;
+                    path.push(PathSegment::Key(key));
+                    error = source;
+                }
+                root_cause => return (position, path, root_cause),
+            }
+        }
+    }
+
+    /// The offset of the failure from the start of the value that could not be decoded.
+    fn local_position(&self) -> usize {
+        match self {
+            DecodeError::ByteStringDecodeError(err) => byte_string_position(err),
+            DecodeError::Int64DecodeError(
+                Int64DecodeError::EndNotFound { position }
+                | Int64DecodeError::NumberUtf8ParseError { position, .. }
+                | Int64DecodeError::NumberParseIntError { position, .. },
+            ) => *position,
+            DecodeError::ListDecodeError(ListDecodeError::EndNotFound { position }) => *position,
+            DecodeError::DictDecodeError(DictDecodeError::KeyDecodeError { position, source }) => {
+                position + byte_string_position(source)
+            }
+            DecodeError::DictDecodeError(
+                DictDecodeError::EndNotFound { position }
+                | DictDecodeError::UnsortedKeys { position }
+                | DictDecodeError::DuplicateKey { position },
+            ) => *position,
+            DecodeError::TrailingData { position } => *position,
+            _ => 0,
+        }
+    }
+}
+
+/// The offset of the failure from the start of the byte string that could not be decoded.
+fn byte_string_position(err: &ByteStringDecodeError) -> usize {
+    match err {
+        ByteStringDecodeError::UnexpectedByte { position, .. }
+        | ByteStringDecodeError::ColonNotFound { position }
+        | ByteStringDecodeError::ContentTooShort { position, .. } => *position,
+        _ => 0,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::bencode::decoders;
+
+    #[test]
+    fn test_location_of_nested_error() {
+        let bencoded = "d4:infod5:filesld4:pathl1:aeed4:pathlx1:beeeee".as_bytes();
+        let err = decoders::decode(bencoded).unwrap_err();
+        assert_eq!(err.path(), "info.files[1].path[0]");
+        assert_eq!(err.position(), 37);
+        assert_eq!(bencoded[err.position()], b'x');
+        assert!(matches!(
+            err.root_cause(),
+            decoders::DecodeError::InvalidStart { found: b'x', .. }
+        ));
+    }
+
+    #[test]
+    fn test_location_of_invalid_key() {
+        let bencoded = "ld1:ai1exee".as_bytes();
+        let err = decoders::decode(bencoded).unwrap_err();
+        assert_eq!(err.path(), "[0]");
+        assert_eq!(err.position(), 8);
+    }
+
+    #[test]
+    fn test_location_of_top_level_error() {
+        let err = decoders::decode("i12x".as_bytes()).unwrap_err();
+        assert_eq!(err.path(), "");
+        assert_eq!(err.position(), 4);
+
+        let err = decoders::decode("i12xe".as_bytes()).unwrap_err();
+        assert_eq!(err.position(), 3);
+    }
+
+    #[test]
+    fn test_location_of_truncated_string() {
+        let err = decoders::decode("d1:a5:abc".as_bytes()).unwrap_err();
+        assert_eq!(err.path(), "a");
+        assert_eq!(err.position(), 9);
+    }
+
+    #[test]
+    fn test_location_of_unterminated_nested_list() {
+        let err = decoders::decode("ld1:ali1e".as_bytes()).unwrap_err();
+        assert_eq!(err.path(), "[0].a");
+        assert_eq!(err.position(), 9);
+    }
 }
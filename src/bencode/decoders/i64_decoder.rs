@@ -10,18 +10,30 @@ pub enum Int64DecodeError {
         "Bencoded numbers must start with 'i' but got '{found:?}' (ASCII: '{found_as_ascii:?}')."
     )]
     StartNotFound { found: u8, found_as_ascii: char },
-    #[error("The end of number ('e') not found.")]
-    EndNotFound,
+    #[error("The end of number ('e') not found at {position}.")]
+    EndNotFound { position: usize },
     #[error("Number not found.")]
     NumberNotFound,
-    #[error("Could not parse the number as UTF-8.")]
-    NumberUtf8ParseError(#[from] FromUtf8Error),
-    #[error("Invalid number: {0}")]
-    InvalidNumberError(String),
-    #[error("")]
-    NumberParseIntError(#[from] ParseIntError),
+    #[error("Could not parse the number as UTF-8 at {position}.")]
+    NumberUtf8ParseError {
+        position: usize,
+        source: FromUtf8Error,
+    },
+    #[error("-0 is not valid for bencoded integers.")]
+    NegativeZero,
+    #[error("Bencoded integers other than 0 can not have leading zeroes.")]
+    LeadingZero,
+    #[error("Bencoded integers can not have a '+' sign.")]
+    PlusSign,
+    #[error("Could not parse the number at {position}.")]
+    NumberParseIntError {
+        position: usize,
+        source: ParseIntError,
+    },
 }
 
+/// Decodes a bencoded integer. Non-canonical forms (`-0`, leading zeroes and a '+' sign) are
+/// invalid bencode, so they are rejected whether or not the strict mode is on.
 pub fn decode_i64(bencoded: &[u8]) -> Result<(i64, usize), Int64DecodeError> {
     if let [start, ..] = bencoded {
         if *start != b'i' {
@@ -46,26 +58,42 @@ pub fn decode_i64(bencoded: &[u8]) -> Result<(i64, usize), Int64DecodeError> {
         }
 
         if !end_of_number_found {
-            return Err(Int64DecodeError::EndNotFound);
+            return Err(Int64DecodeError::EndNotFound { position: pos });
         }
 
         if num_chars_buf.len() == 0 {
             return Err(Int64DecodeError::NumberNotFound);
         }
 
-        let number_as_string = String::from_utf8(num_chars_buf)?;
+        let number_as_string = String::from_utf8(num_chars_buf).map_err(|source| {
+            Int64DecodeError::NumberUtf8ParseError {
+                position: 1 + source.utf8_error().valid_up_to(),
+                source,
+            }
+        })?;
 
         if number_as_string.eq("-0") {
-            return Err(Int64DecodeError::InvalidNumberError(
-                "-0 is not valid for bencoded integers.".to_owned(),
-            ));
-        } else if number_as_string.starts_with('0') && number_as_string.len() > 1 {
-            return Err(Int64DecodeError::InvalidNumberError(
-                "Bencoded integers other than 0 can not have leading zeroes.".to_owned(),
-            ));
+            return Err(Int64DecodeError::NegativeZero);
+        } else if number_as_string.trim_start_matches('-').starts_with('0')
+            && number_as_string.len() > 1
+        {
+            return Err(Int64DecodeError::LeadingZero);
+        } else if number_as_string.starts_with('+') {
+            return Err(Int64DecodeError::PlusSign);
         }
 
-        let number: i64 = number_as_string.parse::<i64>()?;
+        let number: i64 = number_as_string.parse::<i64>().map_err(|source| {
+            // Points at the first byte that can not be part of a number, if any.
+            let offset: usize = number_as_string
+                .bytes()
+                .enumerate()
+                .position(|(i, byte)| !(byte.is_ascii_digit() || i == 0 && byte == b'-'))
+                .unwrap_or(0);
+            Int64DecodeError::NumberParseIntError {
+                position: 1 + offset,
+                source,
+            }
+        })?;
 
         Ok((number, pos))
     } else {
@@ -122,31 +150,35 @@ mod tests {
     fn test_i64_end_not_found_error() {
         let result = decode_i64("i123".as_bytes());
         assert!(result.is_err());
-        assert_eq!(result.unwrap_err(), Int64DecodeError::EndNotFound);
+        assert_eq!(
+            result.unwrap_err(),
+            Int64DecodeError::EndNotFound { position: 4 }
+        );
     }
 
     #[test]
     fn test_negative_zero_error() {
         let result = decode_i64("i-0e".as_bytes());
         assert!(result.is_err());
-        assert_eq!(
-            result.unwrap_err(),
-            Int64DecodeError::InvalidNumberError(
-                "-0 is not valid for bencoded integers.".to_owned()
-            )
-        );
+        assert_eq!(result.unwrap_err(), Int64DecodeError::NegativeZero);
     }
 
     #[test]
     fn test_leading_zero_error() {
         let result = decode_i64("i03e".as_bytes());
         assert!(result.is_err());
-        assert_eq!(
-            result.unwrap_err(),
-            Int64DecodeError::InvalidNumberError(
-                "Bencoded integers other than 0 can not have leading zeroes.".to_owned()
-            )
-        );
+        assert_eq!(result.unwrap_err(), Int64DecodeError::LeadingZero);
+
+        let result = decode_i64("i-03e".as_bytes());
+        assert!(result.is_err());
+        assert_eq!(result.unwrap_err(), Int64DecodeError::LeadingZero);
+    }
+
+    #[test]
+    fn test_plus_sign_error() {
+        let result = decode_i64("i+5e".as_bytes());
+        assert!(result.is_err());
+        assert_eq!(result.unwrap_err(), Int64DecodeError::PlusSign);
     }
 
     #[test]
@@ -188,14 +220,20 @@ mod tests {
         assert!(result.is_err());
         assert!(matches!(
             result.unwrap_err(),
-            Int64DecodeError::NumberParseIntError(_)
+            Int64DecodeError::NumberParseIntError { position: 1, .. }
+        ));
+
+        let result = decode_i64("i12xe".as_bytes());
+        assert!(matches!(
+            result.unwrap_err(),
+            Int64DecodeError::NumberParseIntError { position: 3, .. }
         ));
 
         let result = decode_i64(&[b'i', 0xC, 0xA, 0xF, 0xE, b'e']);
         assert!(result.is_err());
         assert!(matches!(
             result.unwrap_err(),
-            Int64DecodeError::NumberParseIntError(_)
+            Int64DecodeError::NumberParseIntError { position: 1, .. }
         ));
     }
 }
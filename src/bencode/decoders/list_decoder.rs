@@ -1,9 +1,8 @@
-use std::rc::Rc;
-
 use thiserror::Error;
 
 use crate::bencode::decoders;
-use crate::types::DataType;
+use crate::bencode::decoders::DecodeOptions;
+use crate::types::DataTypeRef;
 
 #[derive(Error, Debug, PartialEq)]
 pub enum ListDecodeError {
@@ -13,13 +12,25 @@ pub enum ListDecodeError {
         "Bencoded lists must start with 'l' but got '{found:?}' (ASCII: '{found_as_ascii:?}')."
     )]
     StartNotFound { found: u8, found_as_ascii: char },
-    #[error("The end of list ('e') not found.")]
-    EndNotFound,
-    #[error("Could not decode the element at {position}.")]
-    ElementDecodeError { position: usize },
+    #[error("The end of list ('e') not found at {position}.")]
+    EndNotFound { position: usize },
+    #[error("The list has more than {limit} elements.")]
+    TooManyElements { limit: usize },
+    #[error("Could not decode the element #{index} at {position}.")]
+    ElementDecodeError {
+        position: usize,
+        index: usize,
+        #[source]
+        source: Box<decoders::DecodeError>,
+    },
 }
 
-pub fn decode_list(bencoded: &[u8]) -> Result<(Rc<[DataType]>, usize), ListDecodeError> {
+/// Decodes a list nested in `depth` other lists or dicts.
+pub(crate) fn decode_list_at_depth<'a>(
+    bencoded: &'a [u8],
+    options: &DecodeOptions,
+    depth: usize,
+) -> Result<(Vec<DataTypeRef<'a>>, usize), ListDecodeError> {
     if let [start, ..] = bencoded {
         if *start != b'l' {
             return Err(ListDecodeError::StartNotFound {
@@ -28,7 +39,7 @@ pub fn decode_list(bencoded: &[u8]) -> Result<(Rc<[DataType]>, usize), ListDecod
             });
         }
 
-        let mut decoded_elements: Vec<DataType> = vec![];
+        let mut decoded_elements: Vec<DataTypeRef<'a>> = vec![];
         let mut pos: usize = 1;
         let mut end_of_list_found = false;
         while pos < bencoded.len() {
@@ -39,8 +50,19 @@ pub fn decode_list(bencoded: &[u8]) -> Result<(Rc<[DataType]>, usize), ListDecod
                     break;
                 }
                 _ => {
-                    let (decoded_element, bytes_processed) = decoders::decode(&bencoded[pos..])
-                        .map_err(|err| ListDecodeError::ElementDecodeError { position: pos })?;
+                    if decoded_elements.len() == options.max_entries {
+                        return Err(ListDecodeError::TooManyElements {
+                            limit: options.max_entries,
+                        });
+                    }
+                    let (decoded_element, bytes_processed) =
+                        decoders::decode_value(&bencoded[pos..], options, depth + 1).map_err(
+                            |err| ListDecodeError::ElementDecodeError {
+                                position: pos,
+                                index: decoded_elements.len(),
+                                source: Box::new(err),
+                            },
+                        )?;
                     decoded_elements.push(decoded_element);
                     pos += bytes_processed;
                 }
@@ -48,21 +70,37 @@ pub fn decode_list(bencoded: &[u8]) -> Result<(Rc<[DataType]>, usize), ListDecod
         }
 
         if !end_of_list_found {
-            return Err(ListDecodeError::EndNotFound);
+            return Err(ListDecodeError::EndNotFound { position: pos });
         }
 
-        Ok((decoded_elements.into(), pos))
+        Ok((decoded_elements, pos))
     } else {
-        return Err(ListDecodeError::EmptyInput);
+        Err(ListDecodeError::EmptyInput)
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::types::ByteString;
+    use crate::types::{ByteString, DataType};
 
     use super::*;
 
+    fn decode_list(bencoded: &[u8]) -> Result<(Vec<DataType>, usize), ListDecodeError> {
+        decode_list_ref(bencoded, &DecodeOptions::default()).map(|(list, bytes_processed)| {
+            (
+                list.into_iter().map(DataTypeRef::into_owned).collect(),
+                bytes_processed,
+            )
+        })
+    }
+
+    fn decode_list_ref<'a>(
+        bencoded: &'a [u8],
+        options: &DecodeOptions,
+    ) -> Result<(Vec<DataTypeRef<'a>>, usize), ListDecodeError> {
+        decode_list_at_depth(bencoded, options, 0)
+    }
+
     #[test]
     fn test_empty_list() {
         let (result, bytes_processed) = decode_list("le".as_bytes()).unwrap();
@@ -245,19 +283,31 @@ mod tests {
     fn test_end_not_found_error() {
         let result = decode_list("l".as_bytes());
         assert!(result.is_err());
-        assert_eq!(result.unwrap_err(), ListDecodeError::EndNotFound);
+        assert_eq!(
+            result.unwrap_err(),
+            ListDecodeError::EndNotFound { position: 1 }
+        );
 
         let result = decode_list("lle".as_bytes());
         assert!(result.is_err());
-        assert_eq!(result.unwrap_err(), ListDecodeError::EndNotFound);
+        assert_eq!(
+            result.unwrap_err(),
+            ListDecodeError::EndNotFound { position: 3 }
+        );
 
         let result = decode_list("li123e".as_bytes());
         assert!(result.is_err());
-        assert_eq!(result.unwrap_err(), ListDecodeError::EndNotFound);
+        assert_eq!(
+            result.unwrap_err(),
+            ListDecodeError::EndNotFound { position: 6 }
+        );
 
         let result = decode_list("l4:care".as_bytes());
         assert!(result.is_err());
-        assert_eq!(result.unwrap_err(), ListDecodeError::EndNotFound);
+        assert_eq!(
+            result.unwrap_err(),
+            ListDecodeError::EndNotFound { position: 7 }
+        );
     }
 
     #[test]
@@ -5,6 +5,8 @@ pub(crate) mod dict_decoder;
 pub(crate) mod error;
 pub(crate) mod i64_decoder;
 pub(crate) mod list_decoder;
+pub(crate) mod options;
+pub(crate) mod stream_decoder;
 // endregion:   --- Public Modules
 
 // region:      --- Modules
@@ -17,4 +19,6 @@ pub(crate) use dict_decoder::*;
 pub(crate) use error::*;
 pub(crate) use i64_decoder::*;
 pub(crate) use list_decoder::*;
+pub(crate) use options::*;
+pub(crate) use stream_decoder::*;
 // endregion:   --- Flatten (private, crate, public)
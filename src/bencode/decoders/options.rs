@@ -0,0 +1,48 @@
+const DEFAULT_MAX_DEPTH: usize = 64;
+const DEFAULT_MAX_INPUT_LENGTH: usize = 128 * 1024 * 1024; // 128 MB
+const DEFAULT_MAX_STRING_LENGTH: usize = 128 * 1024 * 1024; // 128 MB
+const DEFAULT_MAX_ENTRIES: usize = 1024 * 1024;
+
+// region:      --- DecodeOptions
+/// Options shared by all the decoders.
+///
+/// The limits protect against hostile input (e.g. from peers or trackers): the defaults are
+/// generous enough for any real-world torrent.
+#[derive(Clone, Debug, PartialEq)]
+pub struct DecodeOptions {
+    /// Only accept canonically encoded input: dict keys must be sorted and unique, byte string
+    /// lengths must not have leading zeros, and nothing may follow the top-level value.
+    pub strict: bool,
+    /// The maximum nesting level of lists and dicts, the top-level container being at level 1.
+    pub max_depth: usize,
+    /// The maximum length of the whole input of `decode` (and the other top-level decoders).
+    pub max_input_length: usize,
+    /// The maximum declared length of a byte string, including dict keys.
+    pub max_string_length: usize,
+    /// The maximum number of elements of a single list or entries of a single dict.
+    pub max_entries: usize,
+}
+
+// region:      --- Constructors
+impl DecodeOptions {
+    pub fn strict() -> DecodeOptions {
+        DecodeOptions {
+            strict: true,
+            ..Default::default()
+        }
+    }
+}
+
+impl Default for DecodeOptions {
+    fn default() -> Self {
+        DecodeOptions {
+            strict: false,
+            max_depth: DEFAULT_MAX_DEPTH,
+            max_input_length: DEFAULT_MAX_INPUT_LENGTH,
+            max_string_length: DEFAULT_MAX_STRING_LENGTH,
+            max_entries: DEFAULT_MAX_ENTRIES,
+        }
+    }
+}
+// endregion:   --- Constructors
+// endregion:   --- DecodeOptions
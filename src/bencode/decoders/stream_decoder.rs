@@ -0,0 +1,364 @@
+use std::io::{self, Read};
+
+use thiserror::Error;
+
+use crate::bencode::decoders;
+use crate::bencode::decoders::{DecodeError, DecodeOptions};
+use crate::types::DataType;
+
+const READ_CHUNK_SIZE: usize = 8 * 1024; // 8 KB
+
+#[derive(Debug, PartialEq)]
+pub enum DecodeStatus {
+    /// The buffered input is a prefix of a bencoded value.
+    NeedMoreData,
+    Complete(DataType),
+}
+
+/// What the scanner expects the next byte to be part of.
+#[derive(Clone, Copy, Debug)]
+enum ScanState {
+    /// The start of a value (or a dict key), or the end of the enclosing list or dict.
+    Value,
+    /// The digits of an integer.
+    Integer,
+    /// The digits of the length of a byte string, parsed so far.
+    Length { length: usize },
+    /// The content of a byte string.
+    Content { remaining: usize },
+}
+
+// region:      --- StreamDecoder
+/// A push-style decoder that can be fed the input in chunks of any size.
+///
+/// The chunks are only scanned for the end of the current value as they arrive, and the value is
+/// decoded once it is complete, so the cost of decoding does not depend on how the input was
+/// split. Bytes following a complete value stay buffered for the next one.
+///
+/// After an error the decoder is left in an unspecified state and should be dropped.
+pub struct StreamDecoder {
+    options: DecodeOptions,
+    buffer: Vec<u8>,
+    /// The number of bytes of the current value that have been scanned.
+    scanned: usize,
+    depth: usize,
+    state: ScanState,
+}
+
+// region:      --- Constructors
+impl StreamDecoder {
+    pub fn new() -> StreamDecoder {
+        StreamDecoder::with_options(DecodeOptions::default())
+    }
+
+    pub fn with_options(options: DecodeOptions) -> StreamDecoder {
+        StreamDecoder {
+            options,
+            buffer: vec![],
+            scanned: 0,
+            depth: 0,
+            state: ScanState::Value,
+        }
+    }
+}
+
+impl Default for StreamDecoder {
+    fn default() -> Self {
+        StreamDecoder::new()
+    }
+}
+// endregion:   --- Constructors
+
+// region:      --- Getters
+impl StreamDecoder {
+    /// The input received after the last complete value.
+    pub fn get_buffered(&self) -> &[u8] {
+        &self.buffer
+    }
+}
+// endregion:   --- Getters
+
+impl StreamDecoder {
+    /// Appends the chunk to the buffered input and decodes the next value if it is complete.
+    ///
+    /// Feeding an empty chunk returns the next value that is already buffered, if any.
+    pub fn feed(&mut self, chunk: &[u8]) -> Result<DecodeStatus, DecodeError> {
+        self.buffer.extend_from_slice(chunk);
+        match self.scan() {
+            Some(end) => {
+                let (value, _) = decoders::decode_with_options(&self.buffer[..end], &self.options)?;
+                self.buffer.drain(..end);
+                self.scanned = 0;
+                self.depth = 0;
+                self.state = ScanState::Value;
+                Ok(DecodeStatus::Complete(value))
+            }
+            None if self.scanned > self.options.max_input_length => {
+                Err(DecodeError::InputTooLarge {
+                    length: self.scanned,
+                    limit: self.options.max_input_length,
+                })
+            }
+            None => Ok(DecodeStatus::NeedMoreData),
+        }
+    }
+
+    /// Scans the buffered input, returning the end of the current value once it is found.
+    ///
+    /// Invalid input, or input exceeding the limits, also ends the value, so that decoding it
+    /// reports the error without waiting for more data.
+    fn scan(&mut self) -> Option<usize> {
+        while self.scanned < self.buffer.len() {
+            let byte: u8 = self.buffer[self.scanned];
+            self.scanned += 1;
+            let value_ended: bool = match self.state {
+                ScanState::Value => match byte {
+                    b'i' => {
+                        self.state = ScanState::Integer;
+                        false
+                    }
+                    b'l' | b'd' => {
+                        self.depth += 1;
+                        self.depth > self.options.max_depth
+                    }
+                    b'0'..=b'9' => {
+                        self.state = ScanState::Length {
+                            length: (byte - b'0') as usize,
+                        };
+                        false
+                    }
+                    b'e' if self.depth > 0 => {
+                        self.depth -= 1;
+                        self.depth == 0
+                    }
+                    _ => true,
+                },
+                ScanState::Integer => {
+                    if byte == b'e' {
+                        self.state = ScanState::Value;
+                        self.depth == 0
+                    } else {
+                        false
+                    }
+                }
+                ScanState::Length { length } => match byte {
+                    b'0'..=b'9' => {
+                        self.state = ScanState::Length {
+                            length: length
+                                .saturating_mul(10)
+                                .saturating_add((byte - b'0') as usize),
+                        };
+                        false
+                    }
+                    b':' if length > self.options.max_string_length => true,
+                    b':' if length == 0 => {
+                        self.state = ScanState::Value;
+                        self.depth == 0
+                    }
+                    b':' => {
+                        self.state = ScanState::Content { remaining: length };
+                        false
+                    }
+                    _ => true,
+                },
+                ScanState::Content { remaining } => {
+                    // Skips the whole buffered part of the content at once.
+                    let available: usize = self.buffer.len() - self.scanned + 1;
+                    let skipped: usize = remaining.min(available);
+                    self.scanned += skipped - 1;
+                    if skipped == remaining {
+                        self.state = ScanState::Value;
+                        self.depth == 0
+                    } else {
+                        self.state = ScanState::Content {
+                            remaining: remaining - skipped,
+                        };
+                        false
+                    }
+                }
+            };
+            if value_ended {
+                return Some(self.scanned);
+            }
+        }
+        None
+    }
+}
+// endregion:   --- StreamDecoder
+
+// region:      --- BencodeReader
+#[derive(Error, Debug)]
+pub enum ReadDecodeError {
+    #[error("Could not read the input.")]
+    Io(#[from] io::Error),
+    #[error(transparent)]
+    DecodeError(#[from] DecodeError),
+    #[error("The input ended in the middle of a bencoded value.")]
+    UnexpectedEof,
+}
+
+/// Decodes bencoded values from a reader as its data arrives.
+pub struct BencodeReader<R: Read> {
+    reader: R,
+    decoder: StreamDecoder,
+}
+
+// region:      --- Constructors
+impl<R: Read> BencodeReader<R> {
+    pub fn with_options(reader: R, options: DecodeOptions) -> BencodeReader<R> {
+        BencodeReader {
+            reader,
+            decoder: StreamDecoder::with_options(options),
+        }
+    }
+}
+// endregion:   --- Constructors
+
+impl<R: Read> BencodeReader<R> {
+    /// Reads the next value, or `None` if the reader is exhausted right after the previous one.
+    pub fn read_value(&mut self) -> Result<Option<DataType>, ReadDecodeError> {
+        let mut chunk: [u8; READ_CHUNK_SIZE] = [0; READ_CHUNK_SIZE];
+        let mut status: DecodeStatus = self.decoder.feed(&[])?;
+        loop {
+            if let DecodeStatus::Complete(value) = status {
+                return Ok(Some(value));
+            }
+            let bytes_read: usize = match self.reader.read(&mut chunk) {
+                Ok(bytes_read) => bytes_read,
+                Err(err) if err.kind() == io::ErrorKind::Interrupted => continue,
+                Err(err) => return Err(err.into()),
+            };
+            if bytes_read == 0 {
+                return if self.decoder.get_buffered().is_empty() {
+                    Ok(None)
+                } else {
+                    Err(ReadDecodeError::UnexpectedEof)
+                };
+            }
+            status = self.decoder.feed(&chunk[..bytes_read])?;
+        }
+    }
+}
+// endregion:   --- BencodeReader
+
+/// Reads and decodes the first bencoded value of the reader. Whatever follows it is not checked.
+pub fn decode_from_reader<R: Read>(
+    reader: R,
+    options: &DecodeOptions,
+) -> Result<DataType, ReadDecodeError> {
+    BencodeReader::with_options(reader, options.clone())
+        .read_value()?
+        .ok_or(ReadDecodeError::UnexpectedEof)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Reads at most `chunk_size` bytes at once.
+    struct ChunkedReader<'a> {
+        data: &'a [u8],
+        chunk_size: usize,
+    }
+
+    impl Read for ChunkedReader<'_> {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            let length = self.chunk_size.min(buf.len()).min(self.data.len());
+            buf[..length].copy_from_slice(&self.data[..length]);
+            self.data = &self.data[length..];
+            Ok(length)
+        }
+    }
+
+    #[test]
+    fn test_feed_byte_by_byte() {
+        let bencoded = "d4:infod6:lengthi92063e4:name10:sample.txtee".as_bytes();
+        let mut decoder = StreamDecoder::new();
+        for byte in &bencoded[..bencoded.len() - 1] {
+            assert_eq!(decoder.feed(&[*byte]).unwrap(), DecodeStatus::NeedMoreData);
+        }
+        assert_eq!(
+            decoder.feed(&bencoded[bencoded.len() - 1..]).unwrap(),
+            DecodeStatus::Complete(decoders::decode(bencoded).unwrap().0)
+        );
+        assert!(decoder.get_buffered().is_empty());
+    }
+
+    #[test]
+    fn test_feed_several_values_at_once() {
+        let mut decoder = StreamDecoder::new();
+        assert_eq!(
+            decoder.feed("i1e0:l3:ab".as_bytes()).unwrap(),
+            DecodeStatus::Complete(DataType::Integer(1))
+        );
+        assert_eq!(
+            decoder.feed(&[]).unwrap(),
+            DecodeStatus::Complete(DataType::ByteString("".into()))
+        );
+        assert_eq!(decoder.feed(&[]).unwrap(), DecodeStatus::NeedMoreData);
+        assert_eq!(
+            decoder.feed("cexyz".as_bytes()).unwrap(),
+            DecodeStatus::Complete(DataType::List(vec![DataType::ByteString("abc".into())]))
+        );
+        assert_eq!(decoder.get_buffered(), "xyz".as_bytes());
+    }
+
+    #[test]
+    fn test_invalid_input_is_reported_without_more_data() {
+        let mut decoder = StreamDecoder::new();
+        let result = decoder.feed("li1ex".as_bytes());
+        assert_eq!(result.unwrap_err().position(), 4);
+    }
+
+    #[test]
+    fn test_limits_are_enforced_without_more_data() {
+        let mut decoder = StreamDecoder::new();
+        let result = decoder.feed("99999999999999:".as_bytes());
+        assert!(matches!(
+            result.unwrap_err(),
+            DecodeError::ByteStringDecodeError(
+                decoders::ByteStringDecodeError::LengthLimitExceeded { .. }
+            )
+        ));
+
+        let mut decoder = StreamDecoder::with_options(DecodeOptions {
+            max_depth: 2,
+            ..Default::default()
+        });
+        let result = decoder.feed("lll".as_bytes());
+        assert_eq!(
+            result.unwrap_err().root_cause(),
+            &DecodeError::DepthLimitExceeded { limit: 2 }
+        );
+    }
+
+    #[test]
+    fn test_reader() {
+        let bencoded = "d3:keyl5:helloi42eee4:next".as_bytes();
+        let mut reader = BencodeReader::with_options(
+            ChunkedReader {
+                data: bencoded,
+                chunk_size: 3,
+            },
+            DecodeOptions::default(),
+        );
+        assert_eq!(
+            reader.read_value().unwrap(),
+            Some(decoders::decode(bencoded).unwrap().0)
+        );
+        assert_eq!(
+            reader.read_value().unwrap(),
+            Some(DataType::ByteString("next".into()))
+        );
+        assert_eq!(reader.read_value().unwrap(), None);
+    }
+
+    #[test]
+    fn test_reader_unexpected_eof() {
+        let result = decode_from_reader("li1e".as_bytes(), &DecodeOptions::default());
+        assert!(matches!(result, Err(ReadDecodeError::UnexpectedEof)));
+
+        let result = decode_from_reader("".as_bytes(), &DecodeOptions::default());
+        assert!(matches!(result, Err(ReadDecodeError::UnexpectedEof)));
+    }
+}
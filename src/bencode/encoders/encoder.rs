@@ -1,53 +1,171 @@
-use std::{collections::BTreeMap, rc::Rc};
+use std::{
+    collections::BTreeMap,
+    io::{self, Write},
+    rc::Rc,
+};
 
-pub fn bencode_i64(value: i64) -> Rc<[u8]> {
-    let mut v: Vec<u8> = vec![b'i'];
-    v.extend_from_slice(value.to_string().as_bytes());
-    v.push(b'e');
-    v.into()
+use crate::types::{ByteString, DataType};
+
+// region:      --- Encoder
+/// Writes bencoded values directly into a `Write` sink, without intermediate buffers.
+///
+/// Writes are not buffered, so wrap unbuffered sinks (files, sockets) in a `BufWriter`.
+pub struct Encoder<W: Write> {
+    writer: W,
+}
+
+// region:      --- Constructors
+impl<W: Write> Encoder<W> {
+    pub fn new(writer: W) -> Encoder<W> {
+        Encoder { writer }
+    }
+
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+}
+// endregion:   --- Constructors
+
+impl<W: Write> Encoder<W> {
+    pub fn encode_i64(&mut self, value: i64) -> io::Result<()> {
+        write!(self.writer, "i{value}e")
+    }
+
+    pub fn encode_byte_string(&mut self, value: &[u8]) -> io::Result<()> {
+        write!(self.writer, "{}:", value.len())?;
+        self.writer.write_all(value)
+    }
+
+    pub fn encode_list(&mut self, list: &[DataType]) -> io::Result<()> {
+        self.writer.write_all(b"l")?;
+        for element in list {
+            self.encode(element)?;
+        }
+        self.writer.write_all(b"e")
+    }
+
+    pub fn encode_dict(&mut self, dict: &BTreeMap<ByteString, DataType>) -> io::Result<()> {
+        self.writer.write_all(b"d")?;
+        for (key, value) in dict {
+            self.encode_byte_string(key.get_data())?;
+            self.encode(value)?;
+        }
+        self.writer.write_all(b"e")
+    }
+
+    /// Writes an already bencoded value as is, e.g. to preserve the exact bytes of a dict.
+    pub fn encode_raw(&mut self, bencoded: &[u8]) -> io::Result<()> {
+        self.writer.write_all(bencoded)
+    }
+
+    pub fn encode(&mut self, value: &DataType) -> io::Result<()> {
+        match value {
+            DataType::Integer(num) => self.encode_i64(*num),
+            DataType::ByteString(byte_str) => self.encode_byte_string(byte_str.get_data()),
+            DataType::List(list) => self.encode_list(list),
+            DataType::Dict(dict) => self.encode_dict(dict),
+        }
+    }
 }
+// endregion:   --- Encoder
 
-pub fn bencode_byte_string(value: &crate::types::byte_string::ByteString) -> Rc<[u8]> {
-    let mut result: Vec<u8> = vec![];
-    result.extend_from_slice(value.get_data().len().to_string().as_bytes());
-    result.push(b':');
-    result.extend_from_slice(value.get_data());
-    result.into()
+/// Encodes the value into a vector allocated once with the exact encoded length.
+pub fn encode_to_vec(value: &DataType) -> Vec<u8> {
+    encode_with_capacity(encoded_len(value), |encoder| encoder.encode(value))
 }
 
-pub fn bencode_list(list: &Vec<crate::types::data_type::DataType>) -> Rc<[u8]> {
-    let mut result: Vec<u8> = vec![b'l'];
-    for element in list {
-        result.extend_from_slice(&bencode(element));
+/// The length of the bencoded representation of the value.
+pub fn encoded_len(value: &DataType) -> usize {
+    match value {
+        DataType::Integer(num) => decimal_len(*num) + 2,
+        DataType::ByteString(byte_str) => byte_string_encoded_len(byte_str.get_data()),
+        DataType::List(list) => list.iter().map(encoded_len).sum::<usize>() + 2,
+        DataType::Dict(dict) => {
+            dict.iter()
+                .map(|(key, value)| byte_string_encoded_len(key.get_data()) + encoded_len(value))
+                .sum::<usize>()
+                + 2
+        }
     }
-    result.push(b'e');
-    result.into()
 }
 
-pub fn bencode_string(value: &str) -> Rc<[u8]> {
-    let bytes = value.as_bytes();
-    let mut result: Vec<u8> = vec![];
-    result.extend_from_slice(&bytes.len().to_string().as_bytes());
-    result.push(b':');
-    result.extend_from_slice(bytes);
-    result.into()
+fn byte_string_encoded_len(value: &[u8]) -> usize {
+    decimal_len(value.len() as i64) + 1 + value.len()
 }
 
-pub fn bencode_dict(dict: &BTreeMap<String, crate::types::data_type::DataType>) -> Rc<[u8]> {
-    let mut result: Vec<u8> = vec![b'd'];
-    for (key, value) in dict {
-        result.extend_from_slice(&bencode_string(key));
-        result.extend_from_slice(&bencode(value));
+/// The number of characters of the decimal representation of the number, including the sign.
+fn decimal_len(value: i64) -> usize {
+    let digits: usize = value
+        .unsigned_abs()
+        .checked_ilog10()
+        .map_or(1, |log| log as usize + 1);
+    if value < 0 {
+        digits + 1
+    } else {
+        digits
     }
-    result.push(b'e');
-    result.into()
 }
 
-pub fn bencode(value: &crate::types::data_type::DataType) -> Rc<[u8]> {
-    match value {
-        crate::types::data_type::DataType::Integer(num) => bencode_i64(*num),
-        crate::types::data_type::DataType::ByteString(byte_str) => bencode_byte_string(byte_str),
-        crate::types::data_type::DataType::List(list) => bencode_list(list),
-        crate::types::data_type::DataType::Dict(dict) => bencode_dict(dict),
+fn encode_with_capacity<F>(capacity: usize, encode: F) -> Vec<u8>
+where
+    F: FnOnce(&mut Encoder<&mut Vec<u8>>) -> io::Result<()>,
+{
+    let mut result: Vec<u8> = Vec::with_capacity(capacity);
+    encode(&mut Encoder::new(&mut result)).expect("writing into a Vec can not fail");
+    result
+}
+
+pub fn bencode(value: &DataType) -> Rc<[u8]> {
+    encode_to_vec(value).into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_dict_binary_keys_are_preserved() {
+        let dict: BTreeMap<ByteString, DataType> = BTreeMap::from_iter([
+            (
+                ByteString::from([0xFF, 0x00].as_slice()),
+                DataType::Integer(1),
+            ),
+            ("a".into(), DataType::Integer(2)),
+        ]);
+        assert_eq!(
+            bencode(&DataType::Dict(dict)).as_ref(),
+            &[
+                b'd', b'1', b':', b'a', b'i', b'2', b'e', b'2', b':', 0xFF, 0x00, b'i', b'1', b'e',
+                b'e'
+            ]
+        );
+    }
+
+    #[test]
+    fn test_encoder_writes_into_sink() {
+        let value = DataType::Dict(BTreeMap::from_iter([
+            (
+                "list".into(),
+                DataType::List(vec![
+                    DataType::Integer(-42),
+                    DataType::ByteString("".into()),
+                    DataType::List(vec![]),
+                ]),
+            ),
+            ("name".into(), DataType::ByteString("sample.txt".into())),
+        ]));
+        let mut encoder = Encoder::new(io::Cursor::new(vec![]));
+        encoder.encode(&value).unwrap();
+        let expected: &[u8] = b"d4:listli-42e0:lee4:name10:sample.txte";
+        assert_eq!(encoder.into_inner().into_inner(), expected);
+        assert_eq!(encode_to_vec(&value), expected);
+        assert_eq!(encoded_len(&value), expected.len());
+    }
+
+    #[test]
+    fn test_decimal_len() {
+        for value in [0, 9, 10, -1, -10, 99999, i64::MAX, i64::MIN] {
+            assert_eq!(decimal_len(value), value.to_string().len());
+        }
     }
 }
@@ -1,9 +1,12 @@
 // region:      --- Public Modules
 pub(crate) mod decoders;
 pub(crate) mod encoders;
+pub(crate) mod serialization;
 // endregion:   --- Public Modules
 
 // region:      --- Modules
+#[cfg(test)]
+mod round_trip_tests;
 // endregion:   --- Modules
 
 // region:      --- Flatten (private, crate, public)
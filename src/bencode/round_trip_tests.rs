@@ -0,0 +1,121 @@
+//! Property-based tests over generated `DataType` trees, checking that the encoders and the
+//! decoders agree with each other and that no input makes the decoders panic.
+
+use std::collections::BTreeMap;
+
+use proptest::collection::{btree_map, vec};
+use proptest::prelude::*;
+
+use crate::bencode::decoders::{self, DecodeOptions, DecodeStatus, StreamDecoder};
+use crate::bencode::encoders;
+use crate::types::{self, ByteString, DataType};
+
+fn byte_string() -> impl Strategy<Value = ByteString> {
+    vec(any::<u8>(), 0..16).prop_map(|bytes| ByteString::from(bytes.as_slice()))
+}
+
+fn data_type() -> impl Strategy<Value = DataType> {
+    let leaf = prop_oneof![
+        any::<i64>().prop_map(DataType::Integer),
+        byte_string().prop_map(DataType::ByteString),
+    ];
+    leaf.prop_recursive(4, 64, 8, |inner| {
+        prop_oneof![
+            vec(inner.clone(), 0..8).prop_map(DataType::List),
+            btree_map(byte_string(), inner, 0..8)
+                .prop_map(|dict: BTreeMap<ByteString, DataType>| DataType::Dict(dict)),
+        ]
+    })
+}
+
+/// An encoded value with a few random bytes overwritten, inserted or cut off.
+fn mutated_encoding() -> impl Strategy<Value = Vec<u8>> {
+    (
+        data_type(),
+        vec((any::<prop::sample::Index>(), any::<u8>()), 0..4),
+        any::<prop::sample::Index>(),
+    )
+        .prop_map(|(value, edits, cut)| {
+            let mut bytes: Vec<u8> = encoders::encode_to_vec(&value);
+            for (index, byte) in edits {
+                if bytes.is_empty() {
+                    bytes.push(byte);
+                } else if byte % 2 == 0 {
+                    let i = index.index(bytes.len());
+                    bytes[i] = byte;
+                } else {
+                    let i = index.index(bytes.len() + 1);
+                    bytes.insert(i, byte);
+                }
+            }
+            let len = cut.index(bytes.len() + 1);
+            bytes.truncate(len);
+            bytes
+        })
+}
+
+proptest! {
+    #[test]
+    fn decode_inverts_encode(value in data_type()) {
+        let encoded: Vec<u8> = encoders::encode_to_vec(&value);
+        let (decoded, length) = decoders::decode(&encoded).unwrap();
+        prop_assert_eq!(&decoded, &value);
+        prop_assert_eq!(length, encoded.len());
+    }
+
+    #[test]
+    fn strict_decode_accepts_encoded(value in data_type()) {
+        let encoded: Vec<u8> = encoders::encode_to_vec(&value);
+        let (decoded, _) = decoders::decode_with_options(&encoded, &DecodeOptions::strict()).unwrap();
+        prop_assert_eq!(decoded, value);
+    }
+
+    #[test]
+    fn decode_ref_matches_decode(value in data_type()) {
+        let encoded: Vec<u8> = encoders::encode_to_vec(&value);
+        let (decoded, _) = decoders::decode_ref(&encoded, &DecodeOptions::default()).unwrap();
+        prop_assert_eq!(decoded.into_owned(), value);
+    }
+
+    #[test]
+    fn encoded_len_matches_encoding(value in data_type()) {
+        prop_assert_eq!(encoders::encoded_len(&value), encoders::bencode(&value).len());
+        prop_assert_eq!(&encoders::encode_to_vec(&value)[..], &encoders::bencode(&value)[..]);
+    }
+
+    #[test]
+    fn stream_decoder_handles_any_chunking(value in data_type(), chunk_size in 1usize..16) {
+        let encoded: Vec<u8> = encoders::encode_to_vec(&value);
+        let mut decoder = StreamDecoder::new();
+        let mut decoded: Option<DataType> = None;
+        for chunk in encoded.chunks(chunk_size) {
+            prop_assert!(decoded.is_none(), "a value was completed before its last chunk");
+            if let DecodeStatus::Complete(value) = decoder.feed(chunk).unwrap() {
+                decoded = Some(value);
+            }
+        }
+        prop_assert_eq!(decoded, Some(value));
+        prop_assert!(decoder.get_buffered().is_empty());
+    }
+
+    #[test]
+    fn lossless_json_round_trips(value in data_type()) {
+        let json = types::to_json_lossless(&value);
+        prop_assert_eq!(types::from_json(&json).unwrap(), value);
+    }
+
+    #[test]
+    fn decode_does_not_panic_on_arbitrary_bytes(bytes in vec(any::<u8>(), 0..64)) {
+        let _ = decoders::decode(&bytes);
+        let _ = decoders::decode_with_options(&bytes, &DecodeOptions::strict());
+        let _ = StreamDecoder::new().feed(&bytes);
+    }
+
+    #[test]
+    fn decode_does_not_panic_on_mutated_encoding(bytes in mutated_encoding()) {
+        let _ = decoders::decode(&bytes);
+        let _ = decoders::decode_with_options(&bytes, &DecodeOptions::strict());
+        let _ = decoders::decode_dict_with_spans(&bytes);
+        let _ = StreamDecoder::new().feed(&bytes);
+    }
+}
@@ -0,0 +1,409 @@
+use std::collections::btree_map;
+
+use serde::de::{self, DeserializeSeed, EnumAccess, MapAccess, SeqAccess, VariantAccess, Visitor};
+use serde::{forward_to_deserialize_any, Deserialize};
+
+use crate::bencode::decoders;
+use crate::bencode::serialization::SerdeError;
+use crate::types::{ByteString, DataType};
+
+/// Decodes a single bencoded value and deserializes it into `T`.
+///
+/// The whole input must be consumed by the value, trailing bytes are reported as an error.
+pub fn from_bytes<T>(bencoded: &[u8]) -> Result<T, SerdeError>
+where
+    T: for<'de> Deserialize<'de>,
+{
+    let (decoded, bytes_processed) = decoders::decode(bencoded)?;
+    if bytes_processed < bencoded.len() {
+        return Err(SerdeError::TrailingData {
+            count: bencoded.len() - bytes_processed,
+        });
+    }
+    from_data_type(&decoded)
+}
+
+/// Deserializes `T` from an already decoded value.
+pub fn from_data_type<'de, T>(value: &'de DataType) -> Result<T, SerdeError>
+where
+    T: Deserialize<'de>,
+{
+    T::deserialize(DataTypeDeserializer::new(value))
+}
+
+// region:      --- DataTypeDeserializer
+pub struct DataTypeDeserializer<'de> {
+    value: &'de DataType,
+}
+
+// region:      --- Constructors
+impl<'de> DataTypeDeserializer<'de> {
+    pub fn new(value: &'de DataType) -> Self {
+        DataTypeDeserializer { value }
+    }
+}
+// endregion:   --- Constructors
+
+// region:      --- Traits impl
+impl<'de> de::Deserializer<'de> for DataTypeDeserializer<'de> {
+    type Error = SerdeError;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, SerdeError> {
+        match self.value {
+            DataType::Integer(number) => visitor.visit_i64(*number),
+            DataType::ByteString(byte_str) => visit_byte_string(byte_str, visitor),
+            DataType::List(list) => visitor.visit_seq(ListAccess { iter: list.iter() }),
+            DataType::Dict(dict) => visitor.visit_map(DictAccess {
+                iter: dict.iter(),
+                value: None,
+            }),
+        }
+    }
+
+    /// Bencode has no booleans, so they are represented by integers (e.g. the 'private' flag).
+    fn deserialize_bool<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, SerdeError> {
+        match self.value {
+            DataType::Integer(number) => visitor.visit_bool(*number != 0),
+            _ => self.deserialize_any(visitor),
+        }
+    }
+
+    fn deserialize_bytes<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, SerdeError> {
+        match self.value {
+            DataType::ByteString(byte_str) => visitor.visit_borrowed_bytes(byte_str.get_data()),
+            _ => self.deserialize_any(visitor),
+        }
+    }
+
+    fn deserialize_byte_buf<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, SerdeError> {
+        self.deserialize_bytes(visitor)
+    }
+
+    /// Bencode has no nulls: a present value is always `Some`, and missing struct fields of
+    /// `Option` types are `None`.
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, SerdeError> {
+        visitor.visit_some(self)
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, SerdeError> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, SerdeError> {
+        match self.value {
+            DataType::ByteString(variant) => visitor.visit_enum(EnumDeserializer {
+                variant,
+                value: None,
+            }),
+            DataType::Dict(dict) if dict.len() == 1 => {
+                let (variant, value) = dict.iter().next().unwrap();
+                visitor.visit_enum(EnumDeserializer {
+                    variant,
+                    value: Some(value),
+                })
+            }
+            _ => Err(de::Error::custom(
+                "expected a string or a dict with a single key for an enum",
+            )),
+        }
+    }
+
+    fn deserialize_ignored_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, SerdeError> {
+        visitor.visit_unit()
+    }
+
+    forward_to_deserialize_any! {
+        i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        unit unit_struct seq tuple tuple_struct map struct identifier
+    }
+}
+// endregion:   --- Traits impl
+// endregion:   --- DataTypeDeserializer
+
+// region:      --- KeyDeserializer
+/// Deserializes dict keys, which are always byte strings.
+struct KeyDeserializer<'de> {
+    key: &'de ByteString,
+}
+
+impl<'de> de::Deserializer<'de> for KeyDeserializer<'de> {
+    type Error = SerdeError;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, SerdeError> {
+        visit_byte_string(self.key, visitor)
+    }
+
+    fn deserialize_bytes<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, SerdeError> {
+        visitor.visit_borrowed_bytes(self.key.get_data())
+    }
+
+    fn deserialize_byte_buf<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, SerdeError> {
+        self.deserialize_bytes(visitor)
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, SerdeError> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        option unit unit_struct seq tuple tuple_struct map struct enum identifier ignored_any
+    }
+}
+// endregion:   --- KeyDeserializer
+
+// region:      --- Accessors
+struct ListAccess<'de> {
+    iter: std::slice::Iter<'de, DataType>,
+}
+
+impl<'de> SeqAccess<'de> for ListAccess<'de> {
+    type Error = SerdeError;
+
+    fn next_element_seed<T: DeserializeSeed<'de>>(
+        &mut self,
+        seed: T,
+    ) -> Result<Option<T::Value>, SerdeError> {
+        self.iter
+            .next()
+            .map(|value| seed.deserialize(DataTypeDeserializer::new(value)))
+            .transpose()
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.iter.len())
+    }
+}
+
+struct DictAccess<'de> {
+    iter: btree_map::Iter<'de, ByteString, DataType>,
+    value: Option<&'de DataType>,
+}
+
+impl<'de> MapAccess<'de> for DictAccess<'de> {
+    type Error = SerdeError;
+
+    fn next_key_seed<K: DeserializeSeed<'de>>(
+        &mut self,
+        seed: K,
+    ) -> Result<Option<K::Value>, SerdeError> {
+        match self.iter.next() {
+            Some((key, value)) => {
+                self.value = Some(value);
+                seed.deserialize(KeyDeserializer { key }).map(Some)
+            }
+            None => Ok(None),
+        }
+    }
+
+    fn next_value_seed<V: DeserializeSeed<'de>>(
+        &mut self,
+        seed: V,
+    ) -> Result<V::Value, SerdeError> {
+        let value = self.value.take().ok_or_else(|| {
+            SerdeError::Message("a dict value was requested before its key".to_owned())
+        })?;
+        seed.deserialize(DataTypeDeserializer::new(value))
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.iter.len())
+    }
+}
+
+/// Enums are represented either by the name of a unit variant, or by a dict with a single key,
+/// mapping the name of the variant to its content.
+struct EnumDeserializer<'de> {
+    variant: &'de ByteString,
+    value: Option<&'de DataType>,
+}
+
+impl<'de> EnumAccess<'de> for EnumDeserializer<'de> {
+    type Error = SerdeError;
+    type Variant = VariantDeserializer<'de>;
+
+    fn variant_seed<V: DeserializeSeed<'de>>(
+        self,
+        seed: V,
+    ) -> Result<(V::Value, Self::Variant), SerdeError> {
+        let variant = seed.deserialize(KeyDeserializer { key: self.variant })?;
+        Ok((variant, VariantDeserializer { value: self.value }))
+    }
+}
+
+struct VariantDeserializer<'de> {
+    value: Option<&'de DataType>,
+}
+
+impl<'de> VariantAccess<'de> for VariantDeserializer<'de> {
+    type Error = SerdeError;
+
+    fn unit_variant(self) -> Result<(), SerdeError> {
+        match self.value {
+            None => Ok(()),
+            Some(_) => Err(de::Error::custom("expected a unit variant")),
+        }
+    }
+
+    fn newtype_variant_seed<T: DeserializeSeed<'de>>(
+        self,
+        seed: T,
+    ) -> Result<T::Value, SerdeError> {
+        let value = self
+            .value
+            .ok_or_else(|| SerdeError::Message("expected a newtype variant".to_owned()))?;
+        seed.deserialize(DataTypeDeserializer::new(value))
+    }
+
+    fn tuple_variant<V: Visitor<'de>>(
+        self,
+        _len: usize,
+        visitor: V,
+    ) -> Result<V::Value, SerdeError> {
+        let value = self
+            .value
+            .ok_or_else(|| SerdeError::Message("expected a tuple variant".to_owned()))?;
+        de::Deserializer::deserialize_seq(DataTypeDeserializer::new(value), visitor)
+    }
+
+    fn struct_variant<V: Visitor<'de>>(
+        self,
+        _fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, SerdeError> {
+        let value = self
+            .value
+            .ok_or_else(|| SerdeError::Message("expected a struct variant".to_owned()))?;
+        de::Deserializer::deserialize_map(DataTypeDeserializer::new(value), visitor)
+    }
+}
+// endregion:   --- Accessors
+
+/// Byte strings are visited as strings when they are valid UTF-8, and as bytes otherwise.
+fn visit_byte_string<'de, V: Visitor<'de>>(
+    byte_str: &'de ByteString,
+    visitor: V,
+) -> Result<V::Value, SerdeError> {
+    match std::str::from_utf8(byte_str.get_data()) {
+        Ok(str) => visitor.visit_borrowed_str(str),
+        Err(_) => visitor.visit_borrowed_bytes(byte_str.get_data()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use serde_bytes::ByteBuf;
+
+    use super::*;
+
+    #[derive(Deserialize, Debug, PartialEq)]
+    struct File {
+        length: u64,
+        path: Vec<String>,
+    }
+
+    #[derive(Deserialize, Debug, PartialEq)]
+    struct Info {
+        name: String,
+        #[serde(rename = "piece length")]
+        piece_length: u32,
+        pieces: ByteBuf,
+        length: Option<u64>,
+        files: Option<Vec<File>>,
+        #[serde(default)]
+        private: bool,
+    }
+
+    #[test]
+    fn test_struct() {
+        let bencoded: &[u8] = &[
+            b"d5:filesld6:lengthi3e4:pathl1:a1:beee4:name4:root12:piece lengthi16e6:pieces2:"
+                .as_slice(),
+            &[0xFF, 0x00],
+            b"7:privatei1ee",
+        ]
+        .concat();
+        let info: Info = from_bytes(bencoded).unwrap();
+        assert_eq!(
+            info,
+            Info {
+                name: "root".to_owned(),
+                piece_length: 16,
+                pieces: ByteBuf::from(vec![0xFF, 0x00]),
+                length: None,
+                files: Some(vec![File {
+                    length: 3,
+                    path: vec!["a".to_owned(), "b".to_owned()]
+                }]),
+                private: true,
+            }
+        );
+    }
+
+    #[test]
+    fn test_missing_field() {
+        let result: Result<File, SerdeError> = from_bytes(b"d6:lengthi3ee");
+        assert_eq!(
+            result.unwrap_err(),
+            SerdeError::Message("missing field `path`".to_owned())
+        );
+    }
+
+    #[test]
+    fn test_invalid_type() {
+        let result: Result<File, SerdeError> = from_bytes(b"d6:length1:a4:pathlee");
+        assert!(matches!(result.unwrap_err(), SerdeError::Message(_)));
+    }
+
+    #[test]
+    fn test_integer_out_of_range() {
+        let result: Result<u8, SerdeError> = from_bytes(b"i256e");
+        assert!(matches!(result.unwrap_err(), SerdeError::Message(_)));
+    }
+
+    #[test]
+    fn test_trailing_data() {
+        let result: Result<i64, SerdeError> = from_bytes(b"i1exyz");
+        assert_eq!(result.unwrap_err(), SerdeError::TrailingData { count: 3 });
+    }
+
+    #[test]
+    fn test_binary_keys() {
+        let bencoded: &[u8] = &[b"d2:".as_slice(), &[0xFF, 0x00], b"i1ee"].concat();
+        let dict: BTreeMap<ByteBuf, i64> = from_bytes(bencoded).unwrap();
+        assert_eq!(
+            dict,
+            BTreeMap::from_iter([(ByteBuf::from(vec![0xFF, 0x00]), 1)])
+        );
+    }
+
+    #[derive(Deserialize, Debug, PartialEq)]
+    enum Event {
+        Started,
+        Progress { downloaded: u64 },
+    }
+
+    #[test]
+    fn test_enum() {
+        assert_eq!(from_bytes::<Event>(b"7:Started").unwrap(), Event::Started);
+        assert_eq!(
+            from_bytes::<Event>(b"d8:Progressd10:downloadedi7eee").unwrap(),
+            Event::Progress { downloaded: 7 }
+        );
+    }
+}
@@ -0,0 +1,33 @@
+use std::fmt::Display;
+
+use thiserror::Error;
+
+use crate::bencode::decoders;
+
+#[derive(Error, Debug, PartialEq)]
+pub enum SerdeError {
+    #[error("{0}")]
+    Message(String),
+    #[error("Could not decode the bencoded input.")]
+    DecodeError(#[from] decoders::DecodeError),
+    #[error("Found {count} bytes of trailing data after the bencoded value.")]
+    TrailingData { count: usize },
+    #[error("Bencode has no representation for {0}.")]
+    UnsupportedType(&'static str),
+    #[error("Bencoded dict keys must be strings, but got {0}.")]
+    InvalidKey(&'static str),
+    #[error("The integer {0} does not fit into a bencoded (64-bit signed) integer.")]
+    IntegerOutOfRange(u64),
+}
+
+impl serde::de::Error for SerdeError {
+    fn custom<T: Display>(msg: T) -> Self {
+        SerdeError::Message(msg.to_string())
+    }
+}
+
+impl serde::ser::Error for SerdeError {
+    fn custom<T: Display>(msg: T) -> Self {
+        SerdeError::Message(msg.to_string())
+    }
+}
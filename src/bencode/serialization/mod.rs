@@ -0,0 +1,14 @@
+// region:      --- Public Modules
+pub(crate) mod de;
+pub(crate) mod error;
+pub(crate) mod ser;
+// endregion:   --- Public Modules
+
+// region:      --- Modules
+// endregion:   --- Modules
+
+// region:      --- Flatten (private, crate, public)
+pub(crate) use de::*;
+pub(crate) use error::*;
+pub(crate) use ser::*;
+// endregion:   --- Flatten (private, crate, public)
@@ -0,0 +1,427 @@
+use std::{collections::BTreeMap, rc::Rc};
+
+use serde::ser::{self, Serialize};
+
+use crate::bencode::encoders;
+use crate::bencode::serialization::SerdeError;
+use crate::types::{ByteString, DataType};
+
+/// Serializes `value` and bencodes the result.
+pub fn to_bytes<T: Serialize + ?Sized>(value: &T) -> Result<Rc<[u8]>, SerdeError> {
+    to_data_type(value).map(|data| encoders::bencode(&data))
+}
+
+/// Serializes `value` into a bencode value.
+///
+/// Dict keys are kept sorted by `DataType::Dict`, so the result is always canonically encoded.
+pub fn to_data_type<T: Serialize + ?Sized>(value: &T) -> Result<DataType, SerdeError> {
+    value
+        .serialize(DataTypeSerializer)?
+        .ok_or(SerdeError::UnsupportedType(
+            "a missing value at the top level",
+        ))
+}
+
+// region:      --- DataTypeSerializer
+/// Serializes values into bencode values.
+///
+/// Bencode has no nulls, so `None` and unit values serialize to nothing: they are left out of
+/// dicts and lists instead.
+pub struct DataTypeSerializer;
+
+// region:      --- Traits impl
+impl ser::Serializer for DataTypeSerializer {
+    type Ok = Option<DataType>;
+    type Error = SerdeError;
+
+    type SerializeSeq = ListSerializer;
+    type SerializeTuple = ListSerializer;
+    type SerializeTupleStruct = ListSerializer;
+    type SerializeTupleVariant = VariantSerializer<ListSerializer>;
+    type SerializeMap = DictSerializer;
+    type SerializeStruct = DictSerializer;
+    type SerializeStructVariant = VariantSerializer<DictSerializer>;
+
+    fn serialize_bool(self, v: bool) -> Result<Self::Ok, SerdeError> {
+        self.serialize_i64(v as i64)
+    }
+
+    fn serialize_i8(self, v: i8) -> Result<Self::Ok, SerdeError> {
+        self.serialize_i64(v.into())
+    }
+
+    fn serialize_i16(self, v: i16) -> Result<Self::Ok, SerdeError> {
+        self.serialize_i64(v.into())
+    }
+
+    fn serialize_i32(self, v: i32) -> Result<Self::Ok, SerdeError> {
+        self.serialize_i64(v.into())
+    }
+
+    fn serialize_i64(self, v: i64) -> Result<Self::Ok, SerdeError> {
+        Ok(Some(DataType::Integer(v)))
+    }
+
+    fn serialize_u8(self, v: u8) -> Result<Self::Ok, SerdeError> {
+        self.serialize_i64(v.into())
+    }
+
+    fn serialize_u16(self, v: u16) -> Result<Self::Ok, SerdeError> {
+        self.serialize_i64(v.into())
+    }
+
+    fn serialize_u32(self, v: u32) -> Result<Self::Ok, SerdeError> {
+        self.serialize_i64(v.into())
+    }
+
+    fn serialize_u64(self, v: u64) -> Result<Self::Ok, SerdeError> {
+        i64::try_from(v)
+            .map_err(|_| SerdeError::IntegerOutOfRange(v))
+            .and_then(|v| self.serialize_i64(v))
+    }
+
+    fn serialize_f32(self, _v: f32) -> Result<Self::Ok, SerdeError> {
+        Err(SerdeError::UnsupportedType("floating point numbers"))
+    }
+
+    fn serialize_f64(self, _v: f64) -> Result<Self::Ok, SerdeError> {
+        Err(SerdeError::UnsupportedType("floating point numbers"))
+    }
+
+    fn serialize_char(self, v: char) -> Result<Self::Ok, SerdeError> {
+        self.serialize_str(v.encode_utf8(&mut [0; 4]))
+    }
+
+    fn serialize_str(self, v: &str) -> Result<Self::Ok, SerdeError> {
+        self.serialize_bytes(v.as_bytes())
+    }
+
+    fn serialize_bytes(self, v: &[u8]) -> Result<Self::Ok, SerdeError> {
+        Ok(Some(DataType::ByteString(v.into())))
+    }
+
+    fn serialize_none(self) -> Result<Self::Ok, SerdeError> {
+        Ok(None)
+    }
+
+    fn serialize_some<T: Serialize + ?Sized>(self, value: &T) -> Result<Self::Ok, SerdeError> {
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<Self::Ok, SerdeError> {
+        Ok(None)
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<Self::Ok, SerdeError> {
+        Ok(None)
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+    ) -> Result<Self::Ok, SerdeError> {
+        self.serialize_str(variant)
+    }
+
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        value: &T,
+    ) -> Result<Self::Ok, SerdeError> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        value: &T,
+    ) -> Result<Self::Ok, SerdeError> {
+        Ok(wrap_variant(variant, value.serialize(self)?))
+    }
+
+    fn serialize_seq(self, len: Option<usize>) -> Result<Self::SerializeSeq, SerdeError> {
+        Ok(ListSerializer {
+            list: Vec::with_capacity(len.unwrap_or(0)),
+        })
+    }
+
+    fn serialize_tuple(self, len: usize) -> Result<Self::SerializeTuple, SerdeError> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        _name: &'static str,
+        len: usize,
+    ) -> Result<Self::SerializeTupleStruct, SerdeError> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        len: usize,
+    ) -> Result<Self::SerializeTupleVariant, SerdeError> {
+        Ok(VariantSerializer {
+            variant,
+            inner: self.serialize_seq(Some(len))?,
+        })
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> Result<Self::SerializeMap, SerdeError> {
+        Ok(DictSerializer {
+            dict: BTreeMap::new(),
+            key: None,
+        })
+    }
+
+    fn serialize_struct(
+        self,
+        _name: &'static str,
+        len: usize,
+    ) -> Result<Self::SerializeStruct, SerdeError> {
+        self.serialize_map(Some(len))
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        len: usize,
+    ) -> Result<Self::SerializeStructVariant, SerdeError> {
+        Ok(VariantSerializer {
+            variant,
+            inner: self.serialize_map(Some(len))?,
+        })
+    }
+}
+// endregion:   --- Traits impl
+// endregion:   --- DataTypeSerializer
+
+// region:      --- Compound serializers
+pub struct ListSerializer {
+    list: Vec<DataType>,
+}
+
+impl ser::SerializeSeq for ListSerializer {
+    type Ok = Option<DataType>;
+    type Error = SerdeError;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), SerdeError> {
+        if let Some(value) = value.serialize(DataTypeSerializer)? {
+            self.list.push(value);
+        }
+        Ok(())
+    }
+
+    fn end(self) -> Result<Self::Ok, SerdeError> {
+        Ok(Some(DataType::List(self.list)))
+    }
+}
+
+impl ser::SerializeTuple for ListSerializer {
+    type Ok = Option<DataType>;
+    type Error = SerdeError;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), SerdeError> {
+        ser::SerializeSeq::serialize_element(self, value)
+    }
+
+    fn end(self) -> Result<Self::Ok, SerdeError> {
+        ser::SerializeSeq::end(self)
+    }
+}
+
+impl ser::SerializeTupleStruct for ListSerializer {
+    type Ok = Option<DataType>;
+    type Error = SerdeError;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), SerdeError> {
+        ser::SerializeSeq::serialize_element(self, value)
+    }
+
+    fn end(self) -> Result<Self::Ok, SerdeError> {
+        ser::SerializeSeq::end(self)
+    }
+}
+
+pub struct DictSerializer {
+    dict: BTreeMap<ByteString, DataType>,
+    key: Option<ByteString>,
+}
+
+impl ser::SerializeMap for DictSerializer {
+    type Ok = Option<DataType>;
+    type Error = SerdeError;
+
+    fn serialize_key<T: Serialize + ?Sized>(&mut self, key: &T) -> Result<(), SerdeError> {
+        match key.serialize(DataTypeSerializer)? {
+            Some(DataType::ByteString(key)) => {
+                self.key = Some(key);
+                Ok(())
+            }
+            Some(DataType::Integer(_)) => Err(SerdeError::InvalidKey("an integer")),
+            Some(DataType::List(_)) => Err(SerdeError::InvalidKey("a list")),
+            Some(DataType::Dict(_)) => Err(SerdeError::InvalidKey("a dict")),
+            None => Err(SerdeError::InvalidKey("a missing value")),
+        }
+    }
+
+    fn serialize_value<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), SerdeError> {
+        let key = self.key.take().ok_or_else(|| {
+            SerdeError::Message("a dict value was serialized before its key".to_owned())
+        })?;
+        if let Some(value) = value.serialize(DataTypeSerializer)? {
+            self.dict.insert(key, value);
+        }
+        Ok(())
+    }
+
+    fn end(self) -> Result<Self::Ok, SerdeError> {
+        Ok(Some(DataType::Dict(self.dict)))
+    }
+}
+
+impl ser::SerializeStruct for DictSerializer {
+    type Ok = Option<DataType>;
+    type Error = SerdeError;
+
+    fn serialize_field<T: Serialize + ?Sized>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<(), SerdeError> {
+        if let Some(value) = value.serialize(DataTypeSerializer)? {
+            self.dict.insert(key.into(), value);
+        }
+        Ok(())
+    }
+
+    fn end(self) -> Result<Self::Ok, SerdeError> {
+        ser::SerializeMap::end(self)
+    }
+}
+
+/// Wraps the content of a tuple or struct variant into a dict with a single key: the name of
+/// the variant.
+pub struct VariantSerializer<S> {
+    variant: &'static str,
+    inner: S,
+}
+
+impl ser::SerializeTupleVariant for VariantSerializer<ListSerializer> {
+    type Ok = Option<DataType>;
+    type Error = SerdeError;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), SerdeError> {
+        ser::SerializeSeq::serialize_element(&mut self.inner, value)
+    }
+
+    fn end(self) -> Result<Self::Ok, SerdeError> {
+        let content = ser::SerializeSeq::end(self.inner)?;
+        Ok(wrap_variant(self.variant, content))
+    }
+}
+
+impl ser::SerializeStructVariant for VariantSerializer<DictSerializer> {
+    type Ok = Option<DataType>;
+    type Error = SerdeError;
+
+    fn serialize_field<T: Serialize + ?Sized>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<(), SerdeError> {
+        ser::SerializeStruct::serialize_field(&mut self.inner, key, value)
+    }
+
+    fn end(self) -> Result<Self::Ok, SerdeError> {
+        let content = ser::SerializeStruct::end(self.inner)?;
+        Ok(wrap_variant(self.variant, content))
+    }
+}
+
+fn wrap_variant(variant: &'static str, content: Option<DataType>) -> Option<DataType> {
+    let mut dict: BTreeMap<ByteString, DataType> = BTreeMap::new();
+    if let Some(content) = content {
+        dict.insert(variant.into(), content);
+    }
+    Some(DataType::Dict(dict))
+}
+// endregion:   --- Compound serializers
+
+#[cfg(test)]
+mod tests {
+    use serde::Serialize;
+    use serde_bytes::ByteBuf;
+
+    use super::*;
+
+    #[derive(Serialize)]
+    struct Info {
+        name: String,
+        #[serde(rename = "piece length")]
+        piece_length: u32,
+        pieces: ByteBuf,
+        length: Option<u64>,
+        private: bool,
+    }
+
+    #[test]
+    fn test_struct_is_encoded_with_sorted_keys() {
+        let info = Info {
+            name: "a.txt".to_owned(),
+            piece_length: 16,
+            pieces: ByteBuf::from(vec![0xFF, 0x00]),
+            length: Some(5),
+            private: true,
+        };
+        assert_eq!(
+            to_bytes(&info).unwrap().as_ref(),
+            [
+                b"d6:lengthi5e4:name5:a.txt12:piece lengthi16e6:pieces2:".as_slice(),
+                &[0xFF, 0x00],
+                b"7:privatei1ee",
+            ]
+            .concat()
+        );
+    }
+
+    #[test]
+    fn test_none_fields_are_omitted() {
+        let info = Info {
+            name: "a".to_owned(),
+            piece_length: 1,
+            pieces: ByteBuf::new(),
+            length: None,
+            private: false,
+        };
+        assert_eq!(
+            to_bytes(&info).unwrap().as_ref(),
+            b"d4:name1:a12:piece lengthi1e6:pieces0:7:privatei0ee"
+        );
+    }
+
+    #[test]
+    fn test_unsupported_values() {
+        assert_eq!(
+            to_bytes(&1.5).unwrap_err(),
+            SerdeError::UnsupportedType("floating point numbers")
+        );
+        assert_eq!(
+            to_bytes(&u64::MAX).unwrap_err(),
+            SerdeError::IntegerOutOfRange(u64::MAX)
+        );
+        assert_eq!(
+            to_bytes(&BTreeMap::from_iter([(1, 2)])).unwrap_err(),
+            SerdeError::InvalidKey("an integer")
+        );
+    }
+}
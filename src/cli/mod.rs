@@ -1,28 +1,33 @@
 use std::{
-    collections::BTreeMap,
     fs::{self, File},
-    io::{BufWriter, Write},
-    os::unix::fs::FileExt,
+    io::{self, Read, Write},
+    path::{Path, PathBuf},
     rc::Rc,
     sync::{Arc, Mutex},
     thread,
+    time::{SystemTime, UNIX_EPOCH},
 };
 
-use clap::{Parser, Subcommand};
-use sha1::{Digest, Sha1};
+use clap::{Parser, Subcommand, ValueEnum};
 
 use crate::{
-    decoders,
+    bencode::encoders,
+    decoders::{self, DecodeOptions},
     error::{Error, Result},
-    magnet::magnet_link_v1::MagnetLinkV1,
+    magnet::{MagnetLink, MagnetLinkError},
+    torrent::{
+        create_output_files, create_torrent, is_safe_path_segment, proof_layers_count,
+        verify_hashes, write_piece, CreateOptions, ExtendedHandshake, HashRequest, MetaVersion,
+        Metadata, TorrentEditor, TorrentFile, MERKLE_BLOCK_SIZE,
+    },
     tracker,
-    types::DataType,
+    types::{self, to_json_lossless, DataType},
     HandshakeMessage, Peer, Piece, Torrent, TrackerResponse,
 };
 
 const PEER_ID: &str = "12345678901234567890";
 const PEER_ID_BYTES: [u8; 20] = *b"12345678901234567890";
-const BLOCK_SIZE: usize = 16 * 1024;
+const CREATED_BY: &str = concat!(env!("CARGO_PKG_NAME"), "/", env!("CARGO_PKG_VERSION"));
 
 #[derive(Parser, Debug)]
 #[command(name = "codecrafters-bittorrent")]
@@ -34,10 +39,136 @@ pub struct Cli {
 
 #[derive(Subcommand, Debug)]
 pub enum CliCommand {
-    /// Decode bencoded string
+    /// Decode bencoded values
     Decode {
-        /// The string to decode (positional argument)
+        /// The string to decode, or '-' to read stdin (positional argument)
+        #[arg(required_unless_present = "file", conflicts_with = "file")]
+        input: Option<String>,
+
+        /// Read the input from a file, or stdin if '-' (named argument)
+        #[arg(long)]
+        file: Option<String>,
+
+        /// Print a tree of the values with the byte offsets of every node (named argument)
+        #[arg(long, conflicts_with = "format")]
+        tree: bool,
+
+        /// Reject input that is not canonically encoded (named argument)
+        #[arg(long)]
+        strict: bool,
+
+        /// The output format (named argument)
+        #[arg(long, value_enum, default_value_t = DecodeFormat::Json)]
+        format: DecodeFormat,
+    },
+    /// Edit the trackers, web seeds and comments of a .torrent file, keeping its info hash
+    Edit {
+        /// The path to a .torrent file (positional argument)
+        torrent_file: String,
+
+        /// Output file path, the edited file itself if not set (named argument)
+        #[arg(short, long)]
+        output: Option<String>,
+
+        /// Remove all the trackers before adding new ones (named argument)
+        #[arg(long)]
+        clear_trackers: bool,
+
+        /// Add a tracker tier, with the URLs separated by commas (named argument)
+        #[arg(long = "add-tracker")]
+        add_trackers: Vec<String>,
+
+        /// Remove a tracker URL from every tier (named argument)
+        #[arg(long = "remove-tracker")]
+        remove_trackers: Vec<String>,
+
+        /// Replace a tracker URL in every tier (named argument)
+        #[arg(long = "replace-tracker", num_args = 2, value_names = ["OLD", "NEW"])]
+        replace_trackers: Vec<String>,
+
+        /// Set the 'announce' URL, after the other tracker edits (named argument)
+        #[arg(long)]
+        announce: Option<String>,
+
+        /// Remove all the web seeds before adding new ones (named argument)
+        #[arg(long)]
+        clear_web_seeds: bool,
+
+        /// Add a web seed URL (named argument)
+        #[arg(long = "add-web-seed")]
+        add_web_seeds: Vec<String>,
+
+        /// Remove a web seed URL (named argument)
+        #[arg(long = "remove-web-seed")]
+        remove_web_seeds: Vec<String>,
+
+        /// Set the comment (named argument)
+        #[arg(long, conflicts_with = "remove_comment")]
+        comment: Option<String>,
+
+        /// Remove the comment (named argument)
+        #[arg(long)]
+        remove_comment: bool,
+
+        /// Set the 'created by' field (named argument)
+        #[arg(long, conflicts_with = "remove_created_by")]
+        created_by: Option<String>,
+
+        /// Remove the 'created by' field (named argument)
+        #[arg(long)]
+        remove_created_by: bool,
+    },
+    /// Bencode a JSON value, in the format printed by `decode --format json-lossless`
+    Encode {
+        /// The JSON to encode (positional argument)
         input: String,
+
+        /// Output file path, stdout if not set (named argument)
+        #[arg(short, long)]
+        output: Option<String>,
+    },
+    /// Create a .torrent file from a file or a directory
+    Create {
+        /// The file or directory to share (positional argument)
+        path: String,
+
+        /// Output file path, '<name>.torrent' if not set (named argument)
+        #[arg(short, long)]
+        output: Option<String>,
+
+        /// Tracker URL, repeat for more tiers and separate URLs of the same tier with commas
+        /// (named argument)
+        #[arg(short, long = "tracker", required = true)]
+        trackers: Vec<String>,
+
+        /// Web seed URL, can be repeated (named argument)
+        #[arg(short, long = "web-seed")]
+        web_seeds: Vec<String>,
+
+        /// Piece length in bytes, a power of two of at least 16384; chosen from the content
+        /// length if not set (named argument)
+        #[arg(short, long)]
+        piece_length: Option<u32>,
+
+        /// Free-form comment (named argument)
+        #[arg(short, long)]
+        comment: Option<String>,
+
+        /// Mark the torrent as private (named argument)
+        #[arg(long)]
+        private: bool,
+
+        /// Source tag, usually the tracker name (named argument)
+        #[arg(short, long)]
+        source: Option<String>,
+
+        /// Leave the creation date out (named argument)
+        #[arg(long)]
+        no_date: bool,
+
+        /// Number of hashing threads, all cores if not set (named argument)
+        #[arg(long)]
+        threads: Option<usize>,
     },
     /// Download the whole content of a .torrent file
     #[command()]
@@ -45,7 +176,7 @@ pub enum CliCommand {
         /// Path to the torrent file (positional argument)
         torrent_file: String,
 
-        /// Output file path (named argument)
+        /// Output file path, or the output directory for multi-file torrents (named argument)
         #[arg(short, long)]
         output: String,
     },
@@ -75,12 +206,44 @@ pub enum CliCommand {
         /// The path to a .torrent file (positional argument)
         torrent_file: String,
     },
+    /// Print the Magnet link of a .torrent file
+    #[command(name = "magnet_create")]
+    MagnetCreate {
+        /// The path to a .torrent file (positional argument)
+        torrent_file: String,
+    },
+    /// Fetch the metadata of a Magnet link from peers and download its whole content
+    #[command(name = "magnet_download")]
+    MagnetDownload {
+        /// Output file path, or the output directory for multi-file torrents (named argument)
+        #[arg(short, long)]
+        output: String,
+
+        /// The Magnet link to download (positional argument)
+        magnet_url: String,
+    },
     /// Parse a Magnet link and perform a handshake with a peer
     #[command(name = "magnet_handshake")]
     MagnetHandshake {
         /// The Magnet link to parse
         magnet_url: String,
     },
+    /// Fetch the metadata of a Magnet link from peers and print info about it
+    #[command(name = "magnet_info")]
+    MagnetInfo {
+        /// The Magnet link to fetch the metadata of
+        magnet_url: String,
+    },
+    /// Fetch the metadata of a Magnet link from peers and save it as a .torrent file
+    #[command(name = "magnet_to_torrent")]
+    MagnetToTorrent {
+        /// Output file path, '<name>.torrent' if not set (named argument)
+        #[arg(short, long)]
+        output: Option<String>,
+
+        /// The Magnet link to fetch the metadata of
+        magnet_url: String,
+    },
     /// Parse a Magnet link
     #[command(name = "magnet_parse")]
     MagnetParse {
@@ -94,10 +257,148 @@ pub enum CliCommand {
     },
 }
 
+#[derive(Clone, Copy, Debug, PartialEq, ValueEnum)]
+pub enum DecodeFormat {
+    /// Compact JSON, with byte strings converted to UTF-8 lossily
+    Json,
+    /// Compact JSON that can be encoded back with the `encode` command
+    JsonLossless,
+    /// Indented lossless JSON
+    Pretty,
+    /// Lossless YAML
+    Yaml,
+}
+
 impl CliCommand {
     pub fn handle(&self) -> Result<()> {
         match self {
-            CliCommand::Decode { input } => handle_decode(input),
+            CliCommand::Decode {
+                input,
+                file,
+                tree,
+                strict,
+                format,
+            } => {
+                let input: Vec<u8> = match (input.as_deref(), file.as_deref()) {
+                    (Some("-"), _) | (_, Some("-")) => read_stdin()?,
+                    (Some(input), _) => input.as_bytes().to_vec(),
+                    (None, Some(path)) => fs::read(path).map_err(Error::FileError)?,
+                    (None, None) => unreachable!("clap requires either the input or a file"),
+                };
+                handle_decode(&input, *tree, *strict, *format)
+            }
+            CliCommand::Edit {
+                torrent_file,
+                output,
+                clear_trackers,
+                add_trackers,
+                remove_trackers,
+                replace_trackers,
+                announce,
+                clear_web_seeds,
+                add_web_seeds,
+                remove_web_seeds,
+                comment,
+                remove_comment,
+                created_by,
+                remove_created_by,
+            } => {
+                let data: Vec<u8> = fs::read(torrent_file).map_err(Error::FileError)?;
+                let mut editor = TorrentEditor::parse(&data)?;
+
+                let mut trackers: Vec<Vec<String>> = if *clear_trackers {
+                    Vec::new()
+                } else {
+                    editor.get_trackers()
+                };
+                for url in trackers.iter_mut().flatten() {
+                    if let Some(pair) = replace_trackers.chunks(2).find(|pair| pair[0] == *url) {
+                        *url = pair[1].clone();
+                    }
+                }
+                for tier in trackers.iter_mut() {
+                    tier.retain(|url| !remove_trackers.contains(url));
+                }
+                trackers.extend(
+                    add_trackers
+                        .iter()
+                        .map(|tier| tier.split(',').map(str::to_owned).collect()),
+                );
+                if trackers != editor.get_trackers() {
+                    editor.set_trackers(&trackers);
+                }
+                if let Some(announce) = announce {
+                    editor.set_announce(Some(announce));
+                }
+
+                let mut web_seeds: Vec<String> = if *clear_web_seeds {
+                    Vec::new()
+                } else {
+                    editor.get_web_seeds()
+                };
+                web_seeds.retain(|url| !remove_web_seeds.contains(url));
+                web_seeds.extend(add_web_seeds.iter().cloned());
+                if web_seeds != editor.get_web_seeds() {
+                    editor.set_web_seeds(&web_seeds);
+                }
+
+                let comment: Option<String> = if *remove_comment {
+                    None
+                } else {
+                    comment.clone().or_else(|| editor.get_comment())
+                };
+                if comment != editor.get_comment() {
+                    editor.set_comment(comment.as_deref());
+                }
+                let created_by: Option<String> = if *remove_created_by {
+                    None
+                } else {
+                    created_by.clone().or_else(|| editor.get_created_by())
+                };
+                if created_by != editor.get_created_by() {
+                    editor.set_created_by(created_by.as_deref());
+                }
+                let output: &str = output.as_deref().unwrap_or(torrent_file);
+                fs::write(output, editor.to_bytes()).map_err(Error::FileError)
+            }
+            CliCommand::Encode { input, output } => handle_encode(input, output.as_deref()),
+            CliCommand::Create {
+                path,
+                output,
+                trackers,
+                web_seeds,
+                piece_length,
+                comment,
+                private,
+                source,
+                no_date,
+                threads,
+            } => {
+                let default_options = CreateOptions::default();
+                let creation_date: Option<i64> = if *no_date {
+                    None
+                } else {
+                    SystemTime::now()
+                        .duration_since(UNIX_EPOCH)
+                        .ok()
+                        .map(|duration| duration.as_secs() as i64)
+                };
+                let options = CreateOptions {
+                    piece_length: *piece_length,
+                    trackers: trackers
+                        .iter()
+                        .map(|tier| tier.split(',').map(str::to_owned).collect())
+                        .collect(),
+                    web_seeds: web_seeds.clone(),
+                    comment: comment.clone(),
+                    created_by: Some(CREATED_BY.to_owned()),
+                    creation_date,
+                    private: *private,
+                    source: source.clone(),
+                    threads: threads.unwrap_or(default_options.threads),
+                };
+                handle_create(Path::new(path), output.as_deref(), &options)
+            }
             CliCommand::Download {
                 torrent_file,
                 output,
@@ -112,27 +413,217 @@ impl CliCommand {
                 address,
             } => handle_handshake(torrent_file, address),
             CliCommand::Info { torrent_file } => handle_info(torrent_file),
+            CliCommand::MagnetCreate { torrent_file } => handle_magnet_create(torrent_file),
+            CliCommand::MagnetDownload { output, magnet_url } => {
+                handle_magnet_download(magnet_url, output)
+            }
             CliCommand::MagnetHandshake { magnet_url } => handle_magnet_handshake(magnet_url),
+            CliCommand::MagnetInfo { magnet_url } => handle_magnet_info(magnet_url),
             CliCommand::MagnetParse { magnet_url } => handle_magnet_parse(magnet_url),
+            CliCommand::MagnetToTorrent { output, magnet_url } => {
+                handle_magnet_to_torrent(magnet_url, output.as_deref())
+            }
             CliCommand::Peers { torrent_file } => handle_peers(torrent_file),
         }
     }
 }
 
-fn handle_decode(input: &str) -> Result<()> {
-    let (decoded, _) = decoders::decode(input.as_bytes()).map_err(|err| Error::DecodeError(err))?;
-    let json: serde_json::Value = decoded.into();
-    println!("{}", &json);
+/// Decodes all the concatenated values of the input, printing each one on its own.
+fn handle_decode(input: &[u8], tree: bool, strict: bool, format: DecodeFormat) -> Result<()> {
+    let options: DecodeOptions = if strict {
+        DecodeOptions::strict()
+    } else {
+        DecodeOptions::default()
+    };
+    decoders::check_input_length(input, &options).map_err(Error::DecodeError)?;
+    let mut begin: usize = 0;
+    loop {
+        // Concatenated values are allowed in strict mode too, but each has to be canonical.
+        let (decoded, length) = decoders::decode_value(&input[begin..], &options, 0)
+            .map(|(decoded, length)| (decoded.into_owned(), length))
+            .map_err(|err| {
+                eprintln!("{}", format_decode_error(input, begin, &err));
+                Error::DecodeError(err)
+            })?;
+        if tree {
+            print_tree(input, begin, "", 0)?;
+        } else {
+            print_decoded(decoded, format)?;
+        }
+        begin += length;
+        // Trailing whitespace, e.g. the newline of `echo`, is not another value.
+        if input[begin..].iter().all(u8::is_ascii_whitespace) {
+            return Ok(());
+        }
+    }
+}
+
+fn print_decoded(decoded: DataType, format: DecodeFormat) -> Result<()> {
+    match format {
+        DecodeFormat::Json => {
+            let json: serde_json::Value = decoded.into();
+            println!("{}", &json);
+        }
+        DecodeFormat::JsonLossless => println!("{}", to_json_lossless(&decoded)),
+        DecodeFormat::Pretty => println!(
+            "{}",
+            serde_json::to_string_pretty(&to_json_lossless(&decoded)).map_err(Error::JsonError)?
+        ),
+        DecodeFormat::Yaml => print!(
+            "{}",
+            serde_yaml::to_string(&to_json_lossless(&decoded)).map_err(Error::YamlError)?
+        ),
+    }
     Ok(())
 }
 
-fn handle_download(torrent_file_path: &str, out_file_path: &str) -> Result<()> {
-    let torrent = parse_torrent_from_file(&torrent_file_path)?;
+/// Prints the value at `begin` and its children, one per line, with the byte ranges they span.
+/// Returns the end of the value.
+///
+/// Nested values are decoded again to find their ranges, so this is only meant for inspection.
+fn print_tree(input: &[u8], begin: usize, label: &str, indent: usize) -> Result<usize> {
+    const PREVIEW_LENGTH: usize = 32;
 
-    let peer_addresses: Box<[String]> = get_peers(&torrent).unwrap();
+    let (decoded, length) = decoders::decode(&input[begin..]).map_err(Error::DecodeError)?;
+    let end: usize = begin + length;
+    let line: String = format!("{}{label}[{begin}..{end}]", "  ".repeat(indent));
+    match decoded {
+        DataType::Integer(number) => println!("{line} integer {number}"),
+        DataType::ByteString(byte_str) => {
+            let data: &[u8] = byte_str.get_data();
+            let preview: String = match byte_str.as_str() {
+                Some(text) if text.chars().count() > PREVIEW_LENGTH => {
+                    format!(
+                        "{:?}...",
+                        text.chars().take(PREVIEW_LENGTH).collect::<String>()
+                    )
+                }
+                Some(text) => format!("{text:?}"),
+                None if data.len() > PREVIEW_LENGTH / 2 => {
+                    format!("0x{}...", hex::encode(&data[..PREVIEW_LENGTH / 2]))
+                }
+                None => format!("0x{}", hex::encode(data)),
+            };
+            println!("{line} string ({} bytes) {preview}", data.len());
+        }
+        DataType::List(list) => {
+            println!("{line} list ({} elements)", list.len());
+            let mut position: usize = begin + 1;
+            for index in 0..list.len() {
+                position = print_tree(input, position, &format!("[{index}] "), indent + 1)?;
+            }
+        }
+        DataType::Dict(_) => {
+            // The entries are read from the input rather than from the decoded dict, so that
+            // duplicate keys are shown too.
+            let mut entries: Vec<(String, usize)> = vec![];
+            let mut position: usize = begin + 1;
+            while input[position] != b'e' {
+                let (key, key_length) = decoders::decode_byte_string(&input[position..])
+                    .map_err(|err| Error::DecodeError(err.into()))?;
+                let (_, value_length) = decoders::decode(&input[position + key_length..])
+                    .map_err(Error::DecodeError)?;
+                entries.push((key.to_string(), position + key_length));
+                position += key_length + value_length;
+            }
+            println!("{line} dict ({} entries)", entries.len());
+            for (key, position) in entries {
+                print_tree(input, position, &format!("{key:?}: "), indent + 1)?;
+            }
+        }
+    }
+    Ok(end)
+}
+
+fn read_stdin() -> Result<Vec<u8>> {
+    let mut input: Vec<u8> = vec![];
+    io::stdin()
+        .read_to_end(&mut input)
+        .map_err(Error::FileError)?;
+    Ok(input)
+}
+
+fn handle_encode(input: &str, output_path: Option<&str>) -> Result<()> {
+    let json: serde_json::Value = serde_json::from_str(input).map_err(Error::JsonError)?;
+    let value: DataType = types::from_json(&json).map_err(Error::JsonConversionError)?;
+    let bencoded: Vec<u8> = encoders::encode_to_vec(&value);
+    match output_path {
+        Some(path) => fs::write(path, bencoded).map_err(Error::FileError),
+        None => io::stdout().write_all(&bencoded).map_err(Error::FileError),
+    }
+}
+
+/// Describes where the decoding failed, followed by an excerpt of the input with a caret under
+/// the offending byte.
+fn format_decode_error(input: &[u8], begin: usize, err: &decoders::DecodeError) -> String {
+    const CONTEXT_LENGTH: usize = 30;
+
+    let position: usize = begin + err.position();
+    let path: String = err.path();
+    let location: String = if path.is_empty() {
+        format!("at byte {position}")
+    } else {
+        format!("at byte {position} ({path})")
+    };
+
+    let begin: usize = position.saturating_sub(CONTEXT_LENGTH);
+    let end: usize = input.len().min(position + CONTEXT_LENGTH);
+    let prefix: &str = if begin > 0 { "..." } else { "" };
+    let suffix: &str = if end < input.len() { "..." } else { "" };
+    // Non-printable bytes are replaced, so that every byte takes exactly one column.
+    let excerpt: String = input[begin..end]
+        .iter()
+        .map(|byte| match byte {
+            b' '..=b'~' => *byte as char,
+            _ => '.',
+        })
+        .collect();
+    let caret_offset: usize = prefix.len() + position.min(input.len()) - begin;
+
+    format!(
+        "Could not decode the input {location}: {}\n{prefix}{excerpt}{suffix}\n{}^",
+        err.root_cause(),
+        " ".repeat(caret_offset)
+    )
+}
+
+fn handle_create(path: &Path, output_path: Option<&str>, options: &CreateOptions) -> Result<()> {
+    let metainfo: DataType = create_torrent(path, options)?;
+    let bencoded = encoders::bencode(&metainfo);
+    let torrent: Torrent = bencoded.as_ref().try_into()?;
+    let output_path: PathBuf = match output_path {
+        Some(output_path) => PathBuf::from(output_path),
+        None => default_torrent_path(&torrent),
+    };
+    fs::write(&output_path, &bencoded).map_err(Error::FileError)?;
+    println!("Created {}", output_path.display());
+    println!("Info Hash: {}", hex::encode(torrent.get_info_hash()));
+    Ok(())
+}
 
-    let mut file = fs::File::create(out_file_path).map_err(|err| Error::FileError(err))?;
-    reserve_space(&mut file, torrent.get_length())?;
+fn handle_download(torrent_file_path: &str, output_path: &str) -> Result<()> {
+    let torrent = parse_torrent_from_file(torrent_file_path)?;
+    let peer_addresses: Box<[String]> = get_peers(&torrent)?;
+    download_torrent(torrent, &peer_addresses, output_path, |_| true)
+}
+
+/// Downloads the pieces of the selected files from the peers, each peer in its own thread.
+fn download_torrent<F>(
+    mut torrent: Torrent,
+    peer_addresses: &[String],
+    output_path: &str,
+    is_file_selected: F,
+) -> Result<()>
+where
+    F: Fn(usize) -> bool,
+{
+    if peer_addresses.is_empty() {
+        Err(Error::NoPeersAvailable)?
+    }
+    fetch_missing_piece_layers(&mut torrent, peer_addresses)?;
+
+    let files: Box<[Option<File>]> =
+        create_output_files(&torrent, Path::new(output_path), &is_file_selected)?;
     for p in torrent.get_pieces().as_ref() {
         println!(
             "Piece #{}: begin={}, length = {}",
@@ -142,76 +633,105 @@ fn handle_download(torrent_file_path: &str, out_file_path: &str) -> Result<()> {
         );
     }
 
-    let pieces_shared: Arc<[Piece]> = torrent.get_pieces();
-    let piece_indices_shared = Arc::new(Mutex::new(Vec::from_iter(0..pieces_shared.len())));
-    let file_shared: Arc<Mutex<File>> = Arc::new(Mutex::new(file));
-    let info_hash: [u8; 20] = *torrent.get_info_hash();
+    let piece_indices_shared = Arc::new(Mutex::new(torrent.get_selected_pieces(&is_file_selected)));
+    let files_shared: Arc<Mutex<Box<[Option<File>]>>> = Arc::new(Mutex::new(files));
+    let torrent_shared: Arc<Torrent> = Arc::new(torrent);
     let mut handles: Vec<thread::JoinHandle<()>> = Vec::new();
-    for peer_addr in peer_addresses.as_ref() {
-        let file_shared: Arc<Mutex<File>> = Arc::clone(&file_shared);
-        let pieces_shared: Arc<[Piece]> = Arc::clone(&pieces_shared);
+    for peer_addr in peer_addresses {
+        let files_shared: Arc<Mutex<Box<[Option<File>]>>> = Arc::clone(&files_shared);
         let piece_indices_shared: Arc<Mutex<Vec<usize>>> = Arc::clone(&piece_indices_shared);
+        let torrent_shared: Arc<Torrent> = Arc::clone(&torrent_shared);
 
         let peer_addr: String = peer_addr.clone();
-        // println!("Peer {} received {} pieces job.", &addr, chunk_boxed.len());
         let handle = thread::spawn(move || {
-            let mut peer = Peer::new(peer_addr).unwrap();
-            let handshake_message =
-                HandshakeMessage::new(&Rc::new(info_hash), &Rc::new(PEER_ID_BYTES));
-            let handshake_response: HandshakeMessage = peer.handshake(&handshake_message).unwrap();
-            println!(
-                "Received handshake from {}: {}",
-                &peer.get_address(),
-                &handshake_response
-            );
-            // Handshake end
-            peer.receive_bitfield().unwrap();
-            peer.send_interested().unwrap();
-            peer.receive_unchoke().unwrap();
-
-            loop {
-                let maybe_piece_index = {
-                    let mut piece_indices_guard = piece_indices_shared.lock().unwrap();
-                    piece_indices_guard.pop()
-                };
-
-                if let Some(index) = maybe_piece_index {
-                    let piece: &Piece = &pieces_shared[index];
-                    let data: Box<[u8]> = download_piece(&mut peer, piece).unwrap();
-                    let mut file = file_shared.lock().unwrap();
-
-                    file.write_all_at(&data, piece.get_begin()).unwrap();
-                    file.flush().unwrap();
-                } else {
-                    break;
-                }
+            if let Err(err) = download_from_peer(
+                &peer_addr,
+                &torrent_shared,
+                &piece_indices_shared,
+                &files_shared,
+            ) {
+                eprintln!("[Peer @{peer_addr}] Could not download: {err:?}");
             }
         });
         handles.push(handle);
     }
 
     // Join all threads
+    let mut panicked: bool = false;
     for handle in handles {
-        handle.join().unwrap();
+        panicked |= handle.join().is_err();
+    }
+    if panicked {
+        Err(Error::PeerThreadPanicked)?
+    }
+    // The pieces given back by the failed peers after the other ones were done are missing.
+    let missing_count: usize = piece_indices_shared.lock().unwrap().len();
+    if missing_count > 0 {
+        Err(Error::PiecesNotDownloaded(missing_count))?
     }
 
-    println!("{}", torrent);
+    println!("{}", torrent_shared);
 
     Ok(())
 }
 
+/// Downloads pieces from a peer until there are none left. When the peer fails, the piece it
+/// was downloading is given back to the other peers.
+fn download_from_peer(
+    peer_address: &str,
+    torrent: &Torrent,
+    piece_indices: &Mutex<Vec<usize>>,
+    files: &Mutex<Box<[Option<File>]>>,
+) -> Result<()> {
+    let mut peer: Peer = download_handshake(peer_address, torrent)?;
+    let pieces: Arc<[Piece]> = torrent.get_pieces();
+    let torrent_files: Arc<[TorrentFile]> = torrent.get_files();
+    loop {
+        let Some(index) = piece_indices.lock().unwrap().pop() else {
+            return Ok(());
+        };
+        let piece: &Piece = &pieces[index];
+        let result: Result<()> = download_piece(&mut peer, piece).and_then(|data| {
+            let mut files = files.lock().unwrap();
+            write_piece(&mut files, &torrent_files, piece, &data)
+        });
+        if let Err(err) = result {
+            piece_indices.lock().unwrap().push(index);
+            return Err(err);
+        }
+    }
+}
+
 fn handle_download_piece(
     torrent_file_path: &str,
     piece_index: usize,
     output_file_path: &str,
 ) -> Result<()> {
-    let torrent: Torrent = parse_torrent_from_file(torrent_file_path)?;
+    let mut torrent: Torrent = parse_torrent_from_file(torrent_file_path)?;
+    let peers: Box<[String]> = get_peers(&torrent)?;
+    let peer_address: &String = peers.first().ok_or(Error::NoPeersAvailable)?;
+    fetch_missing_piece_layers(&mut torrent, &peers)?;
     let pieces: Arc<[Piece]> = torrent.get_pieces();
+    let piece: &Piece = pieces.get(piece_index).ok_or(Error::PieceIndexOutOfRange {
+        index: piece_index,
+        pieces_count: pieces.len(),
+    })?;
 
-    let peers: Box<[String]> = get_peers(&torrent).unwrap();
-    let mut peer = Peer::new(&peers[0])?;
+    let mut peer: Peer = download_handshake(peer_address, &torrent)?;
+    let piece: Box<[u8]> = download_piece(&mut peer, piece)?;
+    let mut out: File = fs::File::create(output_file_path).map_err(Error::FileError)?;
+    out.write_all(&piece).map_err(Error::FileError)?;
+    out.flush().map_err(Error::FileError)?;
+    Ok(())
+}
+
+/// Connects to a peer to download pieces from it. The extension protocol is enabled, so that the
+/// peer can fetch the metadata of the torrent from us meanwhile (BEP 9).
+fn download_handshake(peer_address: &str, torrent: &Torrent) -> Result<Peer> {
     let handshake_message =
-        HandshakeMessage::new(&Rc::new(*torrent.get_info_hash()), &Rc::new(PEER_ID_BYTES));
+        HandshakeMessage::new_magnet(&Rc::new(*torrent.get_info_hash()), &Rc::new(PEER_ID_BYTES));
+    let mut peer = Peer::new(peer_address)?;
+    peer.set_torrent(torrent);
     let handshake_response: HandshakeMessage = peer.handshake(&handshake_message)?;
     println!(
         "Received handshake from {}: {}",
@@ -220,116 +740,138 @@ fn handle_download_piece(
     );
     // Handshake end
     peer.receive_bitfield()?;
+    if handshake_response.is_extension_supported() {
+        peer.extended_handshake()?;
+    }
     peer.send_interested()?;
     peer.receive_unchoke()?;
-
-    let piece: Box<[u8]> = download_piece(&mut peer, &pieces[piece_index as usize])?;
-    let mut out: File = fs::File::create(output_file_path).map_err(|err| Error::FileError(err))?;
-    out.write_all(&piece).map_err(|err| Error::FileError(err))?;
-    out.flush().map_err(|err| Error::FileError(err))?;
-    Ok(())
+    Ok(peer)
 }
 
 fn handle_handshake(torrent_file_path: &str, peer_address: &str) -> Result<()> {
-    let torrent: Torrent = fs::read(&torrent_file_path)
-        .map(|s| s.as_slice().try_into())
-        .map_err(|err| Error::FileError(err))??;
+    let torrent: Torrent = parse_torrent_from_file(torrent_file_path)?;
     let handshake_message =
         HandshakeMessage::new(&Rc::new(*torrent.get_info_hash()), &Rc::new(PEER_ID_BYTES));
-    let mut peer = Peer::new(&peer_address).ok().unwrap();
-    let response = peer.handshake(&handshake_message).ok().unwrap();
+    let mut peer = Peer::new(peer_address)?;
+    let response = peer.handshake(&handshake_message)?;
     println!("{}", &response);
     Ok(())
 }
 
 fn handle_info(torrent_file_path: &str) -> Result<()> {
-    let torrent: Torrent = fs::read(torrent_file_path)
-        .map(|s| s.as_slice().try_into().ok().unwrap())
-        .map_err(|err| Error::FileError(err))?;
+    let torrent: Torrent = parse_torrent_from_file(torrent_file_path)?;
     println!("{}", &torrent);
     Ok(())
 }
 
+fn handle_magnet_create(torrent_file_path: &str) -> Result<()> {
+    let torrent: Torrent = parse_torrent_from_file(torrent_file_path)?;
+    println!("{}", torrent.to_magnet());
+    Ok(())
+}
+
+fn handle_magnet_download(magnet_url: &str, output_path: &str) -> Result<()> {
+    let link: MagnetLink = MagnetLink::parse(magnet_url)?;
+    let peer_addresses: Box<[String]> = get_magnet_peers(&link)?;
+    let torrent: Torrent = fetch_magnet_torrent(&link, &peer_addresses)?;
+    download_torrent(torrent, &peer_addresses, output_path, |index| {
+        link.is_file_selected(index)
+    })
+}
+
 fn handle_magnet_handshake(magnet_url: &str) -> Result<()> {
-    let link: MagnetLinkV1 = MagnetLinkV1::parse(magnet_url)?;
-    let hash_str_bytes = hex::decode(link.get_info_hash().as_bytes()).unwrap();
-    let info_hash: [u8; 20] = hash_str_bytes.as_slice().try_into().unwrap();
-    let tracker_response: TrackerResponse = tracker::get(
-        /* tracker_url= */ link.get_tracker_url(),
-        /* info_hash= */ &info_hash,
-        /* peer_id= */ PEER_ID,
-        /* port= */ 6881,
-        /* uploaded= */ 0,
-        /* downloaded= */ 0,
-        /* left= */ 12345,
-    )?;
-    match tracker_response {
-        TrackerResponse::Ok { interval: _, peers } => {
-            let peer_address = peers.as_ref().get(0).unwrap();
-            let handshake_message =
-                HandshakeMessage::new_magnet(&Rc::new(info_hash), &Rc::new(PEER_ID_BYTES));
-            let mut peer = Peer::new(peer_address).ok().unwrap();
-            let response = peer.handshake(&handshake_message).ok().unwrap();
-            // TODO: send bitfield
-            peer.receive_bitfield()?;
-            if response.is_extension_supported() {
-                let mut m_dict: BTreeMap<String, DataType> = BTreeMap::new();
-                m_dict.insert("ut_metadata".to_owned(), DataType::Integer(123));
-                let mut payload_dict: BTreeMap<String, DataType> = BTreeMap::new();
-                payload_dict.insert("m".to_owned(), DataType::Dict(m_dict));
-                let extended_handshake_response = peer.extended_handshake(payload_dict)?;
-                let (response_data, _) =
-                    decoders::decode(&extended_handshake_response.get_payload()[1..])
-                        .map_err(|err| Error::DecodeError(err))?;
-                if let DataType::Dict(dict) = response_data {
-                    let m_dict = dict.get("m").ok_or_else(|| {
-                        Error::KeyNotFoundInExtendedHandshakeResponse { key: "m".into() }
-                    })?;
-                    if let DataType::Dict(m) = m_dict {
-                        let ut_metadata = m.get("ut_metadata").ok_or_else(|| {
-                            Error::KeyNotFoundInExtendedHandshakeResponse {
-                                key: "ut_metadata".into(),
-                            }
-                        })?;
-                        println!("{}", &response);
-                        println!(
-                            "Peer Metadata Extension ID: {}",
-                            ut_metadata
-                                .as_i64()
-                                .ok_or_else(|| Error::InvalidExtendedHandshakeResponse)?
-                        );
-                    }
-                } else {
-                    return Err(Error::InvalidExtendedHandshakeResponse);
-                }
-            } else {
-                println!("{}", &response);
+    let link: MagnetLink = MagnetLink::parse(magnet_url)?;
+    let peer_addresses: Box<[String]> = get_magnet_peers(&link)?;
+    let (_, response, extended_handshake) =
+        magnet_handshake(&peer_addresses[0], &link.get_info_hash())?;
+    println!("{}", &response);
+    if let Some(extended_handshake) = extended_handshake {
+        let ut_metadata = extended_handshake.m.get("ut_metadata").ok_or_else(|| {
+            Error::KeyNotFoundInExtendedHandshakeResponse {
+                key: "ut_metadata".into(),
             }
-            return Ok(());
-        }
-        TrackerResponse::Failure(reason) => {
-            return Err(Error::TrackerFailureInResponse {
-                failure_reason: reason,
-            })
-        }
+        })?;
+        println!("Peer Metadata Extension ID: {}", ut_metadata);
     }
-    todo!();
+    Ok(())
+}
+
+fn handle_magnet_info(magnet_url: &str) -> Result<()> {
+    let link: MagnetLink = MagnetLink::parse(magnet_url)?;
+    let peer_addresses: Box<[String]> = get_magnet_peers(&link)?;
+    let torrent: Torrent = fetch_magnet_torrent(&link, &peer_addresses)?;
+    println!("{}", &torrent);
+    Ok(())
 }
 
 fn handle_magnet_parse(magnet_url: &str) -> Result<()> {
-    let link: MagnetLinkV1 = MagnetLinkV1::parse(magnet_url)?;
-    println!(
-        "Tracker URL: {}\nInfo Hash: {}",
-        link.get_tracker_url(),
-        link.get_info_hash()
-    );
+    let link: MagnetLink = MagnetLink::parse(magnet_url)?;
+    if let Some(tracker_url) = link.get_trackers().first() {
+        println!("Tracker URL: {tracker_url}");
+    }
+    println!("Info Hash: {}", hex::encode(link.get_info_hash()));
+    if let Some(info_hash_v2) = link.get_info_hash_v2() {
+        println!("Info Hash v2: {}", hex::encode(info_hash_v2));
+    }
+    if let Some(name) = link.get_name() {
+        println!("Name: {name}");
+    }
+    if let Some(length) = link.get_length() {
+        println!("Length: {length}");
+    }
+    if link.get_trackers().len() > 1 {
+        println!("Trackers: {}", link.get_trackers().join(", "));
+    }
+    if !link.get_web_seeds().is_empty() {
+        println!("Web Seeds: {}", link.get_web_seeds().join(", "));
+    }
+    if !link.get_peer_addresses().is_empty() {
+        println!("Peers: {}", link.get_peer_addresses().join(", "));
+    }
+    if !link.get_select_only().is_empty() {
+        let ranges: Vec<String> = link
+            .get_select_only()
+            .iter()
+            .map(|range| match (range.start(), range.end()) {
+                (first, last) if first == last => first.to_string(),
+                (first, last) => format!("{first}-{last}"),
+            })
+            .collect();
+        println!("Selected Files: {}", ranges.join(","));
+    }
+    Ok(())
+}
+
+/// The default path of a .torrent file, in the current directory: its name, or its hex info hash
+/// when the name could escape the directory.
+fn default_torrent_path(torrent: &Torrent) -> PathBuf {
+    let name: &str = torrent.get_name();
+    if is_safe_path_segment(name) {
+        PathBuf::from(format!("{name}.torrent"))
+    } else {
+        PathBuf::from(format!("{}.torrent", hex::encode(torrent.get_info_hash())))
+    }
+}
+
+/// Saves the metadata of a magnet link, with its trackers and web seeds, as a .torrent file. The
+/// 'info' dict is written as fetched, so that the info hash of the file is the one of the link.
+fn handle_magnet_to_torrent(magnet_url: &str, output_path: Option<&str>) -> Result<()> {
+    let link: MagnetLink = MagnetLink::parse(magnet_url)?;
+    let peer_addresses: Box<[String]> = get_magnet_peers(&link)?;
+    let bencoded: Vec<u8> = fetch_magnet_metainfo(&link, &peer_addresses)?.to_bytes();
+    let torrent: Torrent = bencoded.as_slice().try_into()?;
+    let output_path: PathBuf = match output_path {
+        Some(output_path) => PathBuf::from(output_path),
+        None => default_torrent_path(&torrent),
+    };
+    fs::write(&output_path, &bencoded).map_err(Error::FileError)?;
+    println!("Created {}", output_path.display());
+    println!("Info Hash: {}", hex::encode(torrent.get_info_hash()));
     Ok(())
 }
 
 fn handle_peers(torrent_file_path: &str) -> Result<()> {
-    let torrent: Torrent = fs::read(torrent_file_path)
-        .map(|s| s.as_slice().try_into().ok().unwrap())
-        .map_err(|err| Error::FileError(err))?;
+    let torrent: Torrent = parse_torrent_from_file(torrent_file_path)?;
     let tracker_response: TrackerResponse = tracker::get(
         /* tracker_url= */ torrent.get_announce(),
         /* info_hash= */ torrent.get_info_hash(),
@@ -373,6 +915,148 @@ fn get_peers(torrent: &Torrent) -> Result<Box<[String]>> {
     };
 }
 
+/// The peers of a magnet link: its 'x.pe' peers, followed by the ones of every tracker that
+/// answers.
+fn get_magnet_peers(link: &MagnetLink) -> Result<Box<[String]>> {
+    let mut peers: Vec<String> = link.get_peer_addresses().to_vec();
+    let mut last_error: Option<Error> = None;
+    for tracker_url in link.get_trackers() {
+        let tracker_response = tracker::get(
+            /* tracker_url= */ tracker_url,
+            /* info_hash= */ &link.get_info_hash(),
+            /* peer_id= */ PEER_ID,
+            /* port= */ 6881,
+            /* uploaded= */ 0,
+            /* downloaded= */ 0,
+            // The length is unknown until the metadata is fetched, but trackers require one.
+            /* left= */
+            link.get_length().unwrap_or(999),
+        );
+        match tracker_response {
+            Ok(TrackerResponse::Ok {
+                interval: _,
+                peers: tracker_peers,
+            }) => peers.extend(
+                tracker_peers
+                    .into_vec()
+                    .into_iter()
+                    .filter(|peer| !link.get_peer_addresses().contains(peer)),
+            ),
+            Ok(TrackerResponse::Failure(reason)) => {
+                last_error = Some(Error::TrackerFailureInResponse {
+                    failure_reason: reason,
+                })
+            }
+            Err(err) => last_error = Some(err),
+        }
+    }
+    match (peers.is_empty(), last_error) {
+        (false, _) => Ok(peers.into_boxed_slice()),
+        (true, Some(err)) => Err(err),
+        (true, None) => Err(MagnetLinkError::MissingTracker)?,
+    }
+}
+
+/// Connects to a peer with the extension protocol enabled, and exchanges extended handshakes
+/// (BEP 10) when the peer supports them too.
+fn magnet_handshake(
+    peer_address: &str,
+    info_hash: &[u8; 20],
+) -> Result<(Peer, HandshakeMessage, Option<ExtendedHandshake>)> {
+    let handshake_message =
+        HandshakeMessage::new_magnet(&Rc::new(*info_hash), &Rc::new(PEER_ID_BYTES));
+    let mut peer = Peer::new(peer_address)?;
+    let response: HandshakeMessage = peer.handshake(&handshake_message)?;
+    // TODO: send bitfield
+    peer.receive_bitfield()?;
+    if !response.is_extension_supported() {
+        return Ok((peer, response, None));
+    }
+    let extended_handshake: ExtendedHandshake = peer.extended_handshake()?;
+    Ok((peer, response, Some(extended_handshake)))
+}
+
+fn fetch_magnet_torrent(link: &MagnetLink, peer_addresses: &[String]) -> Result<Torrent> {
+    let bencoded: Vec<u8> = fetch_magnet_metainfo(link, peer_addresses)?.to_bytes();
+    bencoded.as_slice().try_into()
+}
+
+/// Fetches the info dict of a magnet link from its peers (BEP 9) and completes it into a metainfo
+/// dict with the trackers and web seeds of the link.
+fn fetch_magnet_metainfo(link: &MagnetLink, peer_addresses: &[String]) -> Result<TorrentEditor> {
+    let raw_info: Box<[u8]> = fetch_metadata(link, peer_addresses)?;
+    let mut editor = TorrentEditor::from_raw_info(&raw_info)?;
+    let tiers: Vec<Vec<String>> = link
+        .get_trackers()
+        .iter()
+        .map(|tracker_url| vec![tracker_url.clone()])
+        .collect();
+    editor.set_trackers(&tiers);
+    editor.set_web_seeds(link.get_web_seeds());
+    Ok(editor)
+}
+
+/// Fetches the info dict of a magnet link, piece by piece, moving on to the next peer when one
+/// fails. The pieces received so far are kept across peers, and the whole dict is checked
+/// against the info hashes of the link.
+fn fetch_metadata(link: &MagnetLink, peer_addresses: &[String]) -> Result<Box<[u8]>> {
+    let mut metadata: Option<Metadata> = None;
+    let mut last_error: Option<Error> = None;
+    for peer_address in peer_addresses {
+        if let Err(err) = fetch_metadata_from_peer(peer_address, link, &mut metadata) {
+            eprintln!("[Peer @{peer_address}] Could not fetch the metadata: {err:?}");
+            last_error = Some(err);
+            continue;
+        }
+        match metadata.take() {
+            Some(metadata) if metadata.verify(link.get_info_hash_v1(), link.get_info_hash_v2()) => {
+                return Ok(metadata.get_data().into());
+            }
+            _ => {
+                eprintln!("[Peer @{peer_address}] The metadata does not match the info hash.");
+                last_error = Some(Error::MetadataHashMismatch);
+            }
+        }
+    }
+    Err(last_error.unwrap_or(Error::MetadataUnavailable))
+}
+
+fn fetch_metadata_from_peer(
+    peer_address: &str,
+    link: &MagnetLink,
+    metadata: &mut Option<Metadata>,
+) -> Result<()> {
+    let (mut peer, _, extended_handshake) = magnet_handshake(peer_address, &link.get_info_hash())?;
+    let extended_handshake: ExtendedHandshake =
+        extended_handshake.ok_or(Error::KeyNotFoundInExtendedHandshakeResponse {
+            key: "ut_metadata".into(),
+        })?;
+    let size: i64 = extended_handshake.metadata_size.ok_or_else(|| {
+        Error::KeyNotFoundInExtendedHandshakeResponse {
+            key: "metadata_size".into(),
+        }
+    })?;
+    // A peer disagreeing on the size can not have the same metadata as the previous ones.
+    if metadata
+        .as_ref()
+        .is_none_or(|metadata| metadata.get_size() as i64 != size)
+    {
+        *metadata = Some(Metadata::new(size)?);
+    }
+    let metadata: &mut Metadata = metadata.as_mut().expect("the metadata has just been set");
+    for piece in metadata.get_missing_pieces() {
+        println!(
+            "[Peer @{}] Fetching metadata piece {}/{}...",
+            &peer.get_address(),
+            piece + 1,
+            metadata.get_pieces_count()
+        );
+        let data: Box<[u8]> = peer.request_metadata_piece(piece)?;
+        metadata.add_piece(piece, &data)?;
+    }
+    Ok(())
+}
+
 fn download_piece(peer: &mut Peer, piece: &Piece) -> Result<Box<[u8]>> {
     println!("Downloading piece #{}...", piece.get_index());
     let blocks_count: usize = piece.get_blocks().len();
@@ -408,24 +1092,21 @@ fn download_piece(peer: &mut Peer, piece: &Piece) -> Result<Box<[u8]>> {
     }
 
     println!(
-        "[Peer @{}] Downloaded piece #{}. Verifying SHA1 checksum...",
+        "[Peer @{}] Downloaded piece #{}. Verifying its hashes...",
         &peer.get_address(),
         piece.get_index()
     );
-    let mut hasher = Sha1::new();
-    hasher.update(piece_data.as_ref());
-    let sha1_hash: [u8; 20] = hasher.finalize().into();
-    if *piece.get_hash() != sha1_hash {
+    if !piece.verify(&piece_data) {
         println!(
-            "[Peer @{}] SHA1 for downloaded piece #{} does not match with torrent file.",
+            "[Peer @{}] The hashes of the downloaded piece #{} do not match with torrent file.",
             &peer.get_address(),
             piece.get_index()
         );
-        Err(Error::Unknown)? // TODO: set actual error
+        Err(Error::PieceVerificationFailed(piece.get_index()))?
     }
 
     println!(
-        "[Peer @{}] SHA1 for downloaded piece #{} matches with torrent file.",
+        "[Peer @{}] The hashes of the downloaded piece #{} match with torrent file.",
         &peer.get_address(),
         piece.get_index()
     );
@@ -433,31 +1114,79 @@ fn download_piece(peer: &mut Peer, piece: &Piece) -> Result<Box<[u8]>> {
     Ok(piece_data)
 }
 
-fn parse_torrent_from_file(path: &str) -> Result<Torrent> {
-    fs::read(path)
-        .map(|s: Vec<u8>| s.as_slice().try_into())
-        .map_err(|err| Error::FileError(err))?
-}
-
-fn reserve_space(file: &mut File, size_in_bytes: u64) -> Result<()> {
-    let mut file: BufWriter<&File> = BufWriter::new(file);
-    if size_in_bytes > BLOCK_SIZE as u64 {
-        let blocks_count: u64 = size_in_bytes / BLOCK_SIZE as u64;
-        let residue: u64 = size_in_bytes % BLOCK_SIZE as u64;
-        for _ in 0..blocks_count {
-            let bytes = vec![0u8; BLOCK_SIZE as usize];
-            file.write_all(&bytes)
-                .map_err(|err| Error::FileError(err))?;
+/// Fetches the piece layers missing from a v2 torrent, moving on to the next peer when one fails.
+/// The layers fetched so far are kept across peers.
+fn fetch_missing_piece_layers(torrent: &mut Torrent, peer_addresses: &[String]) -> Result<()> {
+    if torrent.get_missing_piece_layers().is_empty() {
+        return Ok(());
+    }
+    let mut last_error: Option<Error> = None;
+    for peer_address in peer_addresses {
+        match fetch_piece_layers_from_peer(torrent, peer_address) {
+            Ok(()) => return Ok(()),
+            Err(err) => {
+                eprintln!("[Peer @{peer_address}] Could not fetch the piece layers: {err:?}");
+                last_error = Some(err);
+            }
         }
-        if residue > 0 {
-            let bytes = vec![0u8; residue as usize];
-            file.write_all(&bytes)
-                .map_err(|err| Error::FileError(err))?;
+    }
+    Err(last_error.unwrap_or(Error::NoPeersAvailable))
+}
+
+/// Fetches the piece layers missing from a v2 torrent from the peer at `peer_address`, in
+/// requests of at most 512 hashes (BEP 52), each verified against the pieces root of its file.
+fn fetch_piece_layers_from_peer(torrent: &mut Torrent, peer_address: &str) -> Result<()> {
+    let missing: Vec<([u8; 32], u64)> = torrent
+        .get_missing_piece_layers()
+        .iter()
+        .filter_map(|file| Some((*file.get_pieces_root()?, file.get_length())))
+        .collect();
+    if missing.is_empty() {
+        return Ok(());
+    }
+
+    let mut peer = Peer::new(peer_address)?;
+    let handshake_message =
+        HandshakeMessage::new(&Rc::new(*torrent.get_info_hash()), &Rc::new(PEER_ID_BYTES))
+            .with_v2_support(torrent.get_version() != MetaVersion::V1);
+    peer.handshake(&handshake_message)?;
+    peer.receive_bitfield()?;
+
+    let piece_length: u64 = torrent.get_piece_length() as u64;
+    let base_layer: u32 = (piece_length / MERKLE_BLOCK_SIZE as u64).trailing_zeros();
+    for (pieces_root, file_length) in missing {
+        println!(
+            "[Peer @{}] Fetching the piece layer of {}...",
+            &peer.get_address(),
+            hex::encode(pieces_root)
+        );
+        let count: u64 = file_length.div_ceil(piece_length);
+        let length: u64 = count.next_power_of_two().min(512);
+        let mut layer: Vec<[u8; 32]> = Vec::with_capacity(count as usize);
+        for index in (0..count).step_by(length as usize) {
+            let request = HashRequest::new(pieces_root, base_layer, index as u32, length as u32, 0);
+            let proof_layers: u32 = proof_layers_count(&request, file_length)
+                .ok_or_else(|| Error::InvalidHashes(request.clone()))?;
+            let request = HashRequest::new(
+                pieces_root,
+                base_layer,
+                index as u32,
+                length as u32,
+                proof_layers,
+            );
+            let hashes: Box<[[u8; 32]]> = peer.request_hashes(&request)?;
+            let hashes: &[[u8; 32]] = verify_hashes(&request, file_length, &hashes)
+                .ok_or_else(|| Error::InvalidHashes(request.clone()))?;
+            layer.extend_from_slice(hashes);
         }
-    } else {
-        let bytes = vec![0u8; size_in_bytes as usize];
-        file.write_all(&bytes)
-            .map_err(|err| Error::FileError(err))?;
+        layer.truncate(count as usize);
+        torrent.add_piece_layer(&pieces_root, &layer)?;
     }
-    file.flush().map_err(|err| Error::FileError(err))
+    Ok(())
+}
+
+fn parse_torrent_from_file(path: &str) -> Result<Torrent> {
+    fs::read(path)
+        .map(|s: Vec<u8>| s.as_slice().try_into())
+        .map_err(Error::FileError)?
 }
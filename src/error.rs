@@ -2,13 +2,20 @@ use std::io;
 
 pub type Result<T> = core::result::Result<T, Error>;
 
-use crate::bencode::decoders;
+use crate::bencode::{decoders, serialization};
+use crate::magnet::MagnetLinkError;
 use crate::torrent::{self, MessageTag};
+use crate::types;
 
 #[derive(Debug)]
 pub enum Error {
     DecodeError(decoders::DecodeError),
+    ReadDecodeError(decoders::ReadDecodeError),
     FileError(io::Error),
+    HashRequestRejected(torrent::HashRequest),
+    JsonError(serde_json::Error),
+    JsonConversionError(types::JsonConversionError),
+    YamlError(serde_yaml::Error),
     TrackerHttpError(reqwest::Error),
     KeyNotFoundInTrackerResponse {
         key: String,
@@ -16,9 +23,6 @@ pub enum Error {
     KeyNotFoundInExtendedHandshakeResponse {
         key: String,
     },
-    TrackerIntervalIsNotInteger,
-    TrackerIntervalIsNotByteString,
-    TrackerFailureReasonIsNotUtf8,
     TrackerFailureInResponse {
         failure_reason: String,
     },
@@ -27,18 +31,53 @@ pub enum Error {
         actual_length: u32,
     },
     MessageParsingNotImplemented(MessageTag),
+    MessageTooLong {
+        maximum_length: u32,
+        actual_length: u32,
+    },
+    MetadataHashMismatch,
+    MetadataPieceRejected(u32),
+    MetadataUnavailable,
+    NoPeersAvailable,
+    PeerThreadPanicked,
+    PiecesNotDownloaded(usize),
+    PieceIndexOutOfRange {
+        index: usize,
+        pieces_count: usize,
+    },
+    PieceVerificationFailed(u32),
     NotEnoughData {
         minimum_length: u32,
         actual_length: u32,
     },
-    InvalidExtendedHandshakeResponse,
-    InvalidMagnetLink, // TODO
+    InvalidHashes(torrent::HashRequest),
+    InvalidExtendedMessage {
+        expected_id: u8,
+    },
+    InvalidMagnetLink(MagnetLinkError),
+    InvalidMetadataMessage(String),
+    InvalidMetadataPiece(u32),
+    InvalidMetadataSize(i64),
     InvalidPeerIdLength {
         peer_id: String,
         expected_length: u8,
     },
+    SerdeError(serialization::SerdeError),
     SocketError(io::Error),
-    TorrentParseError(String),
+    TorrentCreateError(String),
+    TorrentError(torrent::TorrentError),
     Unknown,
     UnrecognizedMessageTag(u8),
 }
+
+impl From<torrent::TorrentError> for Error {
+    fn from(err: torrent::TorrentError) -> Self {
+        Error::TorrentError(err)
+    }
+}
+
+impl From<MagnetLinkError> for Error {
+    fn from(err: MagnetLinkError) -> Self {
+        Error::InvalidMagnetLink(err)
+    }
+}
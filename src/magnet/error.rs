@@ -0,0 +1,26 @@
+use thiserror::Error;
+
+/// The reasons a magnet URI can be rejected for.
+#[derive(Error, Clone, Debug, PartialEq)]
+pub enum MagnetLinkError {
+    #[error("A magnet link must start with 'magnet:?'.")]
+    MissingPrefix,
+    #[error("Invalid query string: {0}")]
+    InvalidQuery(String),
+    #[error("Could not find any 'xt' parameter.")]
+    MissingExactTopic,
+    #[error("None of the 'xt' parameters is a BitTorrent info hash: {0:?}.")]
+    UnsupportedExactTopics(Vec<String>),
+    #[error("Invalid 'btih' info hash '{0}': expected 40 hex or 32 base32 characters.")]
+    InvalidInfoHash(String),
+    #[error("Invalid 'btmh' multihash '{0}': expected a hex encoded SHA-256 multihash.")]
+    InvalidMultihash(String),
+    #[error("Invalid 'xl' length '{0}'.")]
+    InvalidLength(String),
+    #[error("Invalid 'x.pe' peer address '{0}': expected 'host:port'.")]
+    InvalidPeerAddress(String),
+    #[error("The magnet link has no tracker to get peers from.")]
+    MissingTracker,
+    #[error("Invalid 'so' file selection '{0}': expected indices and ranges like '0,2,4-6'.")]
+    InvalidSelectOnly(String),
+}
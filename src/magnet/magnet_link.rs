@@ -0,0 +1,350 @@
+use std::ops::RangeInclusive;
+
+use crate::error::Result;
+use crate::magnet::MagnetLinkError;
+
+const PREFIX: &str = "magnet:?";
+const BTIH_PREFIX: &str = "urn:btih:";
+const BTMH_PREFIX: &str = "urn:btmh:";
+/// The multihash header of SHA-256 digests: the function code 0x12 and the digest length 0x20.
+const SHA256_MULTIHASH_HEADER: [u8; 2] = [0x12, 0x20];
+
+// region:      ---MagnetLink
+/// A magnet URI (BEP 9), with the v2 info hashes of BEP 52 and the file selection of BEP 53.
+#[derive(Debug, PartialEq)]
+pub struct MagnetLink {
+    info_hash_v1: Option<[u8; 20]>,
+    info_hash_v2: Option<[u8; 32]>,
+    /// 'dn': the display name, usually the name of the torrent.
+    name: Option<String>,
+    /// 'xl': the total length of the files.
+    length: Option<u64>,
+    /// 'tr', in order and without duplicates.
+    trackers: Vec<String>,
+    /// 'ws': the web seed URLs (BEP 19).
+    web_seeds: Vec<String>,
+    /// 'x.pe': the 'host:port' addresses of peers to connect to directly.
+    peer_addresses: Vec<String>,
+    /// 'so': the ranges of file indices to download, all of them when empty.
+    select_only: Vec<RangeInclusive<usize>>,
+}
+
+// region:      ---Constructors
+impl MagnetLink {
+    /// Parses a magnet URI, which must have a 'btih' (v1) or a 'btmh' (v2) exact topic, or both
+    /// for hybrid torrents. Parameters can be repeated or numbered ('tr.1', 'tr.2', ...), and
+    /// unknown parameters are ignored.
+    pub fn parse(input: &str) -> Result<MagnetLink> {
+        let query: &str = input
+            .strip_prefix(PREFIX)
+            .ok_or(MagnetLinkError::MissingPrefix)?;
+        let params: Vec<(String, String)> = serde_urlencoded::from_str(query)
+            .map_err(|err| MagnetLinkError::InvalidQuery(err.to_string()))?;
+
+        let mut link = MagnetLink {
+            info_hash_v1: None,
+            info_hash_v2: None,
+            name: None,
+            length: None,
+            trackers: Vec::new(),
+            web_seeds: Vec::new(),
+            peer_addresses: Vec::new(),
+            select_only: Vec::new(),
+        };
+        let mut topics: Vec<String> = Vec::new();
+        for (key, value) in params {
+            match base_key(&key) {
+                "xt" => topics.push(value),
+                "dn" if link.name.is_none() => link.name = Some(value),
+                "xl" => {
+                    let length: u64 = value
+                        .parse()
+                        .map_err(|_| MagnetLinkError::InvalidLength(value.clone()))?;
+                    link.length = Some(length);
+                }
+                "tr" => push_unique(&mut link.trackers, value),
+                "ws" => push_unique(&mut link.web_seeds, value),
+                "x.pe" if is_peer_address(&value) => push_unique(&mut link.peer_addresses, value),
+                "x.pe" => Err(MagnetLinkError::InvalidPeerAddress(value))?,
+                "so" => link.select_only.extend(parse_select_only(&value)?),
+                _ => {}
+            }
+        }
+
+        if topics.is_empty() {
+            Err(MagnetLinkError::MissingExactTopic)?
+        }
+        for topic in &topics {
+            if let Some(hash) = topic.strip_prefix(BTIH_PREFIX) {
+                if link.info_hash_v1.is_none() {
+                    link.info_hash_v1 = Some(parse_btih(hash)?);
+                }
+            } else if let Some(multihash) = topic.strip_prefix(BTMH_PREFIX) {
+                if link.info_hash_v2.is_none() {
+                    link.info_hash_v2 = Some(parse_btmh(multihash)?);
+                }
+            }
+        }
+        if link.info_hash_v1.is_none() && link.info_hash_v2.is_none() {
+            Err(MagnetLinkError::UnsupportedExactTopics(topics))?
+        }
+        Ok(link)
+    }
+}
+// endregion:   ---Constructors
+
+// region:      ---Getters
+impl MagnetLink {
+    pub fn get_info_hash_v1(&self) -> Option<&[u8; 20]> {
+        self.info_hash_v1.as_ref()
+    }
+
+    pub fn get_info_hash_v2(&self) -> Option<&[u8; 32]> {
+        self.info_hash_v2.as_ref()
+    }
+
+    /// The 20-byte info hash used with trackers and peers: the v1 one, or else the truncated v2
+    /// one (BEP 52).
+    pub fn get_info_hash(&self) -> [u8; 20] {
+        match (&self.info_hash_v1, &self.info_hash_v2) {
+            (Some(info_hash), _) => *info_hash,
+            (None, Some(info_hash)) => *info_hash.first_chunk().expect("32 > 20 bytes"),
+            (None, None) => unreachable!("parsed magnet links have an info hash"),
+        }
+    }
+
+    pub fn get_name(&self) -> Option<&str> {
+        self.name.as_deref()
+    }
+
+    pub fn get_length(&self) -> Option<u64> {
+        self.length
+    }
+
+    pub fn get_trackers(&self) -> &[String] {
+        &self.trackers
+    }
+
+    pub fn get_web_seeds(&self) -> &[String] {
+        &self.web_seeds
+    }
+
+    pub fn get_peer_addresses(&self) -> &[String] {
+        &self.peer_addresses
+    }
+
+    pub fn get_select_only(&self) -> &[RangeInclusive<usize>] {
+        &self.select_only
+    }
+
+    /// Whether the file at `file_index` is to be downloaded, which all files are when 'so' is
+    /// absent.
+    pub fn is_file_selected(&self, file_index: usize) -> bool {
+        self.select_only.is_empty()
+            || self
+                .select_only
+                .iter()
+                .any(|range| range.contains(&file_index))
+    }
+}
+// endregion:   ---Getters
+// endregion:   ---MagnetLink
+
+/// Maps numbered keys ('xt.1', 'tr.2', ...) to their base key.
+fn base_key(key: &str) -> &str {
+    match key.split_once('.') {
+        Some((base, number))
+            if !number.is_empty() && number.bytes().all(|byte| byte.is_ascii_digit()) =>
+        {
+            base
+        }
+        _ => key,
+    }
+}
+
+fn push_unique(values: &mut Vec<String>, value: String) {
+    if !values.contains(&value) {
+        values.push(value);
+    }
+}
+
+fn is_peer_address(address: &str) -> bool {
+    address
+        .rsplit_once(':')
+        .is_some_and(|(host, port)| !host.is_empty() && port.parse::<u16>().is_ok())
+}
+
+/// Parses a 'btih' info hash, either hex (40 characters) or base32 (32 characters) encoded.
+fn parse_btih(hash: &str) -> Result<[u8; 20]> {
+    let bytes: Option<Vec<u8>> = match hash.len() {
+        40 => hex::decode(hash).ok(),
+        32 => decode_base32(hash),
+        _ => None,
+    };
+    Ok(bytes
+        .and_then(|bytes| bytes.try_into().ok())
+        .ok_or_else(|| MagnetLinkError::InvalidInfoHash(hash.to_owned()))?)
+}
+
+/// Parses a 'btmh' multihash, which must be a SHA-256 one.
+fn parse_btmh(multihash: &str) -> Result<[u8; 32]> {
+    Ok(hex::decode(multihash)
+        .ok()
+        .and_then(|bytes| {
+            bytes
+                .strip_prefix(SHA256_MULTIHASH_HEADER.as_slice())
+                .and_then(|digest| digest.try_into().ok())
+        })
+        .ok_or_else(|| MagnetLinkError::InvalidMultihash(multihash.to_owned()))?)
+}
+
+/// Decodes unpadded RFC 4648 base32, case-insensitively.
+fn decode_base32(input: &str) -> Option<Vec<u8>> {
+    let mut bytes: Vec<u8> = Vec::with_capacity(input.len() * 5 / 8);
+    let mut buffer: u32 = 0;
+    let mut bits: u32 = 0;
+    for char in input.bytes() {
+        let value: u8 = match char.to_ascii_uppercase() {
+            upper @ b'A'..=b'Z' => upper - b'A',
+            digit @ b'2'..=b'7' => digit - b'2' + 26,
+            _ => return None,
+        };
+        buffer = (buffer << 5) | value as u32;
+        bits += 5;
+        if bits >= 8 {
+            bits -= 8;
+            bytes.push((buffer >> bits) as u8);
+            buffer &= (1 << bits) - 1;
+        }
+    }
+    Some(bytes)
+}
+
+/// Parses a BEP 53 file selection: comma separated file indices and inclusive ranges of them.
+fn parse_select_only(value: &str) -> Result<Vec<RangeInclusive<usize>>> {
+    let invalid = || MagnetLinkError::InvalidSelectOnly(value.to_owned());
+    let mut ranges: Vec<RangeInclusive<usize>> = Vec::new();
+    for item in value.split(',') {
+        let (first, last) = item.split_once('-').unwrap_or((item, item));
+        let first: usize = first.parse().map_err(|_| invalid())?;
+        let last: usize = last.parse().map_err(|_| invalid())?;
+        if first > last {
+            Err(invalid())?
+        }
+        ranges.push(first..=last);
+    }
+    Ok(ranges)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::Error;
+
+    const HEX_HASH: &str = "ad42ce8109f54c99613ce38f9b4d87e70f24a165";
+
+    fn parse_error(input: &str) -> MagnetLinkError {
+        match MagnetLink::parse(input) {
+            Err(Error::InvalidMagnetLink(err)) => err,
+            Err(err) => panic!("expected a magnet link error, got {err:?}"),
+            Ok(link) => panic!("expected a magnet link error, got {link:?}"),
+        }
+    }
+
+    #[test]
+    fn test_full_v1_link() {
+        let link = MagnetLink::parse(&format!(
+            "magnet:?xt=urn:btih:{HEX_HASH}&dn=magnet1.gif&tr=http%3A%2F%2Fa%2Fannounce&tr=udp%3A%2F%2Fb%3A80&tr=http%3A%2F%2Fa%2Fannounce&xl=629944&ws=http%3A%2F%2Fseed%2F&x.pe=10.0.0.1%3A6881&x.pe=%5B%3A%3A1%5D%3A51413"
+        ))
+        .unwrap();
+        assert_eq!(
+            link.get_info_hash_v1().map(hex::encode).as_deref(),
+            Some(HEX_HASH)
+        );
+        assert_eq!(link.get_info_hash_v2(), None);
+        assert_eq!(link.get_name(), Some("magnet1.gif"));
+        assert_eq!(link.get_length(), Some(629944));
+        assert_eq!(link.get_trackers(), ["http://a/announce", "udp://b:80"]);
+        assert_eq!(link.get_web_seeds(), ["http://seed/"]);
+        assert_eq!(link.get_peer_addresses(), ["10.0.0.1:6881", "[::1]:51413"]);
+        assert!(link.is_file_selected(42));
+    }
+
+    #[test]
+    fn test_bare_exact_topic() {
+        let link = MagnetLink::parse(&format!("magnet:?xt=urn:btih:{HEX_HASH}")).unwrap();
+        assert_eq!(link.get_name(), None);
+        assert!(link.get_trackers().is_empty());
+    }
+
+    #[test]
+    fn test_base32_info_hash() {
+        let hash: [u8; 20] = hex::decode(HEX_HASH).unwrap().try_into().unwrap();
+        let link =
+            MagnetLink::parse("magnet:?xt=urn:btih:VVBM5AIJ6VGJSYJ44OHZWTMH44HSJILF").unwrap();
+        assert_eq!(link.get_info_hash_v1(), Some(&hash));
+        let lowercase =
+            MagnetLink::parse("magnet:?xt=urn:btih:vvbm5aij6vgjsyj44ohzwtmh44hsjilf").unwrap();
+        assert_eq!(lowercase.get_info_hash(), hash);
+    }
+
+    #[test]
+    fn test_v2_and_hybrid_links() {
+        let digest: String = "ab".repeat(32);
+        let v2 = MagnetLink::parse(&format!("magnet:?xt=urn:btmh:1220{digest}")).unwrap();
+        assert_eq!(v2.get_info_hash_v1(), None);
+        assert_eq!(v2.get_info_hash_v2(), Some(&[0xab; 32]));
+        assert_eq!(v2.get_info_hash(), [0xab; 20]);
+
+        let hybrid = MagnetLink::parse(&format!(
+            "magnet:?xt.1=urn:btih:{HEX_HASH}&xt.2=urn:btmh:1220{digest}&tr.1=http%3A%2F%2Fa%2F"
+        ))
+        .unwrap();
+        assert!(hybrid.get_info_hash_v1().is_some());
+        assert_eq!(hybrid.get_info_hash_v2(), Some(&[0xab; 32]));
+        assert_eq!(hybrid.get_trackers(), ["http://a/"]);
+    }
+
+    #[test]
+    fn test_select_only() {
+        let link =
+            MagnetLink::parse(&format!("magnet:?xt=urn:btih:{HEX_HASH}&so=0,2,4-6")).unwrap();
+        assert_eq!(link.get_select_only(), [0..=0, 2..=2, 4..=6]);
+        let selected: Vec<usize> = (0..8).filter(|i| link.is_file_selected(*i)).collect();
+        assert_eq!(selected, [0, 2, 4, 5, 6]);
+    }
+
+    #[test]
+    fn test_invalid_links() {
+        let btih: String = format!("xt=urn:btih:{HEX_HASH}");
+        assert_eq!(parse_error("http://a/"), MagnetLinkError::MissingPrefix);
+        assert_eq!(
+            parse_error("magnet:?dn=a"),
+            MagnetLinkError::MissingExactTopic
+        );
+        assert_eq!(
+            parse_error("magnet:?xt=urn:ed2k:abc"),
+            MagnetLinkError::UnsupportedExactTopics(vec!["urn:ed2k:abc".to_owned()])
+        );
+        assert_eq!(
+            parse_error("magnet:?xt=urn:btih:abc"),
+            MagnetLinkError::InvalidInfoHash("abc".to_owned())
+        );
+        assert_eq!(
+            parse_error(&format!("magnet:?xt=urn:btmh:1114{}", "ab".repeat(20))),
+            MagnetLinkError::InvalidMultihash(format!("1114{}", "ab".repeat(20)))
+        );
+        assert_eq!(
+            parse_error(&format!("magnet:?{btih}&xl=-1")),
+            MagnetLinkError::InvalidLength("-1".to_owned())
+        );
+        assert_eq!(
+            parse_error(&format!("magnet:?{btih}&x.pe=host")),
+            MagnetLinkError::InvalidPeerAddress("host".to_owned())
+        );
+        assert_eq!(
+            parse_error(&format!("magnet:?{btih}&so=3-1")),
+            MagnetLinkError::InvalidSelectOnly("3-1".to_owned())
+        );
+    }
+}
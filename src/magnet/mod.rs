@@ -1,9 +1,12 @@
 // region:      --- Public Modules
-pub(crate) mod magnet_link_v1;
+pub(crate) mod error;
+pub(crate) mod magnet_link;
 // endregion:   --- Public Modules
 
 // region:      --- Modules
 // endregion:   --- Modules
 
 // region:      --- Flatten (private, crate, public)
+pub(crate) use error::*;
+pub(crate) use magnet_link::*;
 // endregion:   --- Flatten (private, crate, public)
@@ -5,12 +5,14 @@ mod magnet;
 mod torrent;
 mod types;
 
+use std::process::ExitCode;
+
 use clap::Parser;
 use cli::Cli;
 use torrent::Piece;
 
 use crate::bencode::decoders;
-use crate::error::Result;
+use crate::error::Error;
 use crate::torrent::tracker;
 use crate::torrent::HandshakeMessage;
 use crate::torrent::Peer;
@@ -18,7 +20,17 @@ use crate::torrent::Torrent;
 use crate::torrent::TrackerResponse;
 
 // Usage: your_bittorrent.sh decode "<encoded_value>"
-fn main() -> Result<()> {
+fn main() -> ExitCode {
     let cli: Cli = Cli::parse();
-    cli.command.handle()
+    match cli.command.handle() {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(Error::TorrentError(err)) => {
+            eprintln!("Invalid torrent: {err}");
+            ExitCode::FAILURE
+        }
+        Err(err) => {
+            eprintln!("Error: {err:?}");
+            ExitCode::FAILURE
+        }
+    }
 }
@@ -0,0 +1,504 @@
+use std::collections::BTreeMap;
+use std::fs::{self, File};
+use std::io;
+use std::num::NonZeroUsize;
+use std::os::unix::fs::FileExt;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::thread;
+
+use sha1::{Digest, Sha1};
+
+use crate::error::Error;
+use crate::error::Result;
+use crate::torrent::split_by_files;
+use crate::torrent::TorrentFile;
+use crate::types::{ByteString, DataType};
+
+const MIN_PIECE_LENGTH: u32 = 16 * 1024; // 16 KB
+const MAX_AUTO_PIECE_LENGTH: u32 = 16 * 1024 * 1024; // 16 MB
+/// The automatic piece length is the smallest one giving at most this many pieces.
+const TARGET_PIECES_COUNT: u64 = 1500;
+
+// region:      --- CreateOptions
+/// Everything about a new torrent besides its content.
+#[derive(Debug, Clone)]
+pub struct CreateOptions {
+    /// Must be a power of two of at least 16 KiB. Chosen from the content length when `None`.
+    pub piece_length: Option<u32>,
+    /// Tiers of tracker URLs. The first URL is set as 'announce', and 'announce-list' is only set
+    /// when there are more.
+    pub trackers: Vec<Vec<String>>,
+    /// URLs of HTTP servers hosting the content (BEP 19).
+    pub web_seeds: Vec<String>,
+    pub comment: Option<String>,
+    pub created_by: Option<String>,
+    /// Seconds since the Unix epoch.
+    pub creation_date: Option<i64>,
+    pub private: bool,
+    pub source: Option<String>,
+    /// The number of threads hashing the pieces.
+    pub threads: usize,
+}
+
+impl Default for CreateOptions {
+    fn default() -> Self {
+        CreateOptions {
+            piece_length: None,
+            trackers: Vec::new(),
+            web_seeds: Vec::new(),
+            comment: None,
+            created_by: None,
+            creation_date: None,
+            private: false,
+            source: None,
+            threads: thread::available_parallelism().map_or(1, NonZeroUsize::get),
+        }
+    }
+}
+// endregion:   --- CreateOptions
+
+/// A file of the content being turned into a torrent.
+struct SourceFile {
+    path: PathBuf,
+    /// The path relative to the content directory, empty for single-file torrents.
+    segments: Vec<String>,
+    length: u64,
+}
+
+/// Builds the metainfo dict of a torrent whose content is the file or the directory tree at
+/// `path`. Directories become multi-file torrents with their files in path order.
+pub fn create_torrent(path: &Path, options: &CreateOptions) -> Result<DataType> {
+    let path: PathBuf = path.canonicalize().map_err(Error::FileError)?;
+    let name: String = path
+        .file_name()
+        .and_then(|name| name.to_str())
+        .map(str::to_owned)
+        .ok_or_else(|| {
+            Error::TorrentCreateError(format!(
+                "Can not name a torrent after '{}'.",
+                path.display()
+            ))
+        })?;
+    let multi_file: bool = path.is_dir();
+    let sources: Vec<SourceFile> = if multi_file {
+        let mut sources: Vec<SourceFile> = Vec::new();
+        collect_files(&path, &mut Vec::new(), &mut sources)?;
+        if sources.is_empty() {
+            return Err(Error::TorrentCreateError(format!(
+                "There are no files in '{}'.",
+                path.display()
+            )));
+        }
+        sources
+    } else {
+        let length: u64 = fs::metadata(&path).map_err(Error::FileError)?.len();
+        vec![SourceFile {
+            path,
+            segments: Vec::new(),
+            length,
+        }]
+    };
+
+    let mut files: Vec<TorrentFile> = Vec::with_capacity(sources.len());
+    let mut length: u64 = 0;
+    for source in &sources {
+        files.push(TorrentFile::new(
+            source.length,
+            source.segments.clone().into(),
+            length,
+        ));
+        length += source.length;
+    }
+    let piece_length: u32 = match options.piece_length {
+        None => auto_piece_length(length),
+        Some(piece_length) if piece_length.is_power_of_two() && piece_length >= MIN_PIECE_LENGTH => {
+            piece_length
+        }
+        Some(piece_length) => {
+            return Err(Error::TorrentCreateError(format!(
+                "The piece length must be a power of two of at least {MIN_PIECE_LENGTH}, got {piece_length}."
+            )))
+        }
+    };
+    let paths: Vec<&Path> = sources.iter().map(|source| source.path.as_path()).collect();
+    let pieces: Vec<u8> = hash_pieces(&paths, &files, length, piece_length, options.threads)?;
+
+    let mut info: BTreeMap<ByteString, DataType> = BTreeMap::from([
+        ("name".into(), string(&name)),
+        (
+            "piece length".into(),
+            DataType::Integer(piece_length.into()),
+        ),
+        (
+            "pieces".into(),
+            DataType::ByteString(pieces.as_slice().into()),
+        ),
+    ]);
+    if multi_file {
+        let files: Vec<DataType> = sources
+            .iter()
+            .map(|source| {
+                let path: Vec<DataType> = source
+                    .segments
+                    .iter()
+                    .map(|segment| string(segment))
+                    .collect();
+                DataType::Dict(BTreeMap::from([
+                    ("length".into(), DataType::Integer(source.length as i64)),
+                    ("path".into(), DataType::List(path)),
+                ]))
+            })
+            .collect();
+        info.insert("files".into(), DataType::List(files));
+    } else {
+        info.insert("length".into(), DataType::Integer(length as i64));
+    }
+    if options.private {
+        info.insert("private".into(), DataType::Integer(1));
+    }
+    if let Some(source) = &options.source {
+        info.insert("source".into(), string(source));
+    }
+
+    let mut metainfo: BTreeMap<ByteString, DataType> =
+        BTreeMap::from([("info".into(), DataType::Dict(info))]);
+    let urls: Vec<&String> = options.trackers.iter().flatten().collect();
+    if let Some(announce) = urls.first() {
+        metainfo.insert("announce".into(), string(announce));
+    }
+    if urls.len() > 1 {
+        let tiers: Vec<DataType> = options
+            .trackers
+            .iter()
+            .filter(|tier| !tier.is_empty())
+            .map(|tier| DataType::List(tier.iter().map(|url| string(url)).collect()))
+            .collect();
+        metainfo.insert("announce-list".into(), DataType::List(tiers));
+    }
+    if !options.web_seeds.is_empty() {
+        let web_seeds: Vec<DataType> = options.web_seeds.iter().map(|url| string(url)).collect();
+        metainfo.insert("url-list".into(), DataType::List(web_seeds));
+    }
+    if let Some(comment) = &options.comment {
+        metainfo.insert("comment".into(), string(comment));
+    }
+    if let Some(created_by) = &options.created_by {
+        metainfo.insert("created by".into(), string(created_by));
+    }
+    if let Some(creation_date) = options.creation_date {
+        metainfo.insert("creation date".into(), DataType::Integer(creation_date));
+    }
+    Ok(DataType::Dict(metainfo))
+}
+
+fn string(value: &str) -> DataType {
+    DataType::ByteString(value.into())
+}
+
+/// Appends the files under `directory` to `sources`, sorted by their paths.
+///
+/// Symlinks are skipped, as they may point outside of `directory` or to one of its ancestors.
+fn collect_files(
+    directory: &Path,
+    segments: &mut Vec<String>,
+    sources: &mut Vec<SourceFile>,
+) -> Result<()> {
+    let mut entries: Vec<fs::DirEntry> = fs::read_dir(directory)
+        .and_then(|entries| entries.collect::<io::Result<Vec<fs::DirEntry>>>())
+        .map_err(Error::FileError)?;
+    entries.sort_by_key(|entry| entry.file_name());
+    for entry in entries {
+        let path: PathBuf = entry.path();
+        let name: String = entry.file_name().into_string().map_err(|_| {
+            Error::TorrentCreateError(format!("'{}' is not a valid UTF-8 path.", path.display()))
+        })?;
+        let metadata: fs::Metadata = fs::symlink_metadata(&path).map_err(Error::FileError)?;
+        if metadata.is_symlink() {
+            continue;
+        }
+        segments.push(name);
+        if metadata.is_dir() {
+            collect_files(&path, segments, sources)?;
+        } else {
+            sources.push(SourceFile {
+                path,
+                segments: segments.clone(),
+                length: metadata.len(),
+            });
+        }
+        segments.pop();
+    }
+    Ok(())
+}
+
+/// The smallest power of two giving at most `TARGET_PIECES_COUNT` pieces, within 16 KiB and
+/// 16 MiB.
+fn auto_piece_length(length: u64) -> u32 {
+    let mut piece_length: u32 = MIN_PIECE_LENGTH;
+    while piece_length < MAX_AUTO_PIECE_LENGTH
+        && length.div_ceil(piece_length as u64) > TARGET_PIECES_COUNT
+    {
+        piece_length *= 2;
+    }
+    piece_length
+}
+
+/// Computes the concatenated SHA-1 hashes of the pieces of the content made of `paths`, whose
+/// layout is given by `files`. The pieces are spread over `threads` threads.
+fn hash_pieces(
+    paths: &[&Path],
+    files: &[TorrentFile],
+    length: u64,
+    piece_length: u32,
+    threads: usize,
+) -> Result<Vec<u8>> {
+    let pieces_count: usize = length.div_ceil(piece_length as u64) as usize;
+    let handles: Vec<File> = paths
+        .iter()
+        .map(File::open)
+        .collect::<io::Result<Vec<File>>>()
+        .map_err(Error::FileError)?;
+    let next_piece = AtomicUsize::new(0);
+    let hashes: Mutex<Vec<[u8; 20]>> = Mutex::new(vec![[0; 20]; pieces_count]);
+
+    // `Error` is not `Send`, so the threads report `io::Error`s.
+    let hash_next_pieces = || -> io::Result<()> {
+        let mut buffer: Vec<u8> = vec![0; piece_length as usize];
+        loop {
+            let index: usize = next_piece.fetch_add(1, Ordering::Relaxed);
+            if index >= pieces_count {
+                return Ok(());
+            }
+            let begin: u64 = index as u64 * piece_length as u64;
+            let data: &mut [u8] = &mut buffer[..(length - begin).min(piece_length as u64) as usize];
+            for slice in split_by_files(files, begin, data.len()) {
+                let result = handles[slice.get_file_index()].read_exact_at(
+                    &mut data[slice.get_data_begin()..slice.get_data_end()],
+                    slice.get_file_offset(),
+                );
+                if let Err(err) = result {
+                    // Stops the other threads.
+                    next_piece.store(pieces_count, Ordering::Relaxed);
+                    return Err(err);
+                }
+            }
+            let hash: [u8; 20] = Sha1::digest(data).into();
+            hashes.lock().unwrap()[index] = hash;
+        }
+    };
+    let threads: usize = threads.clamp(1, pieces_count.max(1));
+    thread::scope(|scope| {
+        let workers: Vec<thread::ScopedJoinHandle<io::Result<()>>> = (0..threads)
+            .map(|_| scope.spawn(hash_next_pieces))
+            .collect();
+        workers
+            .into_iter()
+            .try_for_each(|worker| worker.join().expect("hashing threads do not panic"))
+    })
+    .map_err(Error::FileError)?;
+
+    Ok(hashes.into_inner().unwrap().concat())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bencode::encoders;
+    use crate::torrent::Torrent;
+
+    fn content(length: usize, seed: u8) -> Vec<u8> {
+        (0..length).map(|i| (i % 251) as u8 ^ seed).collect()
+    }
+
+    fn expected_pieces(content: &[u8], piece_length: usize) -> Vec<u8> {
+        content
+            .chunks(piece_length)
+            .flat_map(|chunk| <[u8; 20]>::from(Sha1::digest(chunk)))
+            .collect()
+    }
+
+    fn parse(metainfo: &DataType) -> Torrent {
+        let bytes: Vec<u8> = encoders::encode_to_vec(metainfo);
+        bytes.as_slice().try_into().unwrap()
+    }
+
+    fn options() -> CreateOptions {
+        CreateOptions {
+            piece_length: Some(MIN_PIECE_LENGTH),
+            trackers: vec![vec!["http://a/".to_owned()]],
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_single_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let data: Vec<u8> = content(40_000, 0);
+        fs::write(dir.path().join("a.bin"), &data).unwrap();
+
+        let metainfo: DataType = create_torrent(&dir.path().join("a.bin"), &options()).unwrap();
+        let info: &DataType = metainfo.get("info").unwrap();
+        assert_eq!(
+            info.get("pieces"),
+            Some(&DataType::ByteString(
+                expected_pieces(&data, MIN_PIECE_LENGTH as usize)
+                    .as_slice()
+                    .into()
+            ))
+        );
+        let torrent: Torrent = parse(&metainfo);
+        assert_eq!(torrent.get_name(), "a.bin");
+        assert_eq!(torrent.get_length(), 40_000);
+        assert_eq!(torrent.get_announce(), "http://a/");
+        assert!(!torrent.is_multi_file());
+        assert!(torrent.get_announce_list().is_empty());
+    }
+
+    #[test]
+    fn test_directory() {
+        let dir = tempfile::tempdir().unwrap();
+        let root: PathBuf = dir.path().join("root");
+        fs::create_dir_all(root.join("b/c")).unwrap();
+        let (first, second, third) = (content(10_000, 1), content(30_000, 2), content(5, 3));
+        fs::write(root.join("b/c/z.txt"), &second).unwrap();
+        fs::write(root.join("a.txt"), &first).unwrap();
+        fs::write(root.join("b/y.txt"), &third).unwrap();
+
+        let metainfo: DataType = create_torrent(&root, &options()).unwrap();
+        let torrent: Torrent = parse(&metainfo);
+        assert!(torrent.is_multi_file());
+        assert_eq!(torrent.get_name(), "root");
+        let paths: Vec<Vec<String>> = torrent
+            .get_files()
+            .iter()
+            .map(|file| file.get_path().to_vec())
+            .collect();
+        assert_eq!(
+            paths,
+            [vec!["a.txt"], vec!["b", "c", "z.txt"], vec!["b", "y.txt"]]
+        );
+        let concatenated: Vec<u8> = [first, second, third].concat();
+        let hashes: Vec<u8> = torrent
+            .get_pieces()
+            .iter()
+            .flat_map(|piece| *piece.get_hash().unwrap())
+            .collect();
+        assert_eq!(
+            hashes,
+            expected_pieces(&concatenated, MIN_PIECE_LENGTH as usize)
+        );
+    }
+
+    #[test]
+    fn test_symlinks_are_skipped() {
+        let dir = tempfile::tempdir().unwrap();
+        let root: PathBuf = dir.path().join("root");
+        fs::create_dir_all(root.join("b")).unwrap();
+        fs::write(root.join("a.txt"), b"hello").unwrap();
+        fs::write(dir.path().join("outside.txt"), b"secret").unwrap();
+        // A loop back to the root, and a file outside of it.
+        std::os::unix::fs::symlink(&root, root.join("b/loop")).unwrap();
+        std::os::unix::fs::symlink(dir.path().join("outside.txt"), root.join("c.txt")).unwrap();
+
+        let metainfo: DataType = create_torrent(&root, &options()).unwrap();
+        let torrent: Torrent = parse(&metainfo);
+        let paths: Vec<Vec<String>> = torrent
+            .get_files()
+            .iter()
+            .map(|file| file.get_path().to_vec())
+            .collect();
+        assert_eq!(paths, [vec!["a.txt"]]);
+        assert_eq!(torrent.get_length(), 5);
+    }
+
+    #[test]
+    fn test_thread_count_does_not_change_output() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("a.bin"), content(200_000, 4)).unwrap();
+        let path: PathBuf = dir.path().join("a.bin");
+        let single: DataType = create_torrent(
+            &path,
+            &CreateOptions {
+                threads: 1,
+                ..options()
+            },
+        )
+        .unwrap();
+        let parallel: DataType = create_torrent(
+            &path,
+            &CreateOptions {
+                threads: 8,
+                ..options()
+            },
+        )
+        .unwrap();
+        assert_eq!(single, parallel);
+    }
+
+    #[test]
+    fn test_optional_fields() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("a.bin"), b"hello").unwrap();
+        let options = CreateOptions {
+            trackers: vec![
+                vec!["http://a/".to_owned(), "http://b/".to_owned()],
+                vec!["http://c/".to_owned()],
+            ],
+            web_seeds: vec!["http://seed/".to_owned()],
+            comment: Some("hi".to_owned()),
+            created_by: Some("me".to_owned()),
+            creation_date: Some(1),
+            private: true,
+            source: Some("src".to_owned()),
+            ..options()
+        };
+        let metainfo: DataType = create_torrent(&dir.path().join("a.bin"), &options).unwrap();
+        assert_eq!(
+            metainfo.get("url-list"),
+            Some(&DataType::List(vec![string("http://seed/")]))
+        );
+        let torrent: Torrent = parse(&metainfo);
+        assert_eq!(torrent.get_announce(), "http://a/");
+        assert_eq!(
+            torrent.get_announce_list(),
+            [vec!["http://a/", "http://b/"], vec!["http://c/"]]
+        );
+        assert_eq!(torrent.get_comment(), Some("hi"));
+        assert_eq!(torrent.get_created_by(), Some("me"));
+        assert_eq!(torrent.get_creation_date(), Some(1));
+        assert!(torrent.is_private());
+        assert_eq!(torrent.get_source(), Some("src"));
+    }
+
+    #[test]
+    fn test_invalid_piece_length() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("a.bin"), b"hello").unwrap();
+        let options = CreateOptions {
+            piece_length: Some(20_000),
+            ..options()
+        };
+        let result = create_torrent(&dir.path().join("a.bin"), &options);
+        assert!(matches!(result, Err(Error::TorrentCreateError(_))));
+    }
+
+    #[test]
+    fn test_empty_directory() {
+        let dir = tempfile::tempdir().unwrap();
+        let result = create_torrent(dir.path(), &options());
+        assert!(matches!(result, Err(Error::TorrentCreateError(_))));
+    }
+
+    #[test]
+    fn test_auto_piece_length() {
+        assert_eq!(auto_piece_length(0), MIN_PIECE_LENGTH);
+        assert_eq!(auto_piece_length(1500 * 16 * 1024), MIN_PIECE_LENGTH);
+        assert_eq!(
+            auto_piece_length(1500 * 16 * 1024 + 1),
+            2 * MIN_PIECE_LENGTH
+        );
+        assert_eq!(auto_piece_length(u64::MAX / 2), MAX_AUTO_PIECE_LENGTH);
+    }
+}
@@ -0,0 +1,291 @@
+use std::collections::BTreeMap;
+use std::io::{self, Write};
+
+use crate::bencode::decoders;
+use crate::bencode::encoders::Encoder;
+use crate::error::Error;
+use crate::error::Result;
+use crate::torrent::TorrentError;
+use crate::types::{ByteString, DataType};
+
+const INFO_KEY: &[u8] = b"info";
+
+// region:      --- TorrentEditor
+/// Edits the keys of a .torrent file outside of its 'info' dict.
+///
+/// The 'info' dict is kept as the original bytes, so the info hash of the edited torrent is
+/// always the same, even if the dict was not canonically encoded.
+pub struct TorrentEditor {
+    /// The top-level entries, except for 'info'.
+    entries: BTreeMap<ByteString, DataType>,
+    raw_info: Box<[u8]>,
+}
+
+// region:      ---Constructors
+impl TorrentEditor {
+    pub fn parse(data: &[u8]) -> Result<TorrentEditor> {
+        let (mut dict, _) = decoders::decode_dict_with_spans(data).map_err(Error::DecodeError)?;
+        let raw_info: Box<[u8]> = match dict.remove(INFO_KEY) {
+            Some((DataType::Dict(_), span)) => data[span].into(),
+            _ => Err(TorrentError::MissingKey("info"))?,
+        };
+        let entries: BTreeMap<ByteString, DataType> = dict
+            .into_iter()
+            .map(|(key, (value, _))| (key, value))
+            .collect();
+        Ok(TorrentEditor { entries, raw_info })
+    }
+
+    /// Starts a torrent with nothing but the given bencoded 'info' dict, e.g. the metadata
+    /// fetched from peers for a magnet link.
+    pub fn from_raw_info(raw_info: &[u8]) -> Result<TorrentEditor> {
+        match decoders::decode(raw_info).map_err(Error::DecodeError)? {
+            (DataType::Dict(_), length) if length == raw_info.len() => Ok(TorrentEditor {
+                entries: BTreeMap::new(),
+                raw_info: raw_info.into(),
+            }),
+            _ => Err(TorrentError::InvalidMetaInfo(
+                "the 'info' value is not a dict".to_owned(),
+            ))?,
+        }
+    }
+}
+// endregion:   ---Constructors
+
+// region:      ---Getters
+impl TorrentEditor {
+    pub fn get_announce(&self) -> Option<String> {
+        self.get_string("announce")
+    }
+
+    /// The tracker tiers: the 'announce-list' tiers when there are some, or else the 'announce'
+    /// URL alone.
+    pub fn get_trackers(&self) -> Vec<Vec<String>> {
+        let tiers: Vec<Vec<String>> = match self.entries.get("announce-list".as_bytes()) {
+            Some(DataType::List(tiers)) => tiers
+                .iter()
+                .map(string_list)
+                .filter(|tier| !tier.is_empty())
+                .collect(),
+            _ => Vec::new(),
+        };
+        match (tiers.is_empty(), self.get_announce()) {
+            (true, Some(announce)) => vec![vec![announce]],
+            _ => tiers,
+        }
+    }
+
+    /// The web seed URLs (BEP 19), from a 'url-list' that is either a list or a single URL.
+    pub fn get_web_seeds(&self) -> Vec<String> {
+        match self.entries.get("url-list".as_bytes()) {
+            Some(DataType::ByteString(url)) if !url.get_data().is_empty() => {
+                vec![String::from_utf8_lossy(url.get_data()).into_owned()]
+            }
+            Some(value) => string_list(value),
+            None => Vec::new(),
+        }
+    }
+
+    pub fn get_comment(&self) -> Option<String> {
+        self.get_string("comment")
+    }
+
+    pub fn get_created_by(&self) -> Option<String> {
+        self.get_string("created by")
+    }
+
+    fn get_string(&self, key: &str) -> Option<String> {
+        self.entries.get(key.as_bytes()).and_then(DataType::as_str)
+    }
+}
+// endregion:   ---Getters
+
+// region:      ---Setters
+impl TorrentEditor {
+    pub fn set_announce(&mut self, url: Option<&str>) {
+        self.set_string("announce", url);
+    }
+
+    /// Replaces all the trackers. 'announce' is set to the first URL, and 'announce-list' is only
+    /// kept when there is more than one URL.
+    pub fn set_trackers(&mut self, tiers: &[Vec<String>]) {
+        let tiers: Vec<&Vec<String>> = tiers.iter().filter(|tier| !tier.is_empty()).collect();
+        let urls_count: usize = tiers.iter().map(|tier| tier.len()).sum();
+        self.set_announce(tiers.first().map(|tier| tier[0].as_str()));
+        if urls_count > 1 {
+            let tiers: Vec<DataType> = tiers.into_iter().map(|tier| list(tier)).collect();
+            self.entries
+                .insert("announce-list".into(), DataType::List(tiers));
+        } else {
+            self.entries.remove("announce-list".as_bytes());
+        }
+    }
+
+    pub fn set_web_seeds(&mut self, urls: &[String]) {
+        if urls.is_empty() {
+            self.entries.remove("url-list".as_bytes());
+        } else {
+            self.entries.insert("url-list".into(), list(urls));
+        }
+    }
+
+    pub fn set_comment(&mut self, comment: Option<&str>) {
+        self.set_string("comment", comment);
+    }
+
+    pub fn set_created_by(&mut self, created_by: Option<&str>) {
+        self.set_string("created by", created_by);
+    }
+
+    fn set_string(&mut self, key: &str, value: Option<&str>) {
+        match value {
+            Some(value) => self
+                .entries
+                .insert(key.into(), DataType::ByteString(value.into())),
+            None => self.entries.remove(key.as_bytes()),
+        };
+    }
+}
+// endregion:   ---Setters
+
+impl TorrentEditor {
+    /// Bencodes the edited torrent, with the original bytes of the 'info' dict.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut encoder = Encoder::new(Vec::with_capacity(self.raw_info.len() + 1024));
+        self.encode(&mut encoder)
+            .expect("writing to a vector can not fail");
+        encoder.into_inner()
+    }
+
+    fn encode<W: Write>(&self, encoder: &mut Encoder<W>) -> io::Result<()> {
+        encoder.encode_raw(b"d")?;
+        let mut info_written = false;
+        for (key, value) in &self.entries {
+            if !info_written && key.get_data().as_ref() > INFO_KEY {
+                encoder.encode_byte_string(INFO_KEY)?;
+                encoder.encode_raw(&self.raw_info)?;
+                info_written = true;
+            }
+            encoder.encode_byte_string(key.get_data())?;
+            encoder.encode(value)?;
+        }
+        if !info_written {
+            encoder.encode_byte_string(INFO_KEY)?;
+            encoder.encode_raw(&self.raw_info)?;
+        }
+        encoder.encode_raw(b"e")
+    }
+}
+// endregion:   --- TorrentEditor
+
+fn list(strings: &[String]) -> DataType {
+    DataType::List(
+        strings
+            .iter()
+            .map(|string| DataType::ByteString(string.as_str().into()))
+            .collect(),
+    )
+}
+
+/// The strings of a list, ignoring any other element.
+fn string_list(value: &DataType) -> Vec<String> {
+    match value {
+        DataType::List(list) => list.iter().filter_map(DataType::as_str).collect(),
+        _ => Vec::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::torrent::Torrent;
+
+    // The 'info' dict is not canonically encoded, so re-encoding it would change the info hash.
+    const TORRENT: &[u8] = b"d8:announce9:http://a/7:comment3:old4:infod6:pieces20:aaaaaaaaaaaaaaaaaaaa4:name1:a12:piece lengthi16e6:lengthi5ee8:url-list9:http://s/e";
+
+    #[test]
+    fn test_unchanged_round_trip() {
+        let editor = TorrentEditor::parse(TORRENT).unwrap();
+        assert_eq!(editor.to_bytes(), TORRENT);
+    }
+
+    #[test]
+    fn test_edits_preserve_info_hash() {
+        let original: Torrent = TORRENT.try_into().unwrap();
+        let mut editor = TorrentEditor::parse(TORRENT).unwrap();
+        editor.set_trackers(&[
+            vec!["http://b/".to_owned(), "http://c/".to_owned()],
+            vec!["http://d/".to_owned()],
+        ]);
+        editor.set_comment(None);
+        editor.set_created_by(Some("me"));
+        editor.set_web_seeds(&["http://s/".to_owned(), "http://t/".to_owned()]);
+
+        let bytes: Vec<u8> = editor.to_bytes();
+        assert!(bytes
+            .windows(editor.raw_info.len())
+            .any(|window| window == editor.raw_info.as_ref()));
+        let edited: Torrent = bytes.as_slice().try_into().unwrap();
+        assert_eq!(edited.get_info_hash(), original.get_info_hash());
+        assert_eq!(edited.get_announce(), "http://b/");
+        assert_eq!(
+            edited.get_announce_list(),
+            [vec!["http://b/", "http://c/"], vec!["http://d/"]]
+        );
+        assert_eq!(edited.get_comment(), None);
+        assert_eq!(edited.get_created_by(), Some("me"));
+
+        let editor = TorrentEditor::parse(&bytes).unwrap();
+        assert_eq!(editor.get_web_seeds(), ["http://s/", "http://t/"]);
+    }
+
+    #[test]
+    fn test_getters() {
+        let editor = TorrentEditor::parse(TORRENT).unwrap();
+        assert_eq!(editor.get_trackers(), [vec!["http://a/"]]);
+        assert_eq!(editor.get_web_seeds(), ["http://s/"]);
+        assert_eq!(editor.get_comment().as_deref(), Some("old"));
+        assert_eq!(editor.get_created_by(), None);
+    }
+
+    #[test]
+    fn test_single_tracker_drops_announce_list() {
+        let mut editor = TorrentEditor::parse(TORRENT).unwrap();
+        editor.set_trackers(&[vec!["http://a/".to_owned(), "http://b/".to_owned()]]);
+        editor.set_trackers(&[vec![], vec!["http://c/".to_owned()]]);
+        assert_eq!(editor.get_trackers(), [vec!["http://c/"]]);
+        assert!(!editor.entries.contains_key("announce-list".as_bytes()));
+    }
+
+    #[test]
+    fn test_from_raw_info() {
+        let original: Torrent = TORRENT.try_into().unwrap();
+        let raw_info: Box<[u8]> = TorrentEditor::parse(TORRENT).unwrap().raw_info;
+        let mut editor = TorrentEditor::from_raw_info(&raw_info).unwrap();
+        let trackerless: Torrent = editor.to_bytes().as_slice().try_into().unwrap();
+        assert_eq!(trackerless.get_info_hash(), original.get_info_hash());
+        assert_eq!(trackerless.get_announce(), "");
+
+        editor.set_trackers(&[vec!["http://a/".to_owned()]]);
+        let torrent: Torrent = editor.to_bytes().as_slice().try_into().unwrap();
+        assert_eq!(torrent.get_info_hash(), original.get_info_hash());
+        assert_eq!(torrent.get_announce(), "http://a/");
+
+        editor.set_web_seeds(&["http://s/".to_owned()]);
+        let editor = TorrentEditor::parse(&editor.to_bytes()).unwrap();
+        assert_eq!(editor.raw_info, raw_info);
+        assert_eq!(editor.get_web_seeds(), ["http://s/"]);
+
+        assert!(TorrentEditor::from_raw_info(b"le").is_err());
+        assert!(TorrentEditor::from_raw_info(b"de1:x").is_err());
+    }
+
+    #[test]
+    fn test_missing_info() {
+        let result = TorrentEditor::parse(b"d8:announce9:http://a/e");
+        assert!(matches!(
+            result,
+            Err(Error::TorrentError(TorrentError::MissingKey("info")))
+        ));
+    }
+}
@@ -0,0 +1,59 @@
+use thiserror::Error;
+
+/// The structural violations that make a .torrent file invalid.
+#[derive(Error, Clone, Debug, PartialEq)]
+pub enum TorrentError {
+    #[error("The metainfo does not have the expected layout: {0}")]
+    InvalidMetaInfo(String),
+    #[error("Could not find the '{0}' key.")]
+    MissingKey(&'static str),
+    #[error("Could not find either the 'length' or the 'files' key.")]
+    MissingLengthOrFiles,
+    #[error("Unsupported meta version {0}.")]
+    UnsupportedMetaVersion(i64),
+    #[error("The piece length must be positive and fit in 32 bits, got {0}.")]
+    InvalidPieceLength(i64),
+    #[error("The piece length of v2 torrents must be a power of two of at least 16384, got {0}.")]
+    InvalidV2PieceLength(u32),
+    #[error("The length of the 'pieces' byte string is {0}, which is not a multiple of 20.")]
+    InvalidPiecesLength(usize),
+    #[error("Expected {expected} piece hashes for {length} bytes in pieces of {piece_length} bytes, got {actual}.")]
+    PieceCountMismatch {
+        expected: u64,
+        actual: u64,
+        length: u64,
+        piece_length: u32,
+    },
+    #[error("The file length must not be negative, got {0}.")]
+    NegativeFileLength(i64),
+    #[error("The total length of the files is too large.")]
+    TotalLengthOverflow,
+    #[error("The name '{0}' is not a valid file name.")]
+    InvalidName(String),
+    #[error("The 'path' of the file entry #{0} is empty.")]
+    EmptyFilePath(usize),
+    #[error("The 'path' of the file entry #{0} has an invalid segment.")]
+    InvalidPathSegment(usize),
+    #[error("The symlink '{0}' has no valid 'symlink path'.")]
+    InvalidSymlinkPath(String),
+    #[error("The 'sha1' of the file '{0}' is not 20 bytes long.")]
+    InvalidFileSha1(String),
+    #[error("Invalid 'file tree': {0}")]
+    InvalidFileTree(String),
+    #[error("Invalid 'piece layers': {0}")]
+    InvalidPieceLayers(String),
+    #[error("The v1 and v2 lengths of the file '{0}' differ.")]
+    FileLengthMismatch(String),
+    #[error("The file '{0}' is missing from the 'file tree'.")]
+    MissingFromFileTree(String),
+    #[error("The file '{0}' of the 'file tree' is missing from the v1 files.")]
+    MissingFromFiles(String),
+    #[error("The file '{0}' does not start at a piece boundary.")]
+    UnalignedFile(String),
+    #[error("The piece layer of the file '{path}' does not have {expected} hashes.")]
+    PieceLayerLengthMismatch { path: String, expected: u64 },
+    #[error("The piece layer of the file '{0}' does not match its pieces root.")]
+    PieceLayerMismatch(String),
+    #[error("No file has the pieces root {0}.")]
+    UnknownPiecesRoot(String),
+}
@@ -0,0 +1,322 @@
+use std::fmt;
+
+// region:      --- FileAttributes
+/// The attributes of a file (BEP 47), from the characters of its 'attr' string.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct FileAttributes {
+    /// 'p': the file only aligns the next one to a piece boundary and is never written to disk.
+    padding: bool,
+    /// 'x'
+    executable: bool,
+    /// 'h'
+    hidden: bool,
+    /// 'l': the file is a symlink to its 'symlink path'.
+    symlink: bool,
+}
+
+// region:      ---Constructors
+impl FileAttributes {
+    /// Parses an 'attr' string, ignoring the characters of unknown attributes.
+    pub fn parse(attr: &str) -> FileAttributes {
+        FileAttributes {
+            padding: attr.contains('p'),
+            executable: attr.contains('x'),
+            hidden: attr.contains('h'),
+            symlink: attr.contains('l'),
+        }
+    }
+}
+// endregion:   ---Constructors
+
+// region:      ---Getters
+impl FileAttributes {
+    pub fn is_padding(&self) -> bool {
+        self.padding
+    }
+
+    pub fn is_executable(&self) -> bool {
+        self.executable
+    }
+
+    pub fn is_hidden(&self) -> bool {
+        self.hidden
+    }
+
+    pub fn is_symlink(&self) -> bool {
+        self.symlink
+    }
+}
+// endregion:   ---Getters
+
+impl fmt::Display for FileAttributes {
+    /// Writes the characters of the set attributes, as in an 'attr' string.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let flags: [(bool, char); 4] = [
+            (self.is_padding(), 'p'),
+            (self.is_executable(), 'x'),
+            (self.is_hidden(), 'h'),
+            (self.is_symlink(), 'l'),
+        ];
+        for (_, flag) in flags.iter().filter(|(is_set, _)| *is_set) {
+            write!(f, "{flag}")?;
+        }
+        Ok(())
+    }
+}
+// endregion:   --- FileAttributes
+
+// region:      --- TorrentFile
+pub struct TorrentFile {
+    length: u64,
+    path: Box<[String]>,
+    begin: u64,
+    /// The root of the merkle tree of the file's blocks, only known for v2 torrents (BEP 52).
+    pieces_root: Option<[u8; 32]>,
+    attributes: FileAttributes,
+    /// The target of a symlink, relative to the root directory of the torrent.
+    symlink_path: Option<Box<[String]>>,
+    /// The SHA-1 hash of the file's content, which some torrents give along with the pieces.
+    sha1: Option<[u8; 20]>,
+}
+
+// region:      ---Constructors
+impl TorrentFile {
+    pub fn new(length: u64, path: Box<[String]>, begin: u64) -> TorrentFile {
+        TorrentFile {
+            length,
+            path,
+            begin,
+            pieces_root: None,
+            attributes: FileAttributes::default(),
+            symlink_path: None,
+            sha1: None,
+        }
+    }
+
+    pub fn new_v2(
+        length: u64,
+        path: Box<[String]>,
+        begin: u64,
+        pieces_root: Option<[u8; 32]>,
+    ) -> TorrentFile {
+        TorrentFile {
+            length,
+            path,
+            begin,
+            pieces_root,
+            attributes: FileAttributes::default(),
+            symlink_path: None,
+            sha1: None,
+        }
+    }
+
+    pub fn with_attributes(
+        self,
+        attributes: FileAttributes,
+        symlink_path: Option<Box<[String]>>,
+        sha1: Option<[u8; 20]>,
+    ) -> TorrentFile {
+        TorrentFile {
+            attributes,
+            symlink_path,
+            sha1,
+            ..self
+        }
+    }
+
+    pub fn with_pieces_root(self, pieces_root: Option<[u8; 32]>) -> TorrentFile {
+        TorrentFile {
+            pieces_root,
+            ..self
+        }
+    }
+}
+// endregion:   ---Constructors
+
+// region:      ---Getters
+impl TorrentFile {
+    pub fn get_length(&self) -> u64 {
+        self.length
+    }
+
+    pub fn get_path(&self) -> &[String] {
+        &self.path
+    }
+
+    /// The offset of the first byte of the file within the concatenated torrent content.
+    pub fn get_begin(&self) -> u64 {
+        self.begin
+    }
+
+    pub fn get_end(&self) -> u64 {
+        self.begin + self.length
+    }
+
+    pub fn get_pieces_root(&self) -> Option<&[u8; 32]> {
+        self.pieces_root.as_ref()
+    }
+
+    pub fn get_attributes(&self) -> FileAttributes {
+        self.attributes
+    }
+
+    pub fn get_symlink_path(&self) -> Option<&[String]> {
+        self.symlink_path.as_deref()
+    }
+
+    pub fn get_sha1(&self) -> Option<&[u8; 20]> {
+        self.sha1.as_ref()
+    }
+}
+// endregion:   ---Getters
+// endregion:   --- TorrentFile
+
+// region:      --- FileSlice
+/// A part of a piece (or any other span of the torrent content) that belongs to a single file.
+#[derive(Debug, PartialEq)]
+pub struct FileSlice {
+    file_index: usize,
+    file_offset: u64,
+    data_begin: usize,
+    length: usize,
+}
+
+// region:      ---Getters
+impl FileSlice {
+    pub fn get_file_index(&self) -> usize {
+        self.file_index
+    }
+
+    pub fn get_file_offset(&self) -> u64 {
+        self.file_offset
+    }
+
+    pub fn get_data_begin(&self) -> usize {
+        self.data_begin
+    }
+
+    pub fn get_data_end(&self) -> usize {
+        self.data_begin + self.length
+    }
+}
+// endregion:   ---Getters
+
+/// Splits the content span `[begin, begin + length)` into slices of the files it overlaps.
+///
+/// `files` must be ordered by their offsets, as they are in the torrent's file table.
+pub fn split_by_files(files: &[TorrentFile], begin: u64, length: usize) -> Vec<FileSlice> {
+    let end: u64 = begin + length as u64;
+    files
+        .iter()
+        .enumerate()
+        .filter(|(_, file)| file.get_length() > 0)
+        .filter(|(_, file)| file.get_begin() < end && begin < file.get_end())
+        .map(|(file_index, file)| {
+            let slice_begin: u64 = begin.max(file.get_begin());
+            let slice_end: u64 = end.min(file.get_end());
+            FileSlice {
+                file_index,
+                file_offset: slice_begin - file.get_begin(),
+                data_begin: (slice_begin - begin) as usize,
+                length: (slice_end - slice_begin) as usize,
+            }
+        })
+        .collect()
+}
+// endregion:   --- FileSlice
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn files(lengths: &[u64]) -> Vec<TorrentFile> {
+        let mut begin: u64 = 0;
+        lengths
+            .iter()
+            .enumerate()
+            .map(|(i, length)| {
+                let file = TorrentFile::new(*length, [format!("file{i}")].into(), begin);
+                begin += length;
+                file
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_span_within_single_file() {
+        let slices = split_by_files(&files(&[100]), 10, 20);
+        assert_eq!(
+            slices,
+            vec![FileSlice {
+                file_index: 0,
+                file_offset: 10,
+                data_begin: 0,
+                length: 20
+            }]
+        );
+    }
+
+    #[test]
+    fn test_span_straddling_files() {
+        let slices = split_by_files(&files(&[10, 5, 30]), 8, 20);
+        assert_eq!(
+            slices,
+            vec![
+                FileSlice {
+                    file_index: 0,
+                    file_offset: 8,
+                    data_begin: 0,
+                    length: 2
+                },
+                FileSlice {
+                    file_index: 1,
+                    file_offset: 0,
+                    data_begin: 2,
+                    length: 5
+                },
+                FileSlice {
+                    file_index: 2,
+                    file_offset: 0,
+                    data_begin: 7,
+                    length: 13
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_file_attributes() {
+        let attributes = FileAttributes::parse("xh");
+        assert!(attributes.is_executable() && attributes.is_hidden());
+        assert!(!attributes.is_padding() && !attributes.is_symlink());
+        assert!(FileAttributes::parse("p").is_padding());
+        assert!(FileAttributes::parse("l?").is_symlink());
+        assert_eq!(FileAttributes::parse(""), FileAttributes::default());
+        assert_eq!(attributes.to_string(), "xh");
+        assert_eq!(FileAttributes::parse("lhq").to_string(), "hl");
+    }
+
+    #[test]
+    fn test_empty_files_are_skipped() {
+        let slices = split_by_files(&files(&[4, 0, 4]), 0, 8);
+        assert_eq!(slices.len(), 2);
+        assert_eq!(slices[0].get_file_index(), 0);
+        assert_eq!(slices[1].get_file_index(), 2);
+        assert_eq!(slices[1].get_data_begin(), 4);
+        assert_eq!(slices[1].get_data_end(), 8);
+    }
+
+    #[test]
+    fn test_span_starting_at_file_boundary() {
+        let slices = split_by_files(&files(&[10, 10]), 10, 10);
+        assert_eq!(
+            slices,
+            vec![FileSlice {
+                file_index: 1,
+                file_offset: 0,
+                data_begin: 0,
+                length: 10
+            }]
+        );
+    }
+}
@@ -0,0 +1,221 @@
+use std::collections::BTreeMap;
+
+use crate::error::Error;
+use crate::error::Result;
+use crate::torrent::is_safe_path_segment;
+use crate::torrent::TorrentError;
+use crate::types::{ByteString, DataType};
+
+// region:      --- FileTreeEntry
+/// A file of the 'file tree' dict of a v2 torrent (BEP 52).
+#[derive(Debug, PartialEq)]
+pub struct FileTreeEntry {
+    path: Box<[String]>,
+    length: u64,
+    /// The root of the merkle tree of the file's 16 KiB blocks, absent for empty files.
+    pieces_root: Option<[u8; 32]>,
+}
+
+// region:      ---Getters
+impl FileTreeEntry {
+    pub fn get_path(&self) -> &[String] {
+        &self.path
+    }
+
+    pub fn get_length(&self) -> u64 {
+        self.length
+    }
+
+    pub fn get_pieces_root(&self) -> Option<&[u8; 32]> {
+        self.pieces_root.as_ref()
+    }
+}
+// endregion:   ---Getters
+// endregion:   --- FileTreeEntry
+
+/// Flattens the 'file tree' dict into its files, in path order.
+///
+/// Directories are dicts keyed by the names of their children, while files are dicts with a
+/// single empty key, mapped to the 'length' and 'pieces root' of the file.
+pub fn parse_file_tree(file_tree: &DataType) -> Result<Vec<FileTreeEntry>> {
+    let root: &BTreeMap<ByteString, DataType> = file_tree
+        .as_dict()
+        .ok_or_else(|| tree_error("The 'file tree' value is not a dict.".to_owned()))?;
+    let mut entries: Vec<FileTreeEntry> = Vec::new();
+    parse_directory(root, &mut Vec::new(), &mut entries)?;
+    if entries.is_empty() {
+        return Err(tree_error("The 'file tree' dict has no files.".to_owned()));
+    }
+    Ok(entries)
+}
+
+fn parse_directory(
+    directory: &BTreeMap<ByteString, DataType>,
+    path: &mut Vec<String>,
+    entries: &mut Vec<FileTreeEntry>,
+) -> Result<()> {
+    for (name, child) in directory {
+        let name: &str = name
+            .as_str()
+            .filter(|name| is_safe_path_segment(name))
+            .ok_or_else(|| {
+                tree_error(format!(
+                    "Invalid path segment '{name}' in the 'file tree' dict at '{}'.",
+                    path.join("/")
+                ))
+            })?;
+        path.push(name.to_owned());
+        let child: &BTreeMap<ByteString, DataType> = child.as_dict().ok_or_else(|| {
+            tree_error(format!("The node at '{}' is not a dict.", path.join("/")))
+        })?;
+        match child.get(b"".as_slice()) {
+            Some(file) if child.len() == 1 => entries.push(parse_file(file, path)?),
+            Some(_) => {
+                return Err(tree_error(format!(
+                    "The file at '{}' has other keys than ''.",
+                    path.join("/")
+                )))
+            }
+            None => parse_directory(child, path, entries)?,
+        }
+        path.pop();
+    }
+    Ok(())
+}
+
+fn parse_file(file: &DataType, path: &[String]) -> Result<FileTreeEntry> {
+    let length: u64 = file
+        .get("length")
+        .and_then(DataType::as_i64)
+        .and_then(|length| u64::try_from(length).ok())
+        .ok_or_else(|| {
+            tree_error(format!(
+                "The file at '{}' has no valid 'length'.",
+                path.join("/")
+            ))
+        })?;
+    let pieces_root: Option<[u8; 32]> = file
+        .get("pieces root")
+        .and_then(DataType::as_byte_string)
+        .and_then(|root| <[u8; 32]>::try_from(root.get_data().as_ref()).ok());
+    if length > 0 && pieces_root.is_none() {
+        return Err(tree_error(format!(
+            "The file at '{}' has no valid 'pieces root'.",
+            path.join("/")
+        )));
+    }
+    Ok(FileTreeEntry {
+        path: path.into(),
+        length,
+        pieces_root: pieces_root.filter(|_| length > 0),
+    })
+}
+
+/// Maps the pieces roots of the files larger than a piece to the roots of the merkle subtrees of
+/// their pieces.
+pub type PieceLayers = BTreeMap<[u8; 32], Box<[[u8; 32]]>>;
+
+/// Parses the 'piece layers' dict, whose values are the concatenated hashes of the layers.
+pub fn parse_piece_layers(piece_layers: &DataType) -> Result<PieceLayers> {
+    let dict: &BTreeMap<ByteString, DataType> = piece_layers
+        .as_dict()
+        .ok_or_else(|| layers_error("The 'piece layers' value is not a dict.".to_owned()))?;
+    let mut layers: PieceLayers = BTreeMap::new();
+    for (root, hashes) in dict {
+        let root: [u8; 32] = root.get_data().as_ref().try_into().map_err(|_| {
+            layers_error("A 'piece layers' key is not a 32-byte pieces root.".to_owned())
+        })?;
+        let hashes: &[u8] = hashes
+            .as_byte_string()
+            .map(|hashes| hashes.get_data().as_ref())
+            .filter(|hashes| !hashes.is_empty() && hashes.len().is_multiple_of(32))
+            .ok_or_else(|| {
+                layers_error(format!(
+                    "The piece layer of {} is not a list of 32-byte hashes.",
+                    hex::encode(root)
+                ))
+            })?;
+        let hashes: Box<[[u8; 32]]> = hashes
+            .chunks_exact(32)
+            .map(|hash| hash.try_into().expect("chunks are 32 bytes long"))
+            .collect();
+        layers.insert(root, hashes);
+    }
+    Ok(layers)
+}
+
+fn tree_error(message: String) -> Error {
+    TorrentError::InvalidFileTree(message).into()
+}
+
+fn layers_error(message: String) -> Error {
+    TorrentError::InvalidPieceLayers(message).into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bencode::decoders;
+
+    fn decode(bencoded: &[u8]) -> DataType {
+        decoders::decode(bencoded).unwrap().0
+    }
+
+    #[test]
+    fn test_nested_files_in_path_order() {
+        let root: String = "r".repeat(32);
+        let file_tree: DataType = decode(
+            format!(
+                "d1:bd1:cd0:d6:lengthi40000e11:pieces root32:{root}ee1:ed0:d6:lengthi0eeee1:ad0:d6:lengthi5e11:pieces root32:{root}eee"
+            )
+            .as_bytes(),
+        );
+        let entries: Vec<FileTreeEntry> = parse_file_tree(&file_tree).unwrap();
+        let paths: Vec<Vec<String>> = entries
+            .iter()
+            .map(|entry| entry.get_path().to_vec())
+            .collect();
+        assert_eq!(paths, [vec!["a"], vec!["b", "c"], vec!["b", "e"]]);
+        assert_eq!(entries[1].get_length(), 40000);
+        assert_eq!(entries[1].get_pieces_root(), Some(&[b'r'; 32]));
+        assert_eq!(entries[2].get_pieces_root(), None);
+    }
+
+    #[test]
+    fn test_invalid_file_trees() {
+        let root: String = "r".repeat(32);
+        let invalid: [String; 5] = [
+            "de".to_owned(),
+            format!("d2:..d0:d6:lengthi5e11:pieces root32:{root}eee"),
+            "d1:ad0:d6:lengthi5eeee".to_owned(),
+            "d1:ad0:d6:lengthi-1eeee".to_owned(),
+            format!("d1:ad0:d6:lengthi5e11:pieces root32:{root}e1:xi1eee"),
+        ];
+        for file_tree in invalid {
+            let result = parse_file_tree(&decode(file_tree.as_bytes()));
+            assert!(
+                matches!(
+                    result,
+                    Err(Error::TorrentError(TorrentError::InvalidFileTree(_)))
+                ),
+                "{file_tree}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_piece_layers() {
+        let layers: DataType = decode(
+            format!(
+                "d32:{}64:{}{}e",
+                "r".repeat(32),
+                "a".repeat(32),
+                "b".repeat(32)
+            )
+            .as_bytes(),
+        );
+        let layers = parse_piece_layers(&layers).unwrap();
+        assert_eq!(layers[&[b'r'; 32]].as_ref(), [[b'a'; 32], [b'b'; 32]]);
+        assert!(parse_piece_layers(&decode(b"d3:abc3:defe")).is_err());
+    }
+}
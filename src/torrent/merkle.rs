@@ -0,0 +1,221 @@
+//! The merkle trees of v2 torrents (BEP 52).
+//!
+//! Every file has its own tree, whose leaves are the SHA-256 hashes of the 16 KiB blocks of the
+//! file. The leaves past the end of the file, needed to make a power of two, are zero hashes.
+
+use sha2::{Digest, Sha256};
+
+use crate::torrent::HashRequest;
+
+/// The size of the blocks hashed into the leaves of the merkle trees.
+pub const MERKLE_BLOCK_SIZE: usize = 16 * 1024;
+
+// region:      --- MerkleRoot
+/// The root of the merkle subtree a piece of a v2 torrent is verified against.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct MerkleRoot {
+    hash: [u8; 32],
+    /// The number of leaves of the subtree: the blocks of a piece for files larger than a piece,
+    /// or else the blocks of the file rounded up to a power of two.
+    leaves_count: u32,
+}
+
+// region:      ---Constructors
+impl MerkleRoot {
+    pub fn new(hash: [u8; 32], leaves_count: u32) -> MerkleRoot {
+        MerkleRoot { hash, leaves_count }
+    }
+}
+// endregion:   ---Constructors
+
+// region:      ---Getters
+impl MerkleRoot {
+    pub fn get_hash(&self) -> &[u8; 32] {
+        &self.hash
+    }
+}
+// endregion:   ---Getters
+
+impl MerkleRoot {
+    /// Whether the blocks of `data` hash up to the root.
+    pub fn verify(&self, data: &[u8]) -> bool {
+        let leaves_count: usize = self.leaves_count as usize;
+        data.len().div_ceil(MERKLE_BLOCK_SIZE) <= leaves_count
+            && merkle_root(&block_hashes(data), leaves_count, &[0; 32]) == self.hash
+    }
+}
+// endregion:   --- MerkleRoot
+
+/// The SHA-256 hashes of the 16 KiB blocks of `data`, the last one being possibly shorter.
+pub fn block_hashes(data: &[u8]) -> Vec<[u8; 32]> {
+    data.chunks(MERKLE_BLOCK_SIZE)
+        .map(|block| Sha256::digest(block).into())
+        .collect()
+}
+
+pub fn hash_pair(left: &[u8; 32], right: &[u8; 32]) -> [u8; 32] {
+    let mut hasher = Sha256::new();
+    hasher.update(left);
+    hasher.update(right);
+    hasher.finalize().into()
+}
+
+/// The root of a subtree of `leaves_count` zero leaves, which pads the layers above the leaves.
+pub fn pad_hash(leaves_count: usize) -> [u8; 32] {
+    let mut hash: [u8; 32] = [0; 32];
+    for _ in 0..leaves_count.trailing_zeros() {
+        hash = hash_pair(&hash, &hash);
+    }
+    hash
+}
+
+/// The root of a tree of `width` nodes (a power of two), the first ones being `hashes` and the
+/// other ones `padding`.
+pub fn merkle_root(hashes: &[[u8; 32]], width: usize, padding: &[u8; 32]) -> [u8; 32] {
+    debug_assert!(width.is_power_of_two() && hashes.len() <= width);
+    let mut layer: Vec<[u8; 32]> = hashes.to_vec();
+    let mut padding: [u8; 32] = *padding;
+    let mut width: usize = width;
+    while width > 1 {
+        layer = layer
+            .chunks(2)
+            .map(|pair| hash_pair(&pair[0], pair.get(1).unwrap_or(&padding)))
+            .collect();
+        padding = hash_pair(&padding, &padding);
+        width /= 2;
+    }
+    layer.first().copied().unwrap_or(padding)
+}
+
+/// Whether the hashes of a piece layer hash up to the pieces root of their file.
+pub fn verify_piece_layer(pieces_root: &[u8; 32], layer: &[[u8; 32]], piece_length: u32) -> bool {
+    let padding: [u8; 32] = pad_hash(piece_length as usize / MERKLE_BLOCK_SIZE);
+    merkle_root(layer, layer.len().next_power_of_two(), &padding) == *pieces_root
+}
+
+/// The number of uncle hashes needed to verify the hashes requested by `request` against the
+/// pieces root of a file of `file_length` bytes, or `None` when the request does not fit the tree.
+pub fn proof_layers_count(request: &HashRequest, file_length: u64) -> Option<u32> {
+    let node_size: u64 = (MERKLE_BLOCK_SIZE as u64).checked_shl(request.get_base_layer())?;
+    let width: u64 = file_length.div_ceil(node_size).max(1).next_power_of_two();
+    let length: u64 = request.get_length() as u64;
+    let index: u64 = request.get_index() as u64;
+    if !length.is_power_of_two()
+        || length > width
+        || !index.is_multiple_of(length)
+        || index >= width
+    {
+        return None;
+    }
+    Some(width.trailing_zeros() - length.trailing_zeros())
+}
+
+/// Verifies the hashes of a 'hashes' message answering `request` for a file of `file_length`
+/// bytes: the requested hashes of the base layer followed by the uncle hashes of their subtree,
+/// from the bottom up, which must hash up to the pieces root. Returns the base layer hashes.
+pub fn verify_hashes<'a>(
+    request: &HashRequest,
+    file_length: u64,
+    hashes: &'a [[u8; 32]],
+) -> Option<&'a [[u8; 32]]> {
+    let proof_layers: u32 = proof_layers_count(request, file_length)?;
+    let length: usize = request.get_length() as usize;
+    if request.get_proof_layers() < proof_layers || hashes.len() != length + proof_layers as usize {
+        return None;
+    }
+    let (base, uncles) = hashes.split_at(length);
+    let padding: [u8; 32] = pad_hash(1 << request.get_base_layer());
+    let mut hash: [u8; 32] = merkle_root(base, length, &padding);
+    let mut position: u32 = request.get_index() / request.get_length();
+    for uncle in uncles {
+        hash = if position.is_multiple_of(2) {
+            hash_pair(&hash, uncle)
+        } else {
+            hash_pair(uncle, &hash)
+        };
+        position /= 2;
+    }
+    (hash == *request.get_pieces_root()).then_some(base)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn layer(count: u8) -> Vec<[u8; 32]> {
+        (0..count).map(|i| [i + 1; 32]).collect()
+    }
+
+    #[test]
+    fn test_pad_hash() {
+        assert_eq!(pad_hash(1), [0; 32]);
+        assert_eq!(pad_hash(4), hash_pair(&pad_hash(2), &pad_hash(2)));
+        assert_eq!(merkle_root(&[], 8, &[0; 32]), pad_hash(8));
+    }
+
+    #[test]
+    fn test_merkle_root_pads_with_zero_hashes() {
+        let hashes: Vec<[u8; 32]> = layer(3);
+        let expected: [u8; 32] = hash_pair(
+            &hash_pair(&hashes[0], &hashes[1]),
+            &hash_pair(&hashes[2], &[0; 32]),
+        );
+        assert_eq!(merkle_root(&hashes, 4, &[0; 32]), expected);
+        assert_eq!(merkle_root(&hashes[..1], 1, &[0; 32]), hashes[0]);
+    }
+
+    #[test]
+    fn test_verify_piece() {
+        let data: Vec<u8> = (0..40000).map(|i| i as u8).collect();
+        let blocks: Vec<[u8; 32]> = block_hashes(&data);
+        assert_eq!(blocks.len(), 3);
+
+        // A file smaller than a piece is rounded up to 4 blocks...
+        let root = MerkleRoot::new(merkle_root(&blocks, 4, &[0; 32]), 4);
+        assert!(root.verify(&data));
+        assert!(!root.verify(&data[1..]));
+        // ...while the last piece of a larger file is padded to the blocks of a piece.
+        let root = MerkleRoot::new(merkle_root(&blocks, 8, &[0; 32]), 8);
+        assert!(root.verify(&data));
+        assert!(!MerkleRoot::new(*root.get_hash(), 2).verify(&data));
+    }
+
+    #[test]
+    fn test_verify_piece_layer() {
+        // Pieces of 2 blocks, so the missing piece hashes are the roots of 2 zero blocks.
+        let hashes: Vec<[u8; 32]> = layer(3);
+        let root: [u8; 32] = hash_pair(
+            &hash_pair(&hashes[0], &hashes[1]),
+            &hash_pair(&hashes[2], &pad_hash(2)),
+        );
+        assert!(verify_piece_layer(&root, &hashes, 32768));
+        assert!(!verify_piece_layer(&root, &hashes, 16384));
+        assert!(!verify_piece_layer(&root, &hashes[..2], 32768));
+    }
+
+    #[test]
+    fn test_verify_hashes() {
+        // A file of 8 pieces of 1 block, whose hashes 4..6 are requested with their proof.
+        let hashes: Vec<[u8; 32]> = layer(8);
+        let left: [u8; 32] = merkle_root(&hashes[..4], 4, &[0; 32]);
+        let right: [u8; 32] = merkle_root(&hashes[4..], 4, &[0; 32]);
+        let pieces_root: [u8; 32] = hash_pair(&left, &right);
+        let file_length: u64 = 8 * MERKLE_BLOCK_SIZE as u64;
+        let request = HashRequest::new(pieces_root, 0, 4, 2, 2);
+        assert_eq!(proof_layers_count(&request, file_length), Some(2));
+
+        let uncles: [[u8; 32]; 2] = [hash_pair(&hashes[6], &hashes[7]), left];
+        let message: Vec<[u8; 32]> = [&hashes[4..6], &uncles].concat();
+        assert_eq!(
+            verify_hashes(&request, file_length, &message),
+            Some(&hashes[4..6])
+        );
+
+        let mut tampered: Vec<[u8; 32]> = message.clone();
+        tampered[3] = right;
+        assert_eq!(verify_hashes(&request, file_length, &tampered), None);
+        assert_eq!(verify_hashes(&request, file_length, &message[..3]), None);
+        let misaligned = HashRequest::new(pieces_root, 0, 3, 2, 2);
+        assert_eq!(verify_hashes(&misaligned, file_length, &message), None);
+    }
+}
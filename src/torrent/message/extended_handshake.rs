@@ -0,0 +1,14 @@
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+// region:      --- ExtendedHandshake
+/// The bencoded payload of the extension protocol handshake (BEP 10).
+#[derive(Deserialize, Serialize, Debug, Default, PartialEq)]
+pub struct ExtendedHandshake {
+    /// Maps the names of the supported extensions to the IDs their messages are expected with.
+    pub m: BTreeMap<String, u8>,
+    /// The size of the info dict in bytes (BEP 9), if the sender has it.
+    pub metadata_size: Option<i64>,
+}
+// endregion:   --- ExtendedHandshake
@@ -6,8 +6,15 @@ pub struct HandshakeMessage {
     info_hash: Rc<[u8; 20]>,
     peer_id: Rc<[u8; 20]>,
     extension_supported: bool,
+    /// Whether v2 torrents are supported (BEP 52), from the reserved bit of the upgrade to v2.
+    v2_supported: bool,
 }
 
+/// The reserved byte and bit telling that the extension protocol is supported (BEP 10).
+const EXTENSION_BIT: (usize, u8) = (5, 0x10);
+/// The reserved byte and bit telling that v2 torrents are supported (BEP 52).
+const V2_BIT: (usize, u8) = (7, 0x10);
+
 // region:      --- Constructors
 impl HandshakeMessage {
     pub fn new(info_hash: &Rc<[u8; 20]>, peer_id: &Rc<[u8; 20]>) -> HandshakeMessage {
@@ -15,6 +22,7 @@ impl HandshakeMessage {
             info_hash: Rc::clone(info_hash),
             peer_id: Rc::clone(peer_id),
             extension_supported: false,
+            v2_supported: false,
         }
     }
 
@@ -23,6 +31,15 @@ impl HandshakeMessage {
             info_hash: Rc::clone(info_hash),
             peer_id: Rc::clone(peer_id),
             extension_supported: true,
+            v2_supported: false,
+        }
+    }
+
+    /// Sets whether we support v2 torrents, which v2 peers may require to answer hash requests.
+    pub fn with_v2_support(self, v2_supported: bool) -> HandshakeMessage {
+        HandshakeMessage {
+            v2_supported,
+            ..self
         }
     }
 }
@@ -41,6 +58,10 @@ impl HandshakeMessage {
     pub fn is_extension_supported(&self) -> bool {
         self.extension_supported
     }
+
+    pub fn is_v2_supported(&self) -> bool {
+        self.v2_supported
+    }
 }
 // endregion:   --- Getters
 
@@ -56,7 +77,8 @@ impl From<&[u8; 68]> for HandshakeMessage {
         HandshakeMessage {
             info_hash: info_hash.into(),
             peer_id: peer_id.into(),
-            extension_supported: reserved == [0x00_u8, 0x00, 0x00, 0x00, 0x00, 0x10, 0x00, 0x00],
+            extension_supported: reserved[EXTENSION_BIT.0] & EXTENSION_BIT.1 != 0,
+            v2_supported: reserved[V2_BIT.0] & V2_BIT.1 != 0,
         }
     }
 }
@@ -68,7 +90,10 @@ impl Into<[u8; 68]> for &HandshakeMessage {
         result[1..20].copy_from_slice(b"BitTorrent protocol");
         // [20..28] are reserved bytes
         if self.extension_supported {
-            result[20..28].copy_from_slice(&[0x00_u8, 0x00, 0x00, 0x00, 0x00, 0x10, 0x00, 0x00]);
+            result[20 + EXTENSION_BIT.0] |= EXTENSION_BIT.1;
+        }
+        if self.v2_supported {
+            result[20 + V2_BIT.0] |= V2_BIT.1;
         }
         result[28..48].copy_from_slice(self.info_hash.as_slice());
         result[48..68].copy_from_slice(self.peer_id.as_slice());
@@ -84,3 +109,24 @@ impl fmt::Display for HandshakeMessage {
 // endregion:   --- Traits impl
 
 // endregion:      --- HandshakeMessage
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reserved_bits() {
+        let message = HandshakeMessage::new_magnet(&Rc::new([1; 20]), &Rc::new([2; 20]))
+            .with_v2_support(true);
+        let bytes: [u8; 68] = (&message).into();
+        assert_eq!(bytes[20..28], [0, 0, 0, 0, 0, 0x10, 0, 0x10]);
+        let parsed: HandshakeMessage = (&bytes).into();
+        assert!(parsed.is_extension_supported() && parsed.is_v2_supported());
+
+        let message = HandshakeMessage::new(&Rc::new([1; 20]), &Rc::new([2; 20]));
+        let bytes: [u8; 68] = (&message).into();
+        assert_eq!(bytes[20..28], [0; 8]);
+        let parsed: HandshakeMessage = (&bytes).into();
+        assert!(!parsed.is_extension_supported() && !parsed.is_v2_supported());
+    }
+}
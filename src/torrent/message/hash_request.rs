@@ -0,0 +1,110 @@
+use crate::error::Error;
+use crate::error::Result;
+
+// region:      --- HashRequest
+/// The common payload of the 'hash request', 'hashes' and 'hash reject' messages (BEP 52),
+/// identifying a range of hashes of a layer of the merkle tree of a file.
+#[derive(Clone, Debug, PartialEq)]
+pub struct HashRequest {
+    pieces_root: [u8; 32],
+    /// The layer of the requested hashes, 0 being the 16 KiB blocks.
+    base_layer: u32,
+    index: u32,
+    /// The number of requested hashes, a power of two.
+    length: u32,
+    /// The number of layers above the requested hashes whose uncle hashes are requested too.
+    proof_layers: u32,
+}
+
+// region:      ---Constructors
+impl HashRequest {
+    pub fn new(
+        pieces_root: [u8; 32],
+        base_layer: u32,
+        index: u32,
+        length: u32,
+        proof_layers: u32,
+    ) -> HashRequest {
+        HashRequest {
+            pieces_root,
+            base_layer,
+            index,
+            length,
+            proof_layers,
+        }
+    }
+}
+// endregion:   ---Constructors
+
+// region:      ---Getters
+impl HashRequest {
+    pub fn get_pieces_root(&self) -> &[u8; 32] {
+        &self.pieces_root
+    }
+
+    pub fn get_base_layer(&self) -> u32 {
+        self.base_layer
+    }
+
+    pub fn get_index(&self) -> u32 {
+        self.index
+    }
+
+    pub fn get_length(&self) -> u32 {
+        self.length
+    }
+
+    pub fn get_proof_layers(&self) -> u32 {
+        self.proof_layers
+    }
+}
+// endregion:   ---Getters
+
+// region:      --- Traits impl
+impl TryFrom<&[u8]> for HashRequest {
+    type Error = Error;
+
+    fn try_from(slice: &[u8]) -> Result<Self> {
+        let Some((pieces_root, fields)) = slice.split_first_chunk::<32>() else {
+            Err(Error::NotEnoughData {
+                minimum_length: HashRequest::LENGTH as u32,
+                actual_length: slice.len() as u32,
+            })?
+        };
+        let field = |i: usize| -> Result<u32> {
+            fields
+                .get(i * 4..i * 4 + 4)
+                .map(|bytes| u32::from_be_bytes(bytes.try_into().expect("4 bytes")))
+                .ok_or(Error::NotEnoughData {
+                    minimum_length: HashRequest::LENGTH as u32,
+                    actual_length: slice.len() as u32,
+                })
+        };
+        Ok(HashRequest::new(
+            *pieces_root,
+            field(0)?,
+            field(1)?,
+            field(2)?,
+            field(3)?,
+        ))
+    }
+}
+
+impl From<&HashRequest> for [u8; 48] {
+    fn from(request: &HashRequest) -> Self {
+        let mut result: [u8; 48] = [0; 48];
+        result[..32].copy_from_slice(&request.pieces_root);
+        result[32..36].copy_from_slice(&request.base_layer.to_be_bytes());
+        result[36..40].copy_from_slice(&request.index.to_be_bytes());
+        result[40..44].copy_from_slice(&request.length.to_be_bytes());
+        result[44..48].copy_from_slice(&request.proof_layers.to_be_bytes());
+        result
+    }
+}
+// endregion:   --- Traits impl
+
+impl HashRequest {
+    /// The length of the encoded payload.
+    pub const LENGTH: usize = 48;
+}
+// endregion:   --- HashRequest
@@ -3,6 +3,7 @@ use std::u8;
 
 use crate::error::Error;
 use crate::error::Result;
+use crate::torrent::HashRequest;
 
 // region:      --- Message
 pub struct Message {
@@ -82,6 +83,26 @@ impl Message {
         )
     }
 
+    pub fn hash_request(request: &HashRequest) -> Self {
+        let payload: [u8; HashRequest::LENGTH] = request.into();
+        Self::new(MessageTag::HashRequest, payload.into())
+    }
+
+    pub fn hashes(request: &HashRequest, hashes: &[[u8; 32]]) -> Self {
+        let request: [u8; HashRequest::LENGTH] = request.into();
+        Self::new(
+            MessageTag::Hashes,
+            [request.as_slice(), hashes.concat().as_slice()]
+                .concat()
+                .into(),
+        )
+    }
+
+    pub fn hash_reject(request: &HashRequest) -> Self {
+        let payload: [u8; HashRequest::LENGTH] = request.into();
+        Self::new(MessageTag::HashReject, payload.into())
+    }
+
     fn new(tag: MessageTag, payload: Box<[u8]>) -> Self {
         Message { tag, payload }
     }
@@ -101,6 +122,39 @@ impl Message {
     pub fn get_payload(&self) -> &Box<[u8]> {
         &self.payload
     }
+
+    /// The extended message ID and the data following it of an 'extended' message (BEP 10).
+    pub fn get_extended(&self) -> Option<(u8, &[u8])> {
+        match (&self.tag, self.payload.split_first()) {
+            (MessageTag::Extended, Some((extended_message_id, data))) => {
+                Some((*extended_message_id, data))
+            }
+            _ => None,
+        }
+    }
+
+    /// The hash request of a 'hash request', 'hashes' or 'hash reject' message.
+    pub fn get_hash_request(&self) -> Option<HashRequest> {
+        match self.tag {
+            MessageTag::HashRequest | MessageTag::Hashes | MessageTag::HashReject => {
+                self.payload.as_ref().try_into().ok()
+            }
+            _ => None,
+        }
+    }
+
+    /// The hashes following the hash request of a 'hashes' message.
+    pub fn get_hashes(&self) -> Option<Box<[[u8; 32]]>> {
+        match self.tag {
+            MessageTag::Hashes => Some(
+                self.payload[HashRequest::LENGTH..]
+                    .chunks_exact(32)
+                    .map(|hash| hash.try_into().expect("chunks are 32 bytes long"))
+                    .collect(),
+            ),
+            _ => None,
+        }
+    }
 }
 // endregion:   --- Getters
 
@@ -148,12 +202,52 @@ impl TryFrom<&[u8]> for Message {
                 Ok(Message::piece(index, begin, block))
             }
             MessageTag::Extended => {
+                if message_len < 2 {
+                    Err(Error::InvalidMessageLength {
+                        minimum_length: 2,
+                        actual_length: message_len,
+                    })?
+                }
+                if arr_len < message_len + 4 {
+                    Err(Error::NotEnoughData {
+                        minimum_length: message_len + 4,
+                        actual_length: arr_len,
+                    })?
+                }
                 let extended_message_id: u8 = slice[5];
                 Ok(Message::extended(
                     extended_message_id,
                     &slice[6..6 + (message_len as usize - 2)],
                 ))
             }
+            MessageTag::HashRequest | MessageTag::Hashes | MessageTag::HashReject => {
+                if arr_len < message_len + 4 {
+                    Err(Error::NotEnoughData {
+                        minimum_length: message_len + 4,
+                        actual_length: arr_len,
+                    })?
+                }
+                let payload: &[u8] = &slice[5..message_len as usize + 4];
+                let request: HashRequest = payload.try_into()?;
+                match tag {
+                    MessageTag::Hashes => {
+                        let hashes: &[u8] = &payload[HashRequest::LENGTH..];
+                        if !hashes.len().is_multiple_of(32) {
+                            Err(Error::InvalidMessageLength {
+                                minimum_length: message_len + 32 - hashes.len() as u32 % 32,
+                                actual_length: message_len,
+                            })?
+                        }
+                        let hashes: Vec<[u8; 32]> = hashes
+                            .chunks_exact(32)
+                            .map(|hash| hash.try_into().expect("chunks are 32 bytes long"))
+                            .collect();
+                        Ok(Message::hashes(&request, &hashes))
+                    }
+                    MessageTag::HashRequest => Ok(Message::hash_request(&request)),
+                    _ => Ok(Message::hash_reject(&request)),
+                }
+            }
             other => Err(Error::MessageParsingNotImplemented(other))?,
         }
     }
@@ -202,6 +296,9 @@ pub enum MessageTag {
     Cancel,
     Port,
     Extended,
+    HashRequest,
+    Hashes,
+    HashReject,
 }
 
 // region:      --- Traits impl
@@ -221,6 +318,9 @@ impl TryFrom<u8> for MessageTag {
             8 => Ok(Self::Cancel),
             9 => Ok(Self::Port),
             20 => Ok(Self::Extended),
+            21 => Ok(Self::HashRequest),
+            22 => Ok(Self::Hashes),
+            23 => Ok(Self::HashReject),
             other => Err(Error::UnrecognizedMessageTag(other)),
         }
     }
@@ -240,9 +340,47 @@ impl Into<u8> for &MessageTag {
             MessageTag::Cancel => 8,
             MessageTag::Port => 9,
             MessageTag::Extended => 20,
+            MessageTag::HashRequest => 21,
+            MessageTag::Hashes => 22,
+            MessageTag::HashReject => 23,
         }
     }
 }
 // endregion:   --- Traits impl
 
 // endregion:   --- MessageTag
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hash_messages_round_trip() {
+        let request = HashRequest::new([7; 32], 1, 4, 2, 3);
+        let hashes: [[u8; 32]; 3] = [[1; 32], [2; 32], [3; 32]];
+        let messages: [(Message, u8); 3] = [
+            (Message::hash_request(&request), 21),
+            (Message::hashes(&request, &hashes), 22),
+            (Message::hash_reject(&request), 23),
+        ];
+        for (message, tag) in messages {
+            let bytes: Box<[u8]> = (&message).into();
+            assert_eq!(bytes[4], tag);
+            let parsed: Message = bytes.as_ref().try_into().unwrap();
+            assert_eq!(parsed.get_hash_request(), Some(request.clone()));
+            assert_eq!(parsed.get_payload(), message.get_payload());
+        }
+        let bytes: Box<[u8]> = (&Message::hashes(&request, &hashes)).into();
+        let parsed: Message = bytes.as_ref().try_into().unwrap();
+        assert_eq!(parsed.get_hashes().as_deref(), Some(hashes.as_slice()));
+    }
+
+    #[test]
+    fn test_truncated_hash_request() {
+        let message = Message::hash_request(&HashRequest::new([7; 32], 0, 0, 1, 0));
+        let bytes: Box<[u8]> = (&message).into();
+        let mut bytes: Vec<u8> = bytes[..40].to_vec();
+        bytes[..4].copy_from_slice(&36u32.to_be_bytes());
+        assert!(Message::try_from(bytes.as_slice()).is_err());
+    }
+}
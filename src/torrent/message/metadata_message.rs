@@ -0,0 +1,190 @@
+use serde::{Deserialize, Serialize};
+
+use crate::bencode::{decoders, serialization};
+use crate::error::Error;
+use crate::error::Result;
+
+/// The length of the pieces the metadata is exchanged in (BEP 9); only the last one is shorter.
+pub const METADATA_PIECE_LENGTH: usize = 16 * 1024;
+
+/// The ID we expect 'ut_metadata' messages with, as advertised in our extended handshake.
+pub const UT_METADATA_ID: u8 = 123;
+
+// region:      --- MetadataMessageType
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum MetadataMessageType {
+    Request,
+    Data,
+    Reject,
+}
+// endregion:   --- MetadataMessageType
+
+// region:      --- MetadataMessage
+/// The payload of a 'ut_metadata' extended message (BEP 9): a bencoded dict, followed by the
+/// piece of metadata in 'data' messages.
+#[derive(Debug, PartialEq)]
+pub struct MetadataMessage {
+    message_type: MetadataMessageType,
+    piece: u32,
+    /// The size of the whole metadata, only sent in 'data' messages.
+    total_size: Option<u64>,
+    data: Box<[u8]>,
+}
+
+/// The bencoded dict of the messages.
+#[derive(Deserialize, Serialize)]
+struct MetadataMessageHeader {
+    msg_type: i64,
+    piece: i64,
+    total_size: Option<i64>,
+}
+
+// region:      ---Constructors
+impl MetadataMessage {
+    pub fn request(piece: u32) -> MetadataMessage {
+        MetadataMessage::new(MetadataMessageType::Request, piece, None, [].into())
+    }
+
+    pub fn data(piece: u32, total_size: u64, data: &[u8]) -> MetadataMessage {
+        MetadataMessage::new(
+            MetadataMessageType::Data,
+            piece,
+            Some(total_size),
+            data.into(),
+        )
+    }
+
+    pub fn reject(piece: u32) -> MetadataMessage {
+        MetadataMessage::new(MetadataMessageType::Reject, piece, None, [].into())
+    }
+
+    fn new(
+        message_type: MetadataMessageType,
+        piece: u32,
+        total_size: Option<u64>,
+        data: Box<[u8]>,
+    ) -> MetadataMessage {
+        MetadataMessage {
+            message_type,
+            piece,
+            total_size,
+            data,
+        }
+    }
+}
+// endregion:   ---Constructors
+
+// region:      ---Getters
+impl MetadataMessage {
+    pub fn get_message_type(&self) -> MetadataMessageType {
+        self.message_type
+    }
+
+    pub fn get_piece(&self) -> u32 {
+        self.piece
+    }
+
+    pub fn get_total_size(&self) -> Option<u64> {
+        self.total_size
+    }
+
+    pub fn get_data(&self) -> &[u8] {
+        &self.data
+    }
+}
+// endregion:   ---Getters
+
+// region:      --- Traits impl
+impl TryFrom<&[u8]> for MetadataMessage {
+    type Error = Error;
+
+    fn try_from(payload: &[u8]) -> Result<Self> {
+        let (header, header_length) = decoders::decode(payload).map_err(Error::DecodeError)?;
+        let header: MetadataMessageHeader =
+            serialization::from_data_type(&header).map_err(Error::SerdeError)?;
+        let message_type: MetadataMessageType = match header.msg_type {
+            0 => MetadataMessageType::Request,
+            1 => MetadataMessageType::Data,
+            2 => MetadataMessageType::Reject,
+            other => Err(Error::InvalidMetadataMessage(format!(
+                "unknown message type {other}"
+            )))?,
+        };
+        let piece: u32 = u32::try_from(header.piece).map_err(|_| {
+            Error::InvalidMetadataMessage(format!("invalid piece index {}", header.piece))
+        })?;
+        let total_size: Option<u64> = match header.total_size.map(u64::try_from) {
+            Some(Ok(total_size)) => Some(total_size),
+            Some(Err(_)) => Err(Error::InvalidMetadataMessage(
+                "negative total size".to_owned(),
+            ))?,
+            None => None,
+        };
+        if message_type == MetadataMessageType::Data && total_size.is_none() {
+            Err(Error::InvalidMetadataMessage(
+                "missing total size".to_owned(),
+            ))?
+        }
+        Ok(MetadataMessage::new(
+            message_type,
+            piece,
+            total_size,
+            payload[header_length..].into(),
+        ))
+    }
+}
+
+impl From<&MetadataMessage> for Box<[u8]> {
+    fn from(message: &MetadataMessage) -> Self {
+        let header = MetadataMessageHeader {
+            msg_type: match message.message_type {
+                MetadataMessageType::Request => 0,
+                MetadataMessageType::Data => 1,
+                MetadataMessageType::Reject => 2,
+            },
+            piece: message.piece as i64,
+            total_size: message.total_size.map(|total_size| total_size as i64),
+        };
+        let header = serialization::to_bytes(&header).expect("the header can be bencoded");
+        [header.as_ref(), &message.data].concat().into()
+    }
+}
+// endregion:   --- Traits impl
+// endregion:   --- MetadataMessage
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_round_trip() {
+        let messages: [(MetadataMessage, &[u8]); 3] = [
+            (MetadataMessage::request(1), b"d8:msg_typei0e5:piecei1ee"),
+            (
+                MetadataMessage::data(0, 3, b"abc"),
+                b"d8:msg_typei1e5:piecei0e10:total_sizei3eeabc",
+            ),
+            (MetadataMessage::reject(2), b"d8:msg_typei2e5:piecei2ee"),
+        ];
+        for (message, bytes) in messages {
+            let encoded: Box<[u8]> = (&message).into();
+            assert_eq!(encoded.as_ref(), bytes);
+            assert_eq!(MetadataMessage::try_from(bytes).unwrap(), message);
+        }
+    }
+
+    #[test]
+    fn test_invalid_messages() {
+        let payloads: [&[u8]; 3] = [
+            b"d8:msg_typei3e5:piecei0ee",
+            b"d8:msg_typei0e5:piecei-1ee",
+            b"d8:msg_typei1e5:piecei0eeabc",
+        ];
+        for payload in payloads {
+            assert!(matches!(
+                MetadataMessage::try_from(payload),
+                Err(Error::InvalidMetadataMessage(_))
+            ));
+        }
+    }
+}
@@ -1,12 +1,18 @@
 // region:      --- Public Modules
+pub(crate) mod extended_handshake;
 pub(crate) mod handshake_message;
+pub(crate) mod hash_request;
 pub(crate) mod message;
+pub(crate) mod metadata_message;
 // endregion:   --- Public Modules
 
 // region:      --- Modules
 // endregion:   --- Modules
 
 // region:      --- Flatten (private, crate, public)
+pub(crate) use extended_handshake::*;
 pub(crate) use handshake_message::*;
+pub(crate) use hash_request::*;
 pub(crate) use message::*;
+pub(crate) use metadata_message::*;
 // endregion:   --- Flatten (private, crate, public)
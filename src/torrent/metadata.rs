@@ -0,0 +1,140 @@
+use sha1::{Digest, Sha1};
+use sha2::Sha256;
+
+use crate::error::Error;
+use crate::error::Result;
+use crate::torrent::METADATA_PIECE_LENGTH;
+
+/// The largest metadata accepted from peers, so that a peer can not make us allocate any size.
+const MAX_METADATA_SIZE: u64 = 16 * 1024 * 1024;
+
+// region:      --- Metadata
+/// The bencoded info dict of a torrent, as it is assembled from the pieces sent by peers (BEP 9).
+pub struct Metadata {
+    data: Box<[u8]>,
+    received: Box<[bool]>,
+}
+
+// region:      ---Constructors
+impl Metadata {
+    /// Prepares the assembly of metadata of `size` bytes, the 'metadata_size' of the extended
+    /// handshake of a peer.
+    pub fn new(size: i64) -> Result<Metadata> {
+        let size: u64 = match u64::try_from(size) {
+            Ok(size) if size > 0 && size <= MAX_METADATA_SIZE => size,
+            _ => Err(Error::InvalidMetadataSize(size))?,
+        };
+        let pieces_count: usize = (size as usize).div_ceil(METADATA_PIECE_LENGTH);
+        Ok(Metadata {
+            data: vec![0; size as usize].into_boxed_slice(),
+            received: vec![false; pieces_count].into_boxed_slice(),
+        })
+    }
+}
+// endregion:   ---Constructors
+
+// region:      ---Getters
+impl Metadata {
+    pub fn get_size(&self) -> u64 {
+        self.data.len() as u64
+    }
+
+    pub fn get_pieces_count(&self) -> u32 {
+        self.received.len() as u32
+    }
+
+    pub fn get_missing_pieces(&self) -> Vec<u32> {
+        (0..self.get_pieces_count())
+            .filter(|piece| !self.received[*piece as usize])
+            .collect()
+    }
+
+    pub fn is_complete(&self) -> bool {
+        self.received.iter().all(|received| *received)
+    }
+
+    pub fn get_data(&self) -> &[u8] {
+        &self.data
+    }
+}
+// endregion:   ---Getters
+
+impl Metadata {
+    /// Stores a piece, which must be exactly as long as expected: 16 KiB, or the remainder for the
+    /// last piece.
+    pub fn add_piece(&mut self, piece: u32, data: &[u8]) -> Result<()> {
+        let begin: usize = piece as usize * METADATA_PIECE_LENGTH;
+        if piece >= self.get_pieces_count() {
+            Err(Error::InvalidMetadataPiece(piece))?
+        }
+        let end: usize = self.data.len().min(begin + METADATA_PIECE_LENGTH);
+        if data.len() != end - begin {
+            Err(Error::InvalidMetadataPiece(piece))?
+        }
+        self.data[begin..end].copy_from_slice(data);
+        self.received[piece as usize] = true;
+        Ok(())
+    }
+
+    /// Whether the complete metadata hashes to every given info hash, SHA-1 for v1 and SHA-256
+    /// for v2 (BEP 52). At least one has to be given.
+    pub fn verify(&self, info_hash_v1: Option<&[u8; 20]>, info_hash_v2: Option<&[u8; 32]>) -> bool {
+        self.is_complete()
+            && (info_hash_v1.is_some() || info_hash_v2.is_some())
+            && info_hash_v1.is_none_or(|hash| Sha1::digest(&self.data).as_slice() == hash)
+            && info_hash_v2.is_none_or(|hash| Sha256::digest(&self.data).as_slice() == hash)
+    }
+}
+// endregion:   --- Metadata
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_assemble_and_verify() {
+        let info: Vec<u8> = [b"d4:name".as_slice(), &[b'x'; 20000], b"e"].concat();
+        let mut metadata = Metadata::new(info.len() as i64).unwrap();
+        assert_eq!(metadata.get_pieces_count(), 2);
+        metadata
+            .add_piece(1, &info[METADATA_PIECE_LENGTH..])
+            .unwrap();
+        assert_eq!(metadata.get_missing_pieces(), [0]);
+        assert!(!metadata.verify(Some(&Sha1::digest(&info).into()), None));
+
+        metadata
+            .add_piece(0, &info[..METADATA_PIECE_LENGTH])
+            .unwrap();
+        assert!(metadata.is_complete());
+        assert_eq!(metadata.get_data(), info.as_slice());
+        assert!(metadata.verify(Some(&Sha1::digest(&info).into()), None));
+        assert!(metadata.verify(
+            Some(&Sha1::digest(&info).into()),
+            Some(&Sha256::digest(&info).into())
+        ));
+        assert!(!metadata.verify(Some(&[0; 20]), None));
+        assert!(!metadata.verify(None, Some(&[0; 32])));
+        assert!(!metadata.verify(None, None));
+    }
+
+    #[test]
+    fn test_invalid_pieces_and_sizes() {
+        let mut metadata = Metadata::new(METADATA_PIECE_LENGTH as i64 + 1).unwrap();
+        assert!(matches!(
+            metadata.add_piece(1, &[0; 2]),
+            Err(Error::InvalidMetadataPiece(1))
+        ));
+        assert!(matches!(
+            metadata.add_piece(2, &[0]),
+            Err(Error::InvalidMetadataPiece(2))
+        ));
+        assert!(metadata.add_piece(1, &[0]).is_ok());
+
+        for size in [0, -1, MAX_METADATA_SIZE as i64 + 1] {
+            assert!(matches!(
+                Metadata::new(size),
+                Err(Error::InvalidMetadataSize(_))
+            ));
+        }
+    }
+}
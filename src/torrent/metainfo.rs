@@ -0,0 +1,67 @@
+use serde::{Deserialize, Serialize};
+use serde_bytes::ByteBuf;
+
+// The bencoded layout of .torrent files (BEP 3), as (de)serialized by `bencode::serialization`.
+
+// region:      --- MetaInfo
+#[derive(Deserialize, Serialize, Debug, PartialEq)]
+pub struct MetaInfo {
+    /// Empty for trackerless torrents, such as the ones built from magnet links without 'tr'.
+    #[serde(default)]
+    pub announce: String,
+    /// Tiers of tracker URLs (BEP 12).
+    #[serde(rename = "announce-list")]
+    pub announce_list: Option<Vec<Vec<String>>>,
+    pub comment: Option<String>,
+    #[serde(rename = "created by")]
+    pub created_by: Option<String>,
+    /// Seconds since the Unix epoch.
+    #[serde(rename = "creation date")]
+    pub creation_date: Option<i64>,
+    /// The encoding of the strings in the 'info' dict.
+    pub encoding: Option<String>,
+    pub info: Info,
+}
+// endregion:   --- MetaInfo
+
+// region:      --- Info
+#[derive(Deserialize, Serialize, Debug, PartialEq)]
+pub struct Info {
+    pub name: String,
+    #[serde(rename = "piece length")]
+    pub piece_length: i64,
+    /// The concatenated SHA-1 hashes of the pieces, absent in v2-only torrents.
+    pub pieces: Option<ByteBuf>,
+    /// Present in single-file torrents only.
+    pub length: Option<i64>,
+    /// Present in multi-file torrents only.
+    pub files: Option<Vec<FileInfo>>,
+    /// Set to 1 by private torrents, which must only get peers from their trackers (BEP 27).
+    pub private: Option<i64>,
+    /// 2 for v2 and hybrid torrents (BEP 52), whose files are described by the 'file tree' dict.
+    #[serde(rename = "meta version")]
+    pub meta_version: Option<i64>,
+    /// Changes the info hash of otherwise identical torrents, usually set to the tracker name.
+    pub source: Option<String>,
+    /// The attributes of the file of single-file torrents (BEP 47).
+    pub attr: Option<String>,
+    #[serde(rename = "symlink path")]
+    pub symlink_path: Option<Vec<String>>,
+    pub sha1: Option<ByteBuf>,
+}
+// endregion:   --- Info
+
+// region:      --- FileInfo
+#[derive(Deserialize, Serialize, Debug, PartialEq)]
+pub struct FileInfo {
+    pub length: i64,
+    pub path: Vec<String>,
+    /// The attributes of the file (BEP 47): 'p'adding, e'x'ecutable, 'h'idden and symlink ('l').
+    pub attr: Option<String>,
+    /// The target of a symlink, as path segments from the root directory of the torrent.
+    #[serde(rename = "symlink path")]
+    pub symlink_path: Option<Vec<String>>,
+    /// The SHA-1 hash of the file's content.
+    pub sha1: Option<ByteBuf>,
+}
+// endregion:   --- FileInfo
@@ -1,7 +1,16 @@
 // region:      --- Public Modules
+pub(crate) mod creator;
+pub(crate) mod editor;
+pub(crate) mod error;
+pub(crate) mod file;
+pub(crate) mod file_tree;
+pub(crate) mod merkle;
 pub(crate) mod message;
+pub(crate) mod metadata;
+pub(crate) mod metainfo;
 pub(crate) mod peer;
 pub(crate) mod piece;
+pub(crate) mod storage;
 pub(crate) mod torrent;
 pub(crate) mod tracker;
 // endregion:   --- Public Modules
@@ -10,9 +19,18 @@ pub(crate) mod tracker;
 // endregion:   --- Modules
 
 // region:      --- Flatten (private, crate, public)
+pub(crate) use creator::*;
+pub(crate) use editor::*;
+pub(crate) use error::*;
+pub(crate) use file::*;
+pub(crate) use file_tree::*;
+pub(crate) use merkle::*;
 pub(crate) use message::*;
+pub(crate) use metadata::*;
+pub(crate) use metainfo::*;
 pub(crate) use peer::*;
 pub(crate) use piece::*;
+pub(crate) use storage::*;
 pub(crate) use torrent::*;
 pub(crate) use tracker::*;
 // endregion:   --- Flatten (private, crate, public)
@@ -2,21 +2,32 @@ use std::{
     collections::BTreeMap,
     io::{Read, Write},
     net::{TcpStream, ToSocketAddrs},
+    rc::Rc,
+    sync::Arc,
     time::Duration,
 };
 
 use crate::error::Result;
+use crate::torrent::ExtendedHandshake;
 use crate::torrent::HandshakeMessage;
+use crate::torrent::HashRequest;
 use crate::torrent::Message;
-use crate::{
-    bencode::{self, encoders},
-    error::Error,
-    types::DataType,
-};
+use crate::torrent::MessageTag;
+use crate::torrent::Torrent;
+use crate::torrent::{MetadataMessage, MetadataMessageType, METADATA_PIECE_LENGTH, UT_METADATA_ID};
+use crate::{bencode::serialization, error::Error};
+
+/// The longest message accepted from a peer, which is enough for the bitfields of torrents with
+/// millions of pieces, and way more than blocks, metadata pieces and hashes need.
+const MAX_MESSAGE_LENGTH: u32 = 1024 * 1024;
 
 // region:      --- Peer
 pub struct Peer {
     socket: TcpStream,
+    /// The bencoded 'info' dict served to the peer (BEP 9), once we have the torrent.
+    metadata: Option<Arc<[u8]>>,
+    /// The ID the peer expects 'ut_metadata' messages with, from its extended handshake.
+    ut_metadata_id: Option<u8>,
 }
 
 // region:      --- Constructors
@@ -26,11 +37,25 @@ impl Peer {
         socket
             .set_read_timeout(Some(Duration::from_secs(5)))
             .map_err(|err| Error::SocketError(err))?;
-        Ok(Peer { socket })
+        Ok(Peer {
+            socket,
+            metadata: None,
+            ut_metadata_id: None,
+        })
     }
 }
 // endregion:   --- Constructors
 
+// region:      --- Setters
+impl Peer {
+    /// Makes the metadata of the torrent available to the peer, which is advertised in the
+    /// extended handshake and served on request.
+    pub fn set_torrent(&mut self, torrent: &Torrent) {
+        self.metadata = Some(torrent.get_raw_info());
+    }
+}
+// endregion:   --- Setters
+
 // region:      --- API
 impl Peer {
     pub fn get_address(&self) -> String {
@@ -50,48 +75,40 @@ impl Peer {
         Ok((&buf).into())
     }
 
-    pub fn extended_handshake(
-        &mut self,
-        payload_dict: BTreeMap<String, DataType>,
-    ) -> Result<Message> {
-        let payload: DataType = DataType::Dict(payload_dict);
-        let handshake_message = Message::extended(0, encoders::bencode(&payload).as_ref());
+    /// Our extended handshake (BEP 10): 'ut_metadata' is always supported, but 'metadata_size'
+    /// is only sent when we have the torrent.
+    pub fn get_extended_handshake(&self) -> ExtendedHandshake {
+        ExtendedHandshake {
+            m: BTreeMap::from_iter([("ut_metadata".to_owned(), UT_METADATA_ID)]),
+            metadata_size: self.metadata.as_ref().map(|metadata| metadata.len() as i64),
+        }
+    }
+
+    /// Exchanges extended handshakes with the peer, returning the one of the peer.
+    pub fn extended_handshake(&mut self) -> Result<ExtendedHandshake> {
+        let payload: Rc<[u8]> =
+            serialization::to_bytes(&self.get_extended_handshake()).map_err(Error::SerdeError)?;
+        let handshake_message = Message::extended(0, &payload);
         let bytes: Box<[u8]> = (&handshake_message).into();
         self.socket
             .write_all(&bytes)
             .map_err(|err| Error::SocketError(err))?;
 
-        let mut length_buf = [0u8; 4];
-        self.socket
-            .read_exact(&mut length_buf)
-            .map_err(|err| Error::SocketError(err))?;
-        let length: u32 = u32::from_be_bytes(length_buf);
-        let mut buf = vec![0u8; length as usize];
-        self.socket
-            .read_exact(&mut buf)
-            .map_err(|err| Error::SocketError(err))?;
-
-        let combined: &[u8] = &[length_buf.as_slice(), buf.as_slice()].concat();
-        let msg: Message = Message::try_from(combined)?;
-        Ok(msg)
+        let bytes: Box<[u8]> = self.receive_message_bytes()?;
+        let msg: Message = Message::try_from(bytes.as_ref())?;
+        let response: ExtendedHandshake =
+            serialization::from_bytes(extended_data(&msg, 0)?).map_err(Error::SerdeError)?;
+        // 0 means that the extension is disabled.
+        self.ut_metadata_id = response.m.get("ut_metadata").copied().filter(|id| *id != 0);
+        Ok(response)
     }
 
     pub fn receive_bitfield(&mut self) -> Result<Message> {
         // println!("receive_bitfield: start");
-        let mut length_buf = [0u8; 4];
-        self.socket
-            .read_exact(&mut length_buf)
-            .map_err(|err| Error::SocketError(err))?;
-        let length: u32 = u32::from_be_bytes(length_buf);
-        let mut buf = vec![0u8; length as usize];
-        self.socket
-            .read_exact(&mut buf)
-            .map_err(|err| Error::SocketError(err))?;
-
-        let combined: &[u8] = &[length_buf.as_slice(), buf.as_slice()].concat();
-        // println!("receive_bitfield: received {} bytes", combined.len());
+        let bytes: Box<[u8]> = self.receive_message_bytes()?;
+        // println!("receive_bitfield: received {} bytes", bytes.len());
 
-        let msg: Message = Message::try_from(combined)?;
+        let msg: Message = Message::try_from(bytes.as_ref())?;
         // println!("receive_bitfield: {}", &msg);
         // println!("receive_bitfield: end");
         Ok(msg)
@@ -143,21 +160,92 @@ impl Peer {
         Ok(())
     }
 
+    /// Receives a requested block, skipping the other messages until it arrives and answering
+    /// the 'ut_metadata' requests of the peer meanwhile.
     pub fn receive_piece_block(&mut self, block_length: u32) -> Result<Box<[u8]>> {
         // Len{4}|Type{1}|Index{4}|Begin{4}|Piece{~}
-        // println!("receive_piece_block: start");
-        // println!("receive_piece_block: block_length={}", block_length);
-        let capacity: usize = 13 + block_length as usize;
-        let mut buf: Vec<u8> = vec![0; capacity];
-        self.socket
-            .read_exact(&mut buf)
-            .map_err(|err| Error::SocketError(err))?;
-        // println!("receive_piece_block: received {} bytes", buf.len());
+        let length: u32 = 13 + block_length;
+        loop {
+            let bytes: Box<[u8]> = self.receive_message_bytes()?;
+            match (bytes.get(4), bytes.get(5)) {
+                (Some(7), _) if bytes.len() != length as usize => {
+                    Err(Error::InvalidMessageLength {
+                        minimum_length: length,
+                        actual_length: bytes.len() as u32,
+                    })?
+                }
+                (Some(7), _) => {
+                    let msg = Message::try_from(bytes.as_ref())?;
+                    return Ok(msg.get_payload()[8..].into());
+                }
+                // The peer may be fetching our metadata meanwhile.
+                (Some(20), Some(&UT_METADATA_ID)) => {
+                    let msg: Message = Message::try_from(bytes.as_ref())?;
+                    let metadata_message =
+                        MetadataMessage::try_from(extended_data(&msg, UT_METADATA_ID)?)?;
+                    if metadata_message.get_message_type() == MetadataMessageType::Request {
+                        self.answer_metadata_request(metadata_message.get_piece())?;
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
 
-        let msg = Message::try_from(buf.as_slice())?;
-        // println!("receive_piece_block: {}", &msg);
-        // println!("receive_piece_block: end");
-        Ok(msg.get_payload()[8..].into())
+    /// Requests merkle tree hashes of a file (BEP 52), skipping the other messages until the
+    /// answer. The hashes are returned as sent: the base layer ones followed by the proof ones.
+    pub fn request_hashes(&mut self, request: &HashRequest) -> Result<Box<[[u8; 32]]>> {
+        let bytes: Box<[u8]> = (&Message::hash_request(request)).into();
+        self.socket.write_all(&bytes).map_err(Error::SocketError)?;
+
+        loop {
+            let bytes: Box<[u8]> = self.receive_message_bytes()?;
+            // Keep-alives and the messages sent meanwhile (have, ...) are not answers.
+            if !matches!(bytes.get(4), Some(22 | 23)) {
+                continue;
+            }
+
+            let msg: Message = Message::try_from(bytes.as_ref())?;
+            if msg.get_hash_request().as_ref() != Some(request) {
+                continue;
+            }
+            return match (msg.get_tag(), msg.get_hashes()) {
+                (MessageTag::Hashes, Some(hashes)) => Ok(hashes),
+                _ => Err(Error::HashRequestRejected(request.clone())),
+            };
+        }
+    }
+
+    /// Requests a piece of the metadata (BEP 9) from a peer supporting 'ut_metadata' in its
+    /// extended handshake, skipping the other messages until the answer.
+    pub fn request_metadata_piece(&mut self, piece: u32) -> Result<Box<[u8]>> {
+        let extension_id: u8 =
+            self.ut_metadata_id
+                .ok_or_else(|| Error::KeyNotFoundInExtendedHandshakeResponse {
+                    key: "ut_metadata".into(),
+                })?;
+        let request: Box<[u8]> = (&MetadataMessage::request(piece)).into();
+        let bytes: Box<[u8]> = (&Message::extended(extension_id, &request)).into();
+        self.socket.write_all(&bytes).map_err(Error::SocketError)?;
+
+        loop {
+            let bytes: Box<[u8]> = self.receive_message_bytes()?;
+            if bytes.get(4) != Some(&20) || bytes.get(5) != Some(&UT_METADATA_ID) {
+                continue;
+            }
+
+            let msg: Message = Message::try_from(bytes.as_ref())?;
+            let metadata_message = MetadataMessage::try_from(extended_data(&msg, UT_METADATA_ID)?)?;
+            match metadata_message.get_message_type() {
+                // The peer may be fetching our metadata meanwhile.
+                MetadataMessageType::Request => {
+                    self.answer_metadata_request(metadata_message.get_piece())?
+                }
+                _ if metadata_message.get_piece() != piece => {}
+                MetadataMessageType::Data => return Ok(metadata_message.get_data().into()),
+                MetadataMessageType::Reject => Err(Error::MetadataPieceRejected(piece))?,
+            }
+        }
     }
 
     pub fn get_piece_block(
@@ -169,7 +257,254 @@ impl Peer {
         self.send_piece_request(piece_index, begin, block_length)?;
         self.receive_piece_block(block_length)
     }
+
+    /// Answers a 'ut_metadata' request of the peer with the piece of our metadata, or with a
+    /// reject when we do not have it.
+    pub fn answer_metadata_request(&mut self, piece: u32) -> Result<()> {
+        let Some(extension_id) = self.ut_metadata_id else {
+            // The peer did not say which ID it expects, so it can not be answered.
+            return Ok(());
+        };
+        let response: MetadataMessage = metadata_response(self.metadata.as_deref(), piece);
+        let response: Box<[u8]> = (&response).into();
+        let bytes: Box<[u8]> = (&Message::extended(extension_id, &response)).into();
+        self.socket.write_all(&bytes).map_err(Error::SocketError)
+    }
+
+    /// Reads the next message, including its length prefix, which is all there is to
+    /// keep-alives. Messages longer than `MAX_MESSAGE_LENGTH` are rejected before reading them.
+    fn receive_message_bytes(&mut self) -> Result<Box<[u8]>> {
+        let mut length_buf = [0u8; 4];
+        self.socket
+            .read_exact(&mut length_buf)
+            .map_err(Error::SocketError)?;
+        let length: u32 = u32::from_be_bytes(length_buf);
+        if length > MAX_MESSAGE_LENGTH {
+            Err(Error::MessageTooLong {
+                maximum_length: MAX_MESSAGE_LENGTH,
+                actual_length: length,
+            })?
+        }
+        let mut bytes = vec![0u8; 4 + length as usize];
+        bytes[..4].copy_from_slice(&length_buf);
+        self.socket
+            .read_exact(&mut bytes[4..])
+            .map_err(Error::SocketError)?;
+        Ok(bytes.into_boxed_slice())
+    }
 }
 // endregion:   --- API
 
 // endregion:   --- Peer
+
+/// The data of an extended message (BEP 10), which must have the expected extended message ID:
+/// 0 for handshakes, or the ID we gave to an extension in ours.
+fn extended_data(msg: &Message, extended_message_id: u8) -> Result<&[u8]> {
+    match msg.get_extended() {
+        Some((id, data)) if id == extended_message_id => Ok(data),
+        _ => Err(Error::InvalidExtendedMessage {
+            expected_id: extended_message_id,
+        }),
+    }
+}
+
+/// The answer to a request for a piece of the metadata: the piece when it exists, or a reject.
+fn metadata_response(metadata: Option<&[u8]>, piece: u32) -> MetadataMessage {
+    let begin: usize = piece as usize * METADATA_PIECE_LENGTH;
+    match metadata {
+        Some(metadata) if begin < metadata.len() => {
+            let end: usize = metadata.len().min(begin + METADATA_PIECE_LENGTH);
+            MetadataMessage::data(piece, metadata.len() as u64, &metadata[begin..end])
+        }
+        _ => MetadataMessage::reject(piece),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::TcpListener;
+
+    use super::*;
+
+    /// A torrent whose 'info' dict is longer than a metadata piece.
+    fn torrent() -> Torrent {
+        let pieces: Vec<u8> = vec![b'a'; 20 * 1000];
+        let data: Vec<u8> = [
+            b"d8:announce9:http://a/4:infod6:lengthi16000e4:name1:a12:piece lengthi16e6:pieces20000:"
+                .as_slice(),
+            &pieces,
+            b"ee",
+        ]
+        .concat();
+        data.as_slice().try_into().unwrap()
+    }
+
+    fn send(stream: &mut TcpStream, message: &Message) {
+        let bytes: Box<[u8]> = message.into();
+        stream.write_all(&bytes).unwrap();
+    }
+
+    fn receive(stream: &mut TcpStream) -> Message {
+        let mut length_buf = [0u8; 4];
+        stream.read_exact(&mut length_buf).unwrap();
+        let mut buf = vec![0u8; u32::from_be_bytes(length_buf) as usize];
+        stream.read_exact(&mut buf).unwrap();
+        Message::try_from([length_buf.as_slice(), &buf].concat().as_slice()).unwrap()
+    }
+
+    fn receive_metadata_message(stream: &mut TcpStream, extension_id: u8) -> MetadataMessage {
+        let message: Message = receive(stream);
+        assert_eq!(message.get_payload()[0], extension_id);
+        MetadataMessage::try_from(&message.get_payload()[1..]).unwrap()
+    }
+
+    #[test]
+    fn test_metadata_is_served() {
+        let torrent: Torrent = torrent();
+        let raw_info: Arc<[u8]> = torrent.get_raw_info();
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let mut peer = Peer::new(listener.local_addr().unwrap()).unwrap();
+        let (mut remote, _) = listener.accept().unwrap();
+        peer.set_torrent(&torrent);
+
+        // The remote peer expects 'ut_metadata' messages with the ID 7.
+        let remote_handshake = ExtendedHandshake {
+            m: BTreeMap::from_iter([("ut_metadata".to_owned(), 7)]),
+            metadata_size: None,
+        };
+        let payload: Rc<[u8]> = serialization::to_bytes(&remote_handshake).unwrap();
+        send(&mut remote, &Message::extended(0, &payload));
+        assert_eq!(peer.extended_handshake().unwrap(), remote_handshake);
+
+        let handshake: Message = receive(&mut remote);
+        let handshake: ExtendedHandshake =
+            serialization::from_bytes(&handshake.get_payload()[1..]).unwrap();
+        assert_eq!(handshake, peer.get_extended_handshake());
+        assert_eq!(handshake.m["ut_metadata"], UT_METADATA_ID);
+        assert_eq!(handshake.metadata_size, Some(raw_info.len() as i64));
+
+        // The request of the remote peer is answered while waiting for the answer to ours.
+        let request: Box<[u8]> = (&MetadataMessage::request(1)).into();
+        send(&mut remote, &Message::extended(UT_METADATA_ID, &request));
+        let data: Box<[u8]> = (&MetadataMessage::data(0, 3, b"abc")).into();
+        send(&mut remote, &Message::extended(UT_METADATA_ID, &data));
+        assert_eq!(peer.request_metadata_piece(0).unwrap().as_ref(), b"abc");
+
+        assert_eq!(
+            receive_metadata_message(&mut remote, 7),
+            MetadataMessage::request(0)
+        );
+        assert_eq!(
+            receive_metadata_message(&mut remote, 7),
+            MetadataMessage::data(1, raw_info.len() as u64, &raw_info[METADATA_PIECE_LENGTH..])
+        );
+
+        peer.answer_metadata_request(2).unwrap();
+        assert_eq!(
+            receive_metadata_message(&mut remote, 7),
+            MetadataMessage::reject(2)
+        );
+    }
+
+    #[test]
+    fn test_metadata_is_served_during_download() {
+        let torrent: Torrent = torrent();
+        let raw_info: Arc<[u8]> = torrent.get_raw_info();
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let mut peer = Peer::new(listener.local_addr().unwrap()).unwrap();
+        let (mut remote, _) = listener.accept().unwrap();
+        peer.set_torrent(&torrent);
+
+        let remote_handshake = ExtendedHandshake {
+            m: BTreeMap::from_iter([("ut_metadata".to_owned(), 7)]),
+            metadata_size: None,
+        };
+        let payload: Rc<[u8]> = serialization::to_bytes(&remote_handshake).unwrap();
+        send(&mut remote, &Message::extended(0, &payload));
+        peer.extended_handshake().unwrap();
+        receive(&mut remote);
+
+        // The remote peer fetches our metadata before sending the block we asked for.
+        let request: Box<[u8]> = (&MetadataMessage::request(0)).into();
+        send(&mut remote, &Message::extended(UT_METADATA_ID, &request));
+        send(&mut remote, &Message::have(3));
+        send(&mut remote, &Message::piece(1, 16, b"abcd"));
+        assert_eq!(peer.get_piece_block(1, 16, 4).unwrap().as_ref(), b"abcd");
+
+        let block_request: Box<[u8]> = (&Message::request(1, 16, 4)).into();
+        let mut buf = vec![0u8; block_request.len()];
+        remote.read_exact(&mut buf).unwrap();
+        assert_eq!(buf, block_request.as_ref());
+        assert_eq!(
+            receive_metadata_message(&mut remote, 7),
+            MetadataMessage::data(0, raw_info.len() as u64, &raw_info[..METADATA_PIECE_LENGTH])
+        );
+
+        // A block of another length than the requested one is an error.
+        send(&mut remote, &Message::piece(1, 16, b"abc"));
+        assert!(peer.get_piece_block(1, 16, 4).is_err());
+    }
+
+    #[test]
+    fn test_invalid_extended_handshake() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let mut peer = Peer::new(listener.local_addr().unwrap()).unwrap();
+        let (mut remote, _) = listener.accept().unwrap();
+
+        // An extended message without an extended message ID.
+        remote.write_all(&[0, 0, 0, 1, 20]).unwrap();
+        assert!(peer.extended_handshake().is_err());
+        receive(&mut remote);
+
+        // A 'ut_metadata' message instead of the handshake.
+        let request: Box<[u8]> = (&MetadataMessage::request(0)).into();
+        send(&mut remote, &Message::extended(UT_METADATA_ID, &request));
+        assert!(matches!(
+            peer.extended_handshake(),
+            Err(Error::InvalidExtendedMessage { expected_id: 0 })
+        ));
+        // Other messages are not extended ones.
+        send(&mut remote, &Message::bitfield(&[0xff]));
+        assert!(matches!(
+            peer.extended_handshake(),
+            Err(Error::InvalidExtendedMessage { expected_id: 0 })
+        ));
+    }
+
+    #[test]
+    fn test_message_too_long() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let mut peer = Peer::new(listener.local_addr().unwrap()).unwrap();
+        let (mut remote, _) = listener.accept().unwrap();
+
+        remote
+            .write_all(&(MAX_MESSAGE_LENGTH + 1).to_be_bytes())
+            .unwrap();
+        assert!(matches!(
+            peer.receive_piece_block(16),
+            Err(Error::MessageTooLong {
+                maximum_length: MAX_MESSAGE_LENGTH,
+                actual_length,
+            }) if actual_length == MAX_MESSAGE_LENGTH + 1
+        ));
+    }
+
+    #[test]
+    fn test_metadata_response() {
+        let metadata: Vec<u8> = vec![1; METADATA_PIECE_LENGTH + 1];
+        assert_eq!(
+            metadata_response(Some(&metadata), 0),
+            MetadataMessage::data(0, metadata.len() as u64, &metadata[..METADATA_PIECE_LENGTH])
+        );
+        assert_eq!(
+            metadata_response(Some(&metadata), 1),
+            MetadataMessage::data(1, metadata.len() as u64, &[1])
+        );
+        assert_eq!(
+            metadata_response(Some(&metadata), 2),
+            MetadataMessage::reject(2)
+        );
+        // Without the torrent, every request is rejected.
+        assert_eq!(metadata_response(None, 0), MetadataMessage::reject(0));
+    }
+}
@@ -1,17 +1,31 @@
+use sha1::{Digest, Sha1};
+
+use crate::torrent::MerkleRoot;
+
 // region:      --- Piece
 pub struct Piece {
     index: u32,
-    hash: [u8; 20],
+    /// The SHA-1 hash of the piece, absent in v2-only torrents.
+    hash: Option<[u8; 20]>,
+    /// The root of the merkle subtree of the piece (BEP 52), absent in v1-only torrents.
+    hash_v2: Option<MerkleRoot>,
     blocks: Box<[Block]>,
     begin: u64,
 }
 
 // region:      ---Constructors
 impl Piece {
-    pub fn new(index: u32, hash: [u8; 20], blocks: Box<[Block]>, begin: u64) -> Piece {
+    pub fn new(
+        index: u32,
+        hash: Option<[u8; 20]>,
+        hash_v2: Option<MerkleRoot>,
+        blocks: Box<[Block]>,
+        begin: u64,
+    ) -> Piece {
         Piece {
             index,
             hash,
+            hash_v2,
             blocks,
             begin,
         }
@@ -25,8 +39,16 @@ impl Piece {
         self.index
     }
 
-    pub fn get_hash(&self) -> &[u8; 20] {
-        &self.hash
+    pub fn get_hash(&self) -> Option<&[u8; 20]> {
+        self.hash.as_ref()
+    }
+
+    pub fn get_hash_v2(&self) -> Option<&[u8; 32]> {
+        self.hash_v2.as_ref().map(MerkleRoot::get_hash)
+    }
+
+    pub fn get_merkle_root(&self) -> Option<&MerkleRoot> {
+        self.hash_v2.as_ref()
     }
 
     pub fn get_blocks(&self) -> &Box<[Block]> {
@@ -43,6 +65,21 @@ impl Piece {
 }
 // endregion:   ---Getters
 
+impl Piece {
+    /// Whether `data` matches every hash of the piece: its SHA-1 hash and the root of the merkle
+    /// tree of its 16 KiB blocks. A piece without any hash can not be verified.
+    pub fn verify(&self, data: &[u8]) -> bool {
+        let v1: Option<bool> = self
+            .hash
+            .map(|hash| <[u8; 20]>::from(Sha1::digest(data)) == hash);
+        let v2: Option<bool> = self.hash_v2.map(|root| root.verify(data));
+        match (v1, v2) {
+            (None, None) => false,
+            (v1, v2) => v1.unwrap_or(true) && v2.unwrap_or(true),
+        }
+    }
+}
+
 // endregion:   --- Piece
 
 // region:      --- Block
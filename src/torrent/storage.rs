@@ -0,0 +1,188 @@
+use std::fs::{self, File, Permissions};
+use std::io::{BufWriter, Write};
+use std::os::unix::fs::{symlink, FileExt, PermissionsExt};
+use std::path::{Path, PathBuf};
+
+use crate::error::Error;
+use crate::error::Result;
+use crate::torrent::{split_by_files, Piece, Torrent, TorrentFile};
+
+const BLOCK_SIZE: usize = 16 * 1024;
+
+/// Creates (and pre-allocates) the files the torrent content is written to.
+///
+/// The content of a single-file torrent goes to `output_path` itself, while the files of a
+/// multi-file torrent are laid out under the `output_path/<torrent name>` directory.
+///
+/// Padding files (BEP 47) and the files that are not selected are not created, and symlinks are
+/// created as such, so none of them has an open file. Executable files get their executable bits.
+pub fn create_output_files<F>(
+    torrent: &Torrent,
+    output_path: &Path,
+    is_file_selected: F,
+) -> Result<Box<[Option<File>]>>
+where
+    F: Fn(usize) -> bool,
+{
+    let paths: Vec<PathBuf> = if torrent.is_multi_file() {
+        let root: PathBuf = output_path.join(torrent.get_name());
+        torrent
+            .get_files()
+            .iter()
+            .map(|file| root.join(file.get_path().iter().collect::<PathBuf>()))
+            .collect()
+    } else {
+        vec![output_path.to_path_buf()]
+    };
+
+    let mut files: Vec<Option<File>> = Vec::with_capacity(paths.len());
+    for (index, (path, torrent_file)) in paths.iter().zip(torrent.get_files().iter()).enumerate() {
+        let attributes = torrent_file.get_attributes();
+        if attributes.is_padding() || !is_file_selected(index) {
+            files.push(None);
+            continue;
+        }
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).map_err(Error::FileError)?;
+        }
+        if let Some(symlink_path) = torrent_file.get_symlink_path() {
+            create_symlink(path, torrent_file.get_path(), symlink_path)?;
+            files.push(None);
+            continue;
+        }
+        let mut file = File::create(path).map_err(Error::FileError)?;
+        reserve_space(&mut file, torrent_file.get_length())?;
+        if attributes.is_executable() {
+            file.set_permissions(Permissions::from_mode(0o755))
+                .map_err(Error::FileError)?;
+        }
+        files.push(Some(file));
+    }
+    Ok(files.into_boxed_slice())
+}
+
+/// Creates a symlink at `link`, the file at `path` in the torrent, pointing to the file at
+/// `symlink_path` in the torrent. The target is relative so that the download can be moved.
+fn create_symlink(link: &Path, path: &[String], symlink_path: &[String]) -> Result<()> {
+    let target: PathBuf = std::iter::repeat_n("..", path.len() - 1)
+        .chain(symlink_path.iter().map(String::as_str))
+        .collect();
+    if fs::symlink_metadata(link).is_ok() {
+        fs::remove_file(link).map_err(Error::FileError)?;
+    }
+    symlink(target, link).map_err(Error::FileError)
+}
+
+/// Writes the data of a piece to the files it overlaps, skipping the padding files and symlinks.
+pub fn write_piece(
+    files: &mut [Option<File>],
+    torrent_files: &[TorrentFile],
+    piece: &Piece,
+    data: &[u8],
+) -> Result<()> {
+    for slice in split_by_files(torrent_files, piece.get_begin(), data.len()) {
+        let Some(file) = &mut files[slice.get_file_index()] else {
+            continue;
+        };
+        file.write_all_at(
+            &data[slice.get_data_begin()..slice.get_data_end()],
+            slice.get_file_offset(),
+        )
+        .map_err(Error::FileError)?;
+        file.flush().map_err(Error::FileError)?;
+    }
+    Ok(())
+}
+
+fn reserve_space(file: &mut File, size_in_bytes: u64) -> Result<()> {
+    let mut file: BufWriter<&File> = BufWriter::new(file);
+    if size_in_bytes > BLOCK_SIZE as u64 {
+        let blocks_count: u64 = size_in_bytes / BLOCK_SIZE as u64;
+        let residue: u64 = size_in_bytes % BLOCK_SIZE as u64;
+        for _ in 0..blocks_count {
+            let bytes = vec![0u8; BLOCK_SIZE];
+            file.write_all(&bytes).map_err(Error::FileError)?;
+        }
+        if residue > 0 {
+            let bytes = vec![0u8; residue as usize];
+            file.write_all(&bytes).map_err(Error::FileError)?;
+        }
+    } else {
+        let bytes = vec![0u8; size_in_bytes as usize];
+        file.write_all(&bytes).map_err(Error::FileError)?;
+    }
+    file.flush().map_err(Error::FileError)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// An executable 'a', padded to the 16-byte piece boundary, then 'dir/b' and a symlink to it.
+    const TORRENT: &[u8] = b"d8:announce9:http://a/4:infod5:filesld4:attr1:x6:lengthi3e4:pathl1:aeed4:attr1:p6:lengthi13e4:pathl4:.pad2:13eed6:lengthi4e4:pathl3:dir1:beed4:attr1:l6:lengthi0e4:pathl4:linke12:symlink pathl3:dir1:beee4:name4:root12:piece lengthi16e6:pieces40:aaaaaaaaaaaaaaaaaaaabbbbbbbbbbbbbbbbbbbbee";
+
+    #[test]
+    fn test_attributes_are_materialized() {
+        let torrent: Torrent = TORRENT.try_into().unwrap();
+        let dir = tempfile::tempdir().unwrap();
+        let mut files: Box<[Option<File>]> =
+            create_output_files(&torrent, dir.path(), |_| true).unwrap();
+        assert_eq!(
+            files.iter().map(Option::is_some).collect::<Vec<bool>>(),
+            [true, false, true, false]
+        );
+
+        let data: Vec<u8> = [b"abc".as_slice(), &[0; 13], b"defg"].concat();
+        for piece in torrent.get_pieces().iter() {
+            let begin: usize = piece.get_begin() as usize;
+            let piece_data: &[u8] = &data[begin..begin + piece.get_length() as usize];
+            write_piece(&mut files, &torrent.get_files(), piece, piece_data).unwrap();
+        }
+
+        let root: PathBuf = dir.path().join("root");
+        assert_eq!(fs::read(root.join("a")).unwrap(), b"abc");
+        let mode: u32 = fs::metadata(root.join("a")).unwrap().permissions().mode();
+        assert_eq!(mode & 0o111, 0o111);
+        assert!(!root.join(".pad").exists());
+        assert_eq!(fs::read(root.join("dir/b")).unwrap(), b"defg");
+        let link: PathBuf = root.join("link");
+        assert!(fs::symlink_metadata(&link).unwrap().is_symlink());
+        assert_eq!(fs::read_link(&link).unwrap(), Path::new("dir/b"));
+        assert_eq!(fs::read(&link).unwrap(), b"defg");
+    }
+
+    #[test]
+    fn test_unselected_files_are_skipped() {
+        let torrent: Torrent = TORRENT.try_into().unwrap();
+        let dir = tempfile::tempdir().unwrap();
+        let mut files: Box<[Option<File>]> =
+            create_output_files(&torrent, dir.path(), |index| index == 2).unwrap();
+        assert_eq!(
+            files.iter().map(Option::is_some).collect::<Vec<bool>>(),
+            [false, false, true, false]
+        );
+
+        let data: Vec<u8> = [b"abc".as_slice(), &[0; 13], b"defg"].concat();
+        for index in torrent.get_selected_pieces(|index| index == 2) {
+            let piece: &Piece = &torrent.get_pieces()[index];
+            let begin: usize = piece.get_begin() as usize;
+            let piece_data: &[u8] = &data[begin..begin + piece.get_length() as usize];
+            write_piece(&mut files, &torrent.get_files(), piece, piece_data).unwrap();
+        }
+
+        let root: PathBuf = dir.path().join("root");
+        assert!(!root.join("a").exists());
+        assert!(!root.join("link").exists());
+        assert_eq!(fs::read(root.join("dir/b")).unwrap(), b"defg");
+    }
+
+    #[test]
+    fn test_nested_symlink_target_is_relative() {
+        let dir = tempfile::tempdir().unwrap();
+        let link: PathBuf = dir.path().join("x/y/link");
+        fs::create_dir_all(link.parent().unwrap()).unwrap();
+        let path: Vec<String> = vec!["x".into(), "y".into(), "link".into()];
+        create_symlink(&link, &path, &["z".to_owned()]).unwrap();
+        assert_eq!(fs::read_link(&link).unwrap(), Path::new("../../z"));
+    }
+}
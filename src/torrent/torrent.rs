@@ -1,24 +1,76 @@
+use std::collections::BTreeMap;
+use std::fmt;
 use std::sync::Arc;
-use std::{collections::HashMap, fmt};
 
 use sha1::{Digest, Sha1};
+use sha2::Sha256;
 
 use crate::bencode::decoders;
 use crate::bencode::encoders;
+use crate::bencode::serialization;
 use crate::error::Error;
 use crate::error::Result;
 use crate::torrent::Block;
+use crate::torrent::FileAttributes;
+use crate::torrent::FileInfo;
+use crate::torrent::FileTreeEntry;
+use crate::torrent::Info;
+use crate::torrent::MerkleRoot;
+use crate::torrent::MetaInfo;
 use crate::torrent::Piece;
-use crate::types::ByteString;
+use crate::torrent::TorrentError;
+use crate::torrent::TorrentFile;
+use crate::torrent::{
+    parse_file_tree, parse_piece_layers, split_by_files, verify_piece_layer, PieceLayers,
+    MERKLE_BLOCK_SIZE,
+};
 use crate::types::DataType;
 
 const DEFAULT_BLOCK_SIZE: u32 = 16 * 1024; // 16 KB
 
+// region:      --- MetaVersion
+/// The versions of the BitTorrent protocol a torrent can be shared with.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum MetaVersion {
+    V1,
+    /// BEP 52 only.
+    V2,
+    /// Both v1 and v2, with the v1 files padded so that files start at piece boundaries.
+    Hybrid,
+}
+
+impl fmt::Display for MetaVersion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MetaVersion::V1 => write!(f, "v1"),
+            MetaVersion::V2 => write!(f, "v2"),
+            MetaVersion::Hybrid => write!(f, "hybrid (v1 and v2)"),
+        }
+    }
+}
+// endregion:   --- MetaVersion
+
 // region:      --- Torrent
 pub struct Torrent {
     announce: String,
+    announce_list: Vec<Vec<String>>,
+    comment: Option<String>,
+    created_by: Option<String>,
+    creation_date: Option<i64>,
+    encoding: Option<String>,
+    private: bool,
+    source: Option<String>,
+    /// The web seed URLs from the 'url-list' key (BEP 19).
+    web_seeds: Vec<String>,
+    name: String,
     length: u64,
-    info_hash: [u8; 20],
+    files: Arc<[TorrentFile]>,
+    multi_file: bool,
+    version: MetaVersion,
+    info_hash_v1: Option<[u8; 20]>,
+    info_hash_v2: Option<[u8; 32]>,
+    /// The bencoded 'info' dict the info hashes are computed over.
+    raw_info: Arc<[u8]>,
     piece_length: u32,
     pieces: Arc<[Piece]>,
 }
@@ -29,12 +81,87 @@ impl Torrent {
         &self.announce
     }
 
+    /// The tiers of tracker URLs from the 'announce-list' key (BEP 12), empty when it is absent.
+    pub fn get_announce_list(&self) -> &[Vec<String>] {
+        &self.announce_list
+    }
+
+    pub fn get_comment(&self) -> Option<&str> {
+        self.comment.as_deref()
+    }
+
+    pub fn get_created_by(&self) -> Option<&str> {
+        self.created_by.as_deref()
+    }
+
+    /// The creation time of the torrent, in seconds since the Unix epoch.
+    pub fn get_creation_date(&self) -> Option<i64> {
+        self.creation_date
+    }
+
+    pub fn get_encoding(&self) -> Option<&str> {
+        self.encoding.as_deref()
+    }
+
+    /// Whether the torrent is private (BEP 27), i.e. peers must only be obtained from its trackers.
+    pub fn is_private(&self) -> bool {
+        self.private
+    }
+
+    pub fn get_source(&self) -> Option<&str> {
+        self.source.as_deref()
+    }
+
+    pub fn get_web_seeds(&self) -> &[String] {
+        &self.web_seeds
+    }
+
+    pub fn get_name(&self) -> &str {
+        &self.name
+    }
+
+    /// The total length of the torrent content, i.e. the sum of the lengths of all its files.
     pub fn get_length(&self) -> u64 {
         self.length
     }
 
+    /// The file table of the torrent.
+    ///
+    /// Single-file torrents have exactly one entry, whose path is the torrent name.
+    pub fn get_files(&self) -> Arc<[TorrentFile]> {
+        Arc::clone(&self.files)
+    }
+
+    pub fn is_multi_file(&self) -> bool {
+        self.multi_file
+    }
+
+    pub fn get_version(&self) -> MetaVersion {
+        self.version
+    }
+
+    /// The 20-byte info hash used in handshakes and tracker requests: the SHA-1 one, or the
+    /// truncated SHA-256 one for v2-only torrents.
     pub fn get_info_hash(&self) -> &[u8; 20] {
-        &self.info_hash
+        self.info_hash_v1
+            .as_ref()
+            .or_else(|| {
+                self.info_hash_v2
+                    .as_ref()
+                    .and_then(|hash| hash.first_chunk())
+            })
+            .expect("torrents have either a v1 or a v2 info hash")
+    }
+
+    /// The SHA-256 info hash of v2 and hybrid torrents (BEP 52).
+    pub fn get_info_hash_v2(&self) -> Option<&[u8; 32]> {
+        self.info_hash_v2.as_ref()
+    }
+
+    /// The bencoded 'info' dict, which is the metadata exchanged with peers for magnet links
+    /// (BEP 9).
+    pub fn get_raw_info(&self) -> Arc<[u8]> {
+        Arc::clone(&self.raw_info)
     }
 
     pub fn get_piece_length(&self) -> u32 {
@@ -44,132 +171,609 @@ impl Torrent {
     pub fn get_pieces(&self) -> Arc<[Piece]> {
         Arc::clone(&self.pieces)
     }
+
+    /// The v2 files larger than a piece whose piece layer was missing from the torrent, so that
+    /// their pieces can not be verified until it is fetched from peers.
+    pub fn get_missing_piece_layers(&self) -> Vec<&TorrentFile> {
+        self.files
+            .iter()
+            .filter(|file| {
+                file.get_pieces_root().is_some()
+                    && file.get_length() > self.piece_length as u64
+                    && self
+                        .get_piece_at(file.get_begin())
+                        .is_some_and(|piece| piece.get_merkle_root().is_none())
+            })
+            .collect()
+    }
+
+    /// The indices of the pieces overlapping at least one of the selected files, e.g. the ones
+    /// of the 'so' parameter of a magnet link (BEP 53).
+    pub fn get_selected_pieces<F>(&self, is_file_selected: F) -> Vec<usize>
+    where
+        F: Fn(usize) -> bool,
+    {
+        self.pieces
+            .iter()
+            .enumerate()
+            .filter(|(_, piece)| {
+                split_by_files(&self.files, piece.get_begin(), piece.get_length() as usize)
+                    .iter()
+                    .any(|slice| is_file_selected(slice.get_file_index()))
+            })
+            .map(|(index, _)| index)
+            .collect()
+    }
+
+    fn get_piece_at(&self, begin: u64) -> Option<&Piece> {
+        self.pieces
+            .binary_search_by_key(&begin, Piece::get_begin)
+            .ok()
+            .map(|index| &self.pieces[index])
+    }
+}
+
+impl Torrent {
+    /// Adds the piece layer of the file with the given pieces root, after checking it against
+    /// the root, so that the pieces of the file can be verified.
+    pub fn add_piece_layer(&mut self, pieces_root: &[u8; 32], layer: &[[u8; 32]]) -> Result<()> {
+        let file: &TorrentFile = self
+            .files
+            .iter()
+            .find(|file| file.get_pieces_root() == Some(pieces_root))
+            .ok_or_else(|| TorrentError::UnknownPiecesRoot(hex::encode(pieces_root)))?;
+        check_piece_layer(file, layer, self.piece_length)?;
+        let roots: Vec<Option<MerkleRoot>> = piece_merkle_roots(layer, self.piece_length);
+        let (begin, end): (u64, u64) = (file.get_begin(), file.get_end());
+        self.pieces = self
+            .pieces
+            .iter()
+            .map(|piece| {
+                let root: Option<MerkleRoot> = match piece.get_begin() {
+                    position if (begin..end).contains(&position) => {
+                        roots[((position - begin) / self.piece_length as u64) as usize]
+                    }
+                    _ => piece.get_merkle_root().copied(),
+                };
+                new_piece(
+                    piece.get_index(),
+                    piece.get_hash().copied(),
+                    root,
+                    piece.get_begin(),
+                    piece.get_length(),
+                )
+            })
+            .collect();
+        Ok(())
+    }
+}
+
+impl Torrent {
+    /// The magnet URI of the torrent (BEP 9): its info hashes ('btih' and/or 'btmh'), name,
+    /// trackers from both 'announce' and 'announce-list', total length and web seeds.
+    pub fn to_magnet(&self) -> String {
+        let mut params: Vec<String> = Vec::new();
+        if let Some(info_hash) = &self.info_hash_v1 {
+            params.push(format!("xt=urn:btih:{}", hex::encode(info_hash)));
+        }
+        if let Some(info_hash) = &self.info_hash_v2 {
+            // The multihash header of SHA-256 digests: the function code and the digest length.
+            params.push(format!("xt=urn:btmh:1220{}", hex::encode(info_hash)));
+        }
+        params.push(format!("dn={}", percent_encode(&self.name)));
+        let mut trackers: Vec<&str> = Vec::new();
+        for url in std::iter::once(&self.announce).chain(self.announce_list.iter().flatten()) {
+            if !url.is_empty() && !trackers.contains(&url.as_str()) {
+                trackers.push(url);
+            }
+        }
+        params.extend(
+            trackers
+                .iter()
+                .map(|url| format!("tr={}", percent_encode(url))),
+        );
+        params.push(format!("xl={}", self.length));
+        params.extend(
+            self.get_web_seeds()
+                .iter()
+                .map(|url| format!("ws={}", percent_encode(url))),
+        );
+        format!("magnet:?{}", params.join("&"))
+    }
 }
 
 impl TryFrom<&[u8]> for Torrent {
     type Error = Error;
 
     fn try_from(data: &[u8]) -> Result<Self> {
-        let (decoded, _) = decoders::decode(data).map_err(|err| Error::DecodeError(err))?;
-        Torrent::try_from(&decoded)
+        let (dict, _) = decoders::decode_dict_with_spans(data).map_err(Error::DecodeError)?;
+        let raw_info: Option<&[u8]> = dict
+            .get("info".as_bytes())
+            .map(|(_, span)| &data[span.clone()]);
+        let decoded = DataType::Dict(
+            dict.into_iter()
+                .map(|(key, (value, _))| (key, value))
+                .collect(),
+        );
+        Torrent::parse(&decoded, raw_info)
     }
 }
 
+/// Note that the info hash of a torrent built from an already decoded value is computed over the
+/// re-encoded 'info' dict, which only matches the original one if it was canonically encoded.
+/// Prefer parsing torrents from their raw bytes.
 impl TryFrom<&DataType> for Torrent {
     type Error = Error;
 
     fn try_from(data: &DataType) -> Result<Self> {
-        match data {
-            DataType::Dict(v) => {
-                let mut map: HashMap<String, DataType> = HashMap::new();
-                for (key, value) in v {
-                    map.insert(key.clone(), value.clone());
+        Torrent::parse(data, None)
+    }
+}
+
+impl Torrent {
+    /// Builds a torrent from the decoded metainfo dict. The info hash is computed over `raw_info`
+    /// (the original bytes of the 'info' dict) when it is known, or over the re-encoded dict.
+    fn parse(data: &DataType, raw_info: Option<&[u8]>) -> Result<Torrent> {
+        let metainfo: MetaInfo = serialization::from_data_type(data)
+            .map_err(|err| TorrentError::InvalidMetaInfo(err.to_string()))?;
+        let info: Info = metainfo.info;
+        let version: MetaVersion = match (info.meta_version.unwrap_or(1), &info.pieces) {
+            (1, Some(_)) => MetaVersion::V1,
+            (2, Some(_)) => MetaVersion::Hybrid,
+            (2, None) => MetaVersion::V2,
+            (1, None) => Err(TorrentError::MissingKey("pieces"))?,
+            (meta_version, _) => Err(TorrentError::UnsupportedMetaVersion(meta_version))?,
+        };
+        // The name is the file or the root directory the content is downloaded to.
+        if !is_safe_path_segment(&info.name) {
+            Err(TorrentError::InvalidName(info.name.clone()))?
+        }
+        let piece_length: u32 = match u32::try_from(info.piece_length) {
+            Ok(piece_length) if piece_length > 0 => piece_length,
+            _ => Err(TorrentError::InvalidPieceLength(info.piece_length))?,
+        };
+        // v2 pieces are the subtrees of the 16 KiB block merkle trees (BEP 52).
+        if version != MetaVersion::V1
+            && (!piece_length.is_power_of_two() || piece_length < DEFAULT_BLOCK_SIZE)
+        {
+            Err(TorrentError::InvalidV2PieceLength(piece_length))?
+        }
+        let file_tree: Option<Vec<FileTreeEntry>> = match version {
+            MetaVersion::V1 => None,
+            MetaVersion::V2 | MetaVersion::Hybrid => {
+                let file_tree: &DataType = data
+                    .get("info")
+                    .and_then(|info| info.get("file tree"))
+                    .ok_or(TorrentError::MissingKey("file tree"))?;
+                Some(parse_file_tree(file_tree)?)
+            }
+        };
+
+        let (files, multi_file): (Vec<TorrentFile>, bool) =
+            match (&file_tree, info.files, info.length) {
+                (Some(entries), None, None) => layout_v2_files(entries, &info.name, piece_length)?,
+                (_, Some(files), _) => (parse_files(&files)?, true),
+                (_, None, Some(length)) => {
+                    let file =
+                        TorrentFile::new(parse_file_length(length)?, [info.name.clone()].into(), 0);
+                    let file: TorrentFile = add_file_attributes(
+                        file,
+                        info.attr.as_deref(),
+                        info.symlink_path.as_deref(),
+                        info.sha1.as_ref().map(|sha1| sha1.as_slice()),
+                    )?;
+                    (vec![file], false)
                 }
-                let announce = map
-                    .get("announce")
-                    .ok_or_else(|| {
-                        Error::TorrentParseError("Could not find the 'announce' key.".to_owned())
-                    })?
-                    .as_str()
-                    .ok_or_else(|| {
-                        Error::TorrentParseError(
-                            "Could not convert the value for the 'announce' key into a string."
-                                .to_owned(),
-                        )
-                    })?;
-                let info = map.get("info").ok_or_else(|| {
-                    Error::TorrentParseError("Could not find the 'info' key.".to_owned())
-                })?;
-                let info_as_dict = info.as_dict().ok_or_else(|| {
-                    Error::TorrentParseError(
-                        "Could not convert the value for the 'info' key into a dict.".to_owned(),
-                    )
-                })?;
-                let length: u64 = info_as_dict
-                    .get("length")
-                    .ok_or_else(|| {
-                        Error::TorrentParseError("Could not find the 'length' key.".to_owned())
-                    })?
-                    .as_i64()
-                    .ok_or_else(|| {
-                        Error::TorrentParseError(
-                            "Could not convert the value of the 'length' key to i64.".to_owned(),
-                        )
-                    })? as u64;
-                let info_bencoded: &[u8] = &encoders::bencode(info);
-                let mut hasher = Sha1::new();
-                hasher.update(info_bencoded);
-                let sha1_hash = hasher.finalize();
-                let piece_length: u32 = info_as_dict
-                    .get("piece length")
-                    .ok_or_else(|| {
-                        Error::TorrentParseError("Could not find the 'length' key.".to_owned())
-                    })?
-                    .as_i64()
-                    .ok_or_else(|| {
-                        Error::TorrentParseError(
-                            "Could not convert the value of the 'piece length' key into i64."
-                                .to_owned(),
-                        )
-                    })? as u32;
-                let pieces_byte_string: &ByteString = info_as_dict
-                    .get("pieces")
-                    .ok_or_else(|| {
-                        Error::TorrentParseError("Could not find the 'pieces' key.".to_owned())
-                    })?
-                    .as_byte_string()
-                    .ok_or_else(|| {
-                        Error::TorrentParseError(
-                            "Could not convert the value of the 'pieces' key into a byte string."
-                                .to_owned(),
-                        )
-                    })?;
-                let pieces = parse_pieces(pieces_byte_string, length, piece_length);
-                Ok(Torrent {
-                    announce,
-                    length,
-                    info_hash: sha1_hash.into(),
-                    piece_length,
-                    pieces,
-                })
+                (None, None, None) => Err(TorrentError::MissingLengthOrFiles)?,
+            };
+        let files: Vec<TorrentFile> = match (version, &file_tree) {
+            (MetaVersion::Hybrid, Some(entries)) => add_pieces_roots(files, entries)?,
+            _ => files,
+        };
+        let length: u64 = files.iter().map(TorrentFile::get_end).max().unwrap_or(0);
+
+        let raw_info: Arc<[u8]> = match (raw_info, data.get("info")) {
+            (Some(raw_info), _) => raw_info.into(),
+            (None, Some(info)) => encoders::encode_to_vec(info).into(),
+            (None, None) => unreachable!("the 'info' dict has been deserialized"),
+        };
+        let info_hash_v1: Option<[u8; 20]> = match version {
+            MetaVersion::V1 | MetaVersion::Hybrid => Some(Sha1::digest(&raw_info).into()),
+            MetaVersion::V2 => None,
+        };
+        let info_hash_v2: Option<[u8; 32]> = match version {
+            MetaVersion::V1 => None,
+            MetaVersion::V2 | MetaVersion::Hybrid => Some(Sha256::digest(&raw_info).into()),
+        };
+
+        let v2_hashes: V2PieceHashes = match version {
+            MetaVersion::V1 => BTreeMap::new(),
+            MetaVersion::V2 | MetaVersion::Hybrid => {
+                let piece_layers = match data.get("piece layers") {
+                    Some(piece_layers) => parse_piece_layers(piece_layers)?,
+                    None => BTreeMap::new(),
+                };
+                parse_v2_piece_hashes(&files, &piece_layers, piece_length)?
             }
-            other => Err(Error::TorrentParseError(format!(
-                "Could not convert into a Torrent. Only dicts are supported, but got {:?}",
-                other
-            ))),
-        }
+        };
+        let pieces: Arc<[Piece]> = match &info.pieces {
+            Some(hashes) => parse_pieces(hashes, length, piece_length, &v2_hashes)?,
+            None => v2_hashes
+                .iter()
+                .map(|(begin, (length, root))| {
+                    let index: u32 = (begin / piece_length as u64) as u32;
+                    new_piece(index, None, *root, *begin, *length)
+                })
+                .collect(),
+        };
+
+        Ok(Torrent {
+            announce: metainfo.announce,
+            announce_list: metainfo.announce_list.unwrap_or_default(),
+            comment: metainfo.comment,
+            created_by: metainfo.created_by,
+            creation_date: metainfo.creation_date,
+            encoding: metainfo.encoding,
+            private: info.private == Some(1),
+            source: info.source,
+            web_seeds: parse_web_seeds(data.get("url-list")),
+            name: info.name,
+            length,
+            files: files.into(),
+            multi_file,
+            version,
+            info_hash_v1,
+            info_hash_v2,
+            raw_info,
+            piece_length,
+            pieces,
+        })
     }
 }
 
 impl fmt::Display for Torrent {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Tracker URL: {}", &self.announce)?;
+        if !self.get_announce_list().is_empty() {
+            writeln!(f, "Announce List:")?;
+            for (index, tier) in self.get_announce_list().iter().enumerate() {
+                writeln!(f, "  Tier {}: {}", index + 1, tier.join(", "))?;
+            }
+        }
+        writeln!(f, "Name: {}", &self.name)?;
+        writeln!(f, "Length: {}", self.length)?;
+        writeln!(f, "Info Hash: {}", hex::encode(self.get_info_hash()))?;
+        if let Some(info_hash_v2) = self.get_info_hash_v2() {
+            writeln!(f, "Info Hash v2: {}", hex::encode(info_hash_v2))?;
+            writeln!(f, "Meta Version: {}", self.get_version())?;
+        }
+        writeln!(f, "Piece Length: {}", self.piece_length)?;
+        if let Some(comment) = self.get_comment() {
+            writeln!(f, "Comment: {comment}")?;
+        }
+        if let Some(created_by) = self.get_created_by() {
+            writeln!(f, "Created By: {created_by}")?;
+        }
+        if let Some(creation_date) = self.get_creation_date() {
+            writeln!(
+                f,
+                "Creation Date: {} ({creation_date})",
+                format_unix_timestamp(creation_date)
+            )?;
+        }
+        if let Some(encoding) = self.get_encoding() {
+            writeln!(f, "Encoding: {encoding}")?;
+        }
+        if self.is_private() {
+            writeln!(f, "Private: yes")?;
+        }
+        if let Some(source) = self.get_source() {
+            writeln!(f, "Source: {source}")?;
+        }
+        if !self.get_web_seeds().is_empty() {
+            writeln!(f, "Web Seeds: {}", self.get_web_seeds().join(", "))?;
+        }
+        if self.is_multi_file() {
+            writeln!(f, "Files:")?;
+            for file in self.files.iter() {
+                let attributes: FileAttributes = file.get_attributes();
+                if attributes.is_padding() {
+                    continue;
+                }
+                write!(f, "  {} ({})", file.get_path().join("/"), file.get_length())?;
+                if attributes != FileAttributes::default() {
+                    write!(f, " [{attributes}]")?;
+                }
+                if let Some(sha1) = file.get_sha1() {
+                    write!(f, " SHA-1: {}", hex::encode(sha1))?;
+                }
+                writeln!(f)?;
+            }
+        }
         write!(
             f,
-            "Tracker URL: {}\nLength: {}\nInfo Hash: {}\nPiece Length: {}\nPiece Hashes:\n{}",
-            &self.announce,
-            self.length,
-            hex::encode(self.get_info_hash().as_ref()),
-            self.piece_length,
+            "Piece Hashes:\n{}",
             self.pieces
                 .iter()
-                .map(|piece| hex::encode(piece.get_hash()))
+                .map(|piece| {
+                    piece
+                        .get_hash()
+                        .map(hex::encode)
+                        .or_else(|| piece.get_hash_v2().map(hex::encode))
+                        .unwrap_or_default()
+                })
                 .collect::<Vec<String>>()
                 .join("\n")
         )
     }
 }
 
-fn parse_pieces(byte_str: &ByteString, torrent_length: u64, piece_length: u32) -> Arc<[Piece]> {
+/// Percent-encodes everything but the unreserved characters of RFC 3986.
+fn percent_encode(value: &str) -> String {
+    let mut result = String::with_capacity(value.len());
+    for byte in value.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => {
+                result.push(byte as char)
+            }
+            _ => result.push_str(&format!("%{byte:02X}")),
+        }
+    }
+    result
+}
+
+/// The web seed URLs (BEP 19), from a 'url-list' that is either a list or a single URL.
+fn parse_web_seeds(url_list: Option<&DataType>) -> Vec<String> {
+    match url_list {
+        Some(DataType::List(urls)) => urls.iter().filter_map(DataType::as_str).collect(),
+        Some(url) => url
+            .as_str()
+            .filter(|url| !url.is_empty())
+            .into_iter()
+            .collect(),
+        None => Vec::new(),
+    }
+}
+
+/// Formats seconds since the Unix epoch as a UTC date and time, e.g. `2016-01-10 23:23:14 UTC`.
+fn format_unix_timestamp(timestamp: i64) -> String {
+    let days: i64 = timestamp.div_euclid(86_400);
+    let seconds: i64 = timestamp.rem_euclid(86_400);
+    // Converts days since the epoch to a proleptic Gregorian date (Howard Hinnant's algorithm).
+    let z: i64 = days + 719_468;
+    let era: i64 = z.div_euclid(146_097);
+    let day_of_era: i64 = z.rem_euclid(146_097);
+    let year_of_era: i64 =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year: i64 = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let shifted_month: i64 = (5 * day_of_year + 2) / 153;
+    let day: i64 = day_of_year - (153 * shifted_month + 2) / 5 + 1;
+    let month: i64 = if shifted_month < 10 {
+        shifted_month + 3
+    } else {
+        shifted_month - 9
+    };
+    let year: i64 = year_of_era + era * 400 + i64::from(month <= 2);
+    format!(
+        "{year:04}-{month:02}-{day:02} {:02}:{:02}:{:02} UTC",
+        seconds / 3600,
+        seconds % 3600 / 60,
+        seconds % 60
+    )
+}
+
+fn parse_files(files: &[FileInfo]) -> Result<Vec<TorrentFile>> {
+    let mut result: Vec<TorrentFile> = Vec::with_capacity(files.len());
+    let mut begin: u64 = 0;
+    for (index, file) in files.iter().enumerate() {
+        let length: u64 = parse_file_length(file.length)?;
+        if file.path.is_empty() {
+            Err(TorrentError::EmptyFilePath(index))?
+        }
+        if !file
+            .path
+            .iter()
+            .all(|segment| is_safe_path_segment(segment))
+        {
+            Err(TorrentError::InvalidPathSegment(index))?
+        }
+        result.push(add_file_attributes(
+            TorrentFile::new(length, file.path.clone().into(), begin),
+            file.attr.as_deref(),
+            file.symlink_path.as_deref(),
+            file.sha1.as_ref().map(|sha1| sha1.as_slice()),
+        )?);
+        begin = begin
+            .checked_add(length)
+            .ok_or(TorrentError::TotalLengthOverflow)?;
+    }
+    Ok(result)
+}
+
+/// Adds the BEP 47 keys of a file: its 'attr' string, the 'symlink path' that symlinks must
+/// have, and its 'sha1' hash.
+fn add_file_attributes(
+    file: TorrentFile,
+    attr: Option<&str>,
+    symlink_path: Option<&[String]>,
+    sha1: Option<&[u8]>,
+) -> Result<TorrentFile> {
+    let path: String = file.get_path().join("/");
+    let attributes: FileAttributes = attr.map(FileAttributes::parse).unwrap_or_default();
+    let symlink_path: Option<Box<[String]>> = match symlink_path {
+        _ if !attributes.is_symlink() => None,
+        Some(segments)
+            if !segments.is_empty()
+                && segments.iter().all(|segment| is_safe_path_segment(segment)) =>
+        {
+            Some(segments.into())
+        }
+        _ => Err(TorrentError::InvalidSymlinkPath(path.clone()))?,
+    };
+    let sha1: Option<[u8; 20]> = sha1
+        .map(|sha1| {
+            sha1.try_into()
+                .map_err(|_| TorrentError::InvalidFileSha1(path.clone()))
+        })
+        .transpose()?;
+    Ok(file.with_attributes(attributes, symlink_path, sha1))
+}
+
+/// Lays the files of a v2-only torrent out as if they were padded to piece boundaries, like the
+/// v1 files of hybrid torrents, since v2 pieces never span several files.
+fn layout_v2_files(
+    entries: &[FileTreeEntry],
+    name: &str,
+    piece_length: u32,
+) -> Result<(Vec<TorrentFile>, bool)> {
+    // A single file named after the torrent makes a single-file torrent.
+    let multi_file: bool = !(entries.len() == 1 && entries[0].get_path() == [name]);
+    let mut files: Vec<TorrentFile> = Vec::with_capacity(entries.len());
+    let mut begin: u64 = 0;
+    for entry in entries {
+        files.push(TorrentFile::new_v2(
+            entry.get_length(),
+            entry.get_path().into(),
+            begin,
+            entry.get_pieces_root().copied(),
+        ));
+        begin = begin
+            .checked_add(entry.get_length())
+            .and_then(|end| end.checked_next_multiple_of(piece_length as u64))
+            .ok_or(TorrentError::TotalLengthOverflow)?;
+    }
+    Ok((files, multi_file))
+}
+
+/// Attaches the pieces roots of the v2 file tree of a hybrid torrent to the matching v1 files.
+/// Only the padding files (BEP 47) can be missing from the file tree.
+fn add_pieces_roots(
+    files: Vec<TorrentFile>,
+    entries: &[FileTreeEntry],
+) -> Result<Vec<TorrentFile>> {
+    let mut entries_by_path: BTreeMap<&[String], &FileTreeEntry> = entries
+        .iter()
+        .map(|entry| (entry.get_path(), entry))
+        .collect();
+    let files: Vec<TorrentFile> = files
+        .into_iter()
+        .map(|file| match entries_by_path.remove(file.get_path()) {
+            Some(entry) if entry.get_length() == file.get_length() => {
+                Ok(file.with_pieces_root(entry.get_pieces_root().copied()))
+            }
+            Some(_) => Err(TorrentError::FileLengthMismatch(file.get_path().join("/")).into()),
+            None if file.get_attributes().is_padding() => Ok(file),
+            None => Err(TorrentError::MissingFromFileTree(file.get_path().join("/")).into()),
+        })
+        .collect::<Result<Vec<TorrentFile>>>()?;
+    match entries_by_path.keys().next() {
+        Some(path) => Err(TorrentError::MissingFromFiles(path.join("/")))?,
+        None => Ok(files),
+    }
+}
+
+/// The lengths and merkle roots of the v2 pieces, keyed by their begin.
+type V2PieceHashes = BTreeMap<u64, (u32, Option<MerkleRoot>)>;
+
+/// Maps the begin of every v2 piece to its length and the root of its merkle subtree.
+///
+/// The piece hashes of files larger than a piece are in 'piece layers', while the pieces root of
+/// smaller files is the hash of their only piece. A piece layer that is missing can be fetched
+/// from peers later on, so the pieces of its file are left without a root.
+fn parse_v2_piece_hashes(
+    files: &[TorrentFile],
+    piece_layers: &PieceLayers,
+    piece_length: u32,
+) -> Result<V2PieceHashes> {
+    let mut hashes: V2PieceHashes = BTreeMap::new();
+    for file in files {
+        let Some(pieces_root) = file.get_pieces_root() else {
+            continue;
+        };
+        if !file.get_begin().is_multiple_of(piece_length as u64) {
+            Err(TorrentError::UnalignedFile(file.get_path().join("/")))?
+        }
+        let count: u64 = file.get_length().div_ceil(piece_length as u64);
+        let roots: Vec<Option<MerkleRoot>> = if count == 1 {
+            let blocks_count: u64 = file.get_length().div_ceil(MERKLE_BLOCK_SIZE as u64);
+            let leaves_count: u32 = blocks_count.next_power_of_two() as u32;
+            vec![Some(MerkleRoot::new(*pieces_root, leaves_count))]
+        } else {
+            match piece_layers.get(pieces_root) {
+                Some(layer) => {
+                    check_piece_layer(file, layer, piece_length)?;
+                    piece_merkle_roots(layer, piece_length)
+                }
+                None => vec![None; count as usize],
+            }
+        };
+        for (i, root) in roots.into_iter().enumerate() {
+            let begin: u64 = file.get_begin() + i as u64 * piece_length as u64;
+            let length: u32 = (file.get_end() - begin).min(piece_length as u64) as u32;
+            hashes.insert(begin, (length, root));
+        }
+    }
+    Ok(hashes)
+}
+
+/// Checks that the piece layer of a file has a hash per piece, and that they hash up to the
+/// pieces root of the file.
+fn check_piece_layer(file: &TorrentFile, layer: &[[u8; 32]], piece_length: u32) -> Result<()> {
+    let count: u64 = file.get_length().div_ceil(piece_length as u64);
+    if layer.len() as u64 != count {
+        Err(TorrentError::PieceLayerLengthMismatch {
+            path: file.get_path().join("/"),
+            expected: count,
+        })?
+    }
+    match file.get_pieces_root() {
+        Some(pieces_root) if verify_piece_layer(pieces_root, layer, piece_length) => Ok(()),
+        _ => Err(TorrentError::PieceLayerMismatch(file.get_path().join("/")))?,
+    }
+}
+
+fn piece_merkle_roots(layer: &[[u8; 32]], piece_length: u32) -> Vec<Option<MerkleRoot>> {
+    let leaves_count: u32 = piece_length / MERKLE_BLOCK_SIZE as u32;
+    layer
+        .iter()
+        .map(|hash| Some(MerkleRoot::new(*hash, leaves_count)))
+        .collect()
+}
+
+fn parse_file_length(length: i64) -> Result<u64> {
+    Ok(u64::try_from(length).map_err(|_| TorrentError::NegativeFileLength(length))?)
+}
+
+/// Path segments must name an entry inside the download directory, so they can not be empty,
+/// refer to the current or parent directory, or contain path separators.
+pub(crate) fn is_safe_path_segment(segment: &str) -> bool {
+    !segment.is_empty() && segment != "." && segment != ".." && !segment.contains(['/', '\\'])
+}
+
+/// Builds the pieces from the concatenated SHA-1 hashes of the 'pieces' value, along with the v2
+/// hashes of hybrid torrents, keyed by the begin of their pieces.
+fn parse_pieces(
+    byte_str: &[u8],
+    torrent_length: u64,
+    piece_length: u32,
+    v2_hashes: &V2PieceHashes,
+) -> Result<Arc<[Piece]>> {
     let mut hashes: Vec<[u8; 20]> = vec![];
-    // TODO: replace with an actual error
-    assert!(
-        byte_str.get_data().len() % 20 == 0,
-        "Invalid 'pieces' value: the length of the byte string is {} and is not a multiple of 20.",
-        byte_str.get_data().len()
-    );
-    for chunk in byte_str.get_data().chunks_exact(20) {
+    if !byte_str.len().is_multiple_of(20) {
+        Err(TorrentError::InvalidPiecesLength(byte_str.len()))?
+    }
+    // Every piece but the last one is full, so the pieces have to cover the whole content.
+    let expected_count: u64 = torrent_length.div_ceil(piece_length as u64);
+    if byte_str.len() as u64 / 20 != expected_count {
+        Err(TorrentError::PieceCountMismatch {
+            expected: expected_count,
+            actual: byte_str.len() as u64 / 20,
+            length: torrent_length,
+            piece_length,
+        })?
+    }
+    for chunk in byte_str.chunks_exact(20) {
         let mut hash: [u8; 20] = [0; 20];
-        // let hex_hash: String = hex::encode();
         hash.copy_from_slice(chunk);
         hashes.push(hash);
     }
@@ -183,35 +787,38 @@ fn parse_pieces(byte_str: &ByteString, torrent_length: u64, piece_length: u32) -
         } else {
             piece_length
         };
-        let blocks_count: u32 = piece_length / DEFAULT_BLOCK_SIZE;
-        let residue: u32 = piece_length % DEFAULT_BLOCK_SIZE;
-        let mut blocks: Vec<Block> =
-            Vec::with_capacity(blocks_count as usize + if residue > 0 { 1 } else { 0 });
-        for i in 0..blocks_count {
-            blocks.push(Block::new(i * DEFAULT_BLOCK_SIZE, DEFAULT_BLOCK_SIZE));
-        }
-        if residue > 0 {
-            blocks.push(Block::new(blocks_count * DEFAULT_BLOCK_SIZE, residue));
-        }
-        pieces.push(Piece::new(
+        let hash_v2: Option<MerkleRoot> = v2_hashes.get(&position).and_then(|(_, root)| *root);
+        pieces.push(new_piece(
             index as u32,
-            *hash,
-            blocks.into_boxed_slice(),
+            Some(*hash),
+            hash_v2,
             position,
+            piece_length,
         ));
         position += piece_length as u64;
     }
 
-    let sum: u32 = pieces.iter().map(|p| p.get_length()).sum();
-    println!(
-        "get_pieces: hashes = {}, pieces = {}, torrent_length = {}, pieces_sum = {}",
-        hashes.len(),
-        pieces.len(),
-        torrent_length,
-        sum
-    );
+    Ok(pieces.into())
+}
 
-    pieces.into()
+fn new_piece(
+    index: u32,
+    hash: Option<[u8; 20]>,
+    hash_v2: Option<MerkleRoot>,
+    begin: u64,
+    length: u32,
+) -> Piece {
+    let blocks_count: u32 = length / DEFAULT_BLOCK_SIZE;
+    let residue: u32 = length % DEFAULT_BLOCK_SIZE;
+    let mut blocks: Vec<Block> =
+        Vec::with_capacity(blocks_count as usize + if residue > 0 { 1 } else { 0 });
+    for i in 0..blocks_count {
+        blocks.push(Block::new(i * DEFAULT_BLOCK_SIZE, DEFAULT_BLOCK_SIZE));
+    }
+    if residue > 0 {
+        blocks.push(Block::new(blocks_count * DEFAULT_BLOCK_SIZE, residue));
+    }
+    Piece::new(index, hash, hash_v2, blocks.into_boxed_slice(), begin)
 }
 
 fn get_last_piece_length(torrent_length: u64, piece_length: u32) -> u32 {
@@ -224,3 +831,653 @@ fn get_last_piece_length(torrent_length: u64, piece_length: u32) -> u32 {
 }
 
 // endregion:   ---Torrent
+
+#[cfg(test)]
+mod tests {
+    use proptest::collection::vec;
+    use proptest::prelude::*;
+
+    use super::*;
+    use crate::magnet::MagnetLink;
+    use crate::torrent::{block_hashes, merkle_root};
+    use crate::types::ByteString;
+
+    #[test]
+    fn test_single_file() {
+        let torrent: Torrent = std::fs::read("sample.torrent")
+            .unwrap()
+            .as_slice()
+            .try_into()
+            .unwrap();
+        assert!(!torrent.is_multi_file());
+        assert_eq!(torrent.get_name(), "sample.txt");
+        assert_eq!(torrent.get_length(), 92063);
+        assert_eq!(torrent.get_files().len(), 1);
+        assert_eq!(torrent.get_files()[0].get_path(), ["sample.txt".to_owned()]);
+        assert_eq!(
+            hex::encode(torrent.get_info_hash()),
+            "d69f91e6b2ae4c542468d1073a71d4ea13879a7f"
+        );
+    }
+
+    #[test]
+    fn test_multi_file() {
+        let data: &[u8] = b"d8:announce9:http://a/4:infod5:filesld6:lengthi5e4:pathl1:a5:b.txteed6:lengthi20e4:pathl5:c.txteee4:name4:root12:piece lengthi16e6:pieces40:aaaaaaaaaaaaaaaaaaaabbbbbbbbbbbbbbbbbbbbee";
+        let torrent: Torrent = data.try_into().unwrap();
+        assert!(torrent.is_multi_file());
+        assert_eq!(torrent.get_name(), "root");
+        assert_eq!(torrent.get_length(), 25);
+        let files = torrent.get_files();
+        assert_eq!(files.len(), 2);
+        assert_eq!(files[0].get_path(), ["a".to_owned(), "b.txt".to_owned()]);
+        assert_eq!(files[0].get_begin(), 0);
+        assert_eq!(files[1].get_path(), ["c.txt".to_owned()]);
+        assert_eq!(files[1].get_begin(), 5);
+        assert_eq!(torrent.get_pieces().len(), 2);
+        assert_eq!(torrent.get_pieces()[1].get_length(), 9);
+    }
+
+    #[test]
+    fn test_selected_pieces() {
+        let data: &[u8] = b"d8:announce9:http://a/4:infod5:filesld6:lengthi5e4:pathl1:aeed6:lengthi20e4:pathl1:beed6:lengthi16e4:pathl1:ceee4:name4:root12:piece lengthi16e6:pieces60:aaaaaaaaaaaaaaaaaaaabbbbbbbbbbbbbbbbbbbbccccccccccccccccccccee";
+        let torrent: Torrent = data.try_into().unwrap();
+        assert_eq!(torrent.get_selected_pieces(|_| true), [0, 1, 2]);
+        assert_eq!(torrent.get_selected_pieces(|index| index == 0), [0]);
+        assert_eq!(torrent.get_selected_pieces(|index| index == 1), [0, 1]);
+        assert_eq!(torrent.get_selected_pieces(|index| index == 2), [1, 2]);
+        assert!(torrent.get_selected_pieces(|_| false).is_empty());
+    }
+
+    #[test]
+    fn test_to_magnet() {
+        let data: &[u8] = b"d8:announce9:http://a/13:announce-listll9:http://a/9:http://b/el9:http://c/ee4:infod6:lengthi5e4:name7:a b&c.d12:piece lengthi16e6:pieces20:aaaaaaaaaaaaaaaaaaaae8:url-list13:http://s/x y/e";
+        let torrent: Torrent = data.try_into().unwrap();
+        let magnet: String = torrent.to_magnet();
+        assert_eq!(
+            magnet,
+            format!(
+                "magnet:?xt=urn:btih:{}&dn=a%20b%26c.d&tr=http%3A%2F%2Fa%2F&tr=http%3A%2F%2Fb%2F\
+                 &tr=http%3A%2F%2Fc%2F&xl=5&ws=http%3A%2F%2Fs%2Fx%20y%2F",
+                hex::encode(torrent.get_info_hash())
+            )
+        );
+
+        let link = MagnetLink::parse(&magnet).unwrap();
+        assert_eq!(link.get_info_hash_v1(), Some(torrent.get_info_hash()));
+        assert_eq!(link.get_name(), Some("a b&c.d"));
+        assert_eq!(link.get_trackers(), ["http://a/", "http://b/", "http://c/"]);
+        assert_eq!(link.get_length(), Some(5));
+        assert_eq!(link.get_web_seeds(), ["http://s/x y/"]);
+    }
+
+    #[test]
+    fn test_hybrid_to_magnet() {
+        let torrent: Torrent = (&v2_metainfo(true, &three_hashes())).try_into().unwrap();
+        let link = MagnetLink::parse(&torrent.to_magnet()).unwrap();
+        assert_eq!(link.get_info_hash_v1(), torrent.info_hash_v1.as_ref());
+        assert_eq!(link.get_info_hash_v2(), torrent.get_info_hash_v2());
+
+        let torrent: Torrent = (&v2_metainfo(false, &three_hashes())).try_into().unwrap();
+        let link = MagnetLink::parse(&torrent.to_magnet()).unwrap();
+        assert_eq!(link.get_info_hash_v1(), None);
+        assert_eq!(link.get_info_hash_v2(), torrent.get_info_hash_v2());
+        assert_eq!(link.get_info_hash(), *torrent.get_info_hash());
+    }
+
+    #[test]
+    fn test_info_hash_of_non_canonical_torrent() {
+        // The keys of the 'info' dict are not sorted, so re-encoding it would produce other bytes.
+        let info: &[u8] =
+            b"d6:pieces20:aaaaaaaaaaaaaaaaaaaa4:name1:a12:piece lengthi16e6:lengthi5ee";
+        let data: &[u8] = &[b"d8:announce9:http://a/4:info", info, b"e"].concat();
+        let torrent: Torrent = data.try_into().unwrap();
+
+        let expected: [u8; 20] = Sha1::digest(info).into();
+        assert_eq!(*torrent.get_info_hash(), expected);
+
+        let (decoded, _) = decoders::decode(data).unwrap();
+        let reencoded: [u8; 20] =
+            Sha1::digest(encoders::bencode(decoded.get("info").unwrap())).into();
+        assert_ne!(*torrent.get_info_hash(), reencoded);
+    }
+
+    fn parse_info(info: &str) -> Result<Torrent> {
+        let data: String = format!("d8:announce9:http://a/4:info{info}e");
+        data.as_bytes().try_into()
+    }
+
+    fn torrent_error(result: Result<Torrent>) -> TorrentError {
+        match result {
+            Err(Error::TorrentError(err)) => err,
+            Err(err) => panic!("expected a torrent error, got {err:?}"),
+            Ok(_) => panic!("expected a torrent error, got a torrent"),
+        }
+    }
+
+    // The tests below are regressions for inputs that used to panic.
+
+    #[test]
+    fn test_zero_piece_length() {
+        let result = parse_info("d6:lengthi5e4:name1:a12:piece lengthi0e6:pieces0:e");
+        assert_eq!(torrent_error(result), TorrentError::InvalidPieceLength(0));
+    }
+
+    #[test]
+    fn test_negative_piece_length() {
+        let result = parse_info("d6:lengthi5e4:name1:a12:piece lengthi-16e6:pieces0:e");
+        assert_eq!(torrent_error(result), TorrentError::InvalidPieceLength(-16));
+    }
+
+    #[test]
+    fn test_v1_piece_length_not_power_of_two() {
+        let torrent: Torrent =
+            parse_info("d6:lengthi5e4:name1:a12:piece lengthi24e6:pieces20:aaaaaaaaaaaaaaaaaaaae")
+                .unwrap();
+        assert_eq!(torrent.get_piece_length(), 24);
+        assert_eq!(torrent.get_pieces()[0].get_length(), 5);
+    }
+
+    #[test]
+    fn test_piece_length_out_of_range() {
+        let result = parse_info("d6:lengthi5e4:name1:a12:piece lengthi4294967296e6:pieces0:e");
+        assert_eq!(
+            torrent_error(result),
+            TorrentError::InvalidPieceLength(4294967296)
+        );
+    }
+
+    #[test]
+    fn test_pieces_not_multiple_of_20() {
+        let result =
+            parse_info("d6:lengthi5e4:name1:a12:piece lengthi16e6:pieces19:aaaaaaaaaaaaaaaaaaae");
+        assert_eq!(torrent_error(result), TorrentError::InvalidPiecesLength(19));
+    }
+
+    #[test]
+    fn test_pieces_count_mismatch() {
+        let result =
+            parse_info("d6:lengthi17e4:name1:a12:piece lengthi16e6:pieces20:aaaaaaaaaaaaaaaaaaaae");
+        assert_eq!(
+            torrent_error(result),
+            TorrentError::PieceCountMismatch {
+                expected: 2,
+                actual: 1,
+                length: 17,
+                piece_length: 16
+            }
+        );
+    }
+
+    #[test]
+    fn test_total_length_overflow() {
+        let result = parse_info("d5:filesld6:lengthi9223372036854775807e4:pathl1:aeed6:lengthi9223372036854775807e4:pathl1:beed6:lengthi9223372036854775807e4:pathl1:ceee4:name1:a12:piece lengthi16e6:pieces0:e");
+        assert_eq!(torrent_error(result), TorrentError::TotalLengthOverflow);
+    }
+
+    fn torrent_like_info() -> impl Strategy<Value = DataType> {
+        let file = (any::<i64>(), vec("[a-z.]{0,3}", 0..3)).prop_map(|(length, path)| {
+            let path: Vec<DataType> = path
+                .iter()
+                .map(|segment| DataType::ByteString(segment.as_str().into()))
+                .collect();
+            DataType::Dict(BTreeMap::from([
+                ("length".into(), DataType::Integer(length)),
+                ("path".into(), DataType::List(path)),
+            ]))
+        });
+        (
+            prop_oneof![any::<i64>(), 0i64..64],
+            prop_oneof![any::<i64>(), 0i64..256],
+            vec(file, 0..4),
+            vec(any::<u8>(), 0..120),
+        )
+            .prop_map(|(piece_length, length, files, pieces)| {
+                let mut info: BTreeMap<ByteString, DataType> = BTreeMap::from([
+                    ("name".into(), DataType::ByteString("a".into())),
+                    ("piece length".into(), DataType::Integer(piece_length)),
+                    (
+                        "pieces".into(),
+                        DataType::ByteString(pieces.as_slice().into()),
+                    ),
+                ]);
+                if files.is_empty() {
+                    info.insert("length".into(), DataType::Integer(length));
+                } else {
+                    info.insert("files".into(), DataType::List(files));
+                }
+                DataType::Dict(BTreeMap::from([
+                    ("announce".into(), DataType::ByteString("http://a/".into())),
+                    ("info".into(), DataType::Dict(info)),
+                ]))
+            })
+    }
+
+    proptest! {
+        #[test]
+        fn test_parse_does_not_panic(metainfo in torrent_like_info()) {
+            let bytes: Vec<u8> = encoders::encode_to_vec(&metainfo);
+            let _ = Torrent::try_from(bytes.as_slice());
+        }
+    }
+
+    #[test]
+    fn test_optional_metainfo_fields() {
+        let data: &[u8] = b"d8:announce9:http://a/13:announce-listll9:http://a/9:http://b/el9:http://c/ee7:comment5:hello10:created by4:test13:creation datei1452468194e8:encoding5:UTF-84:infod6:lengthi5e4:name1:a12:piece lengthi16e6:pieces20:aaaaaaaaaaaaaaaaaaaa7:privatei1e6:source3:abcee";
+        let torrent: Torrent = data.try_into().unwrap();
+        assert_eq!(
+            torrent.get_announce_list(),
+            [vec!["http://a/", "http://b/"], vec!["http://c/"]]
+        );
+        assert_eq!(torrent.get_comment(), Some("hello"));
+        assert_eq!(torrent.get_created_by(), Some("test"));
+        assert_eq!(torrent.get_creation_date(), Some(1452468194));
+        assert_eq!(torrent.get_encoding(), Some("UTF-8"));
+        assert!(torrent.is_private());
+        assert_eq!(torrent.get_source(), Some("abc"));
+        let display: String = torrent.to_string();
+        assert!(display.contains("  Tier 1: http://a/, http://b/\n  Tier 2: http://c/\n"));
+        assert!(display.contains("Creation Date: 2016-01-10 23:23:14 UTC (1452468194)\n"));
+        assert!(display.contains("Private: yes\n"));
+    }
+
+    #[test]
+    fn test_missing_optional_metainfo_fields() {
+        let torrent: Torrent = parse_info(
+            "d6:lengthi5e4:name1:a12:piece lengthi16e6:pieces20:aaaaaaaaaaaaaaaaaaaa7:privatei0ee",
+        )
+        .unwrap();
+        assert!(torrent.get_announce_list().is_empty());
+        assert_eq!(torrent.get_comment(), None);
+        assert_eq!(torrent.get_creation_date(), None);
+        assert!(!torrent.is_private());
+        assert!(!torrent.to_string().contains("Private"));
+    }
+
+    #[test]
+    fn test_format_unix_timestamp() {
+        assert_eq!(format_unix_timestamp(0), "1970-01-01 00:00:00 UTC");
+        assert_eq!(
+            format_unix_timestamp(951_782_400),
+            "2000-02-29 00:00:00 UTC"
+        );
+        assert_eq!(format_unix_timestamp(-1), "1969-12-31 23:59:59 UTC");
+    }
+
+    fn dict(entries: Vec<(&str, DataType)>) -> DataType {
+        DataType::Dict(
+            entries
+                .into_iter()
+                .map(|(key, value)| (key.into(), value))
+                .collect(),
+        )
+    }
+
+    fn bytes(value: &[u8]) -> DataType {
+        DataType::ByteString(value.into())
+    }
+
+    fn v2_file(length: i64, pieces_root: &[u8; 32]) -> DataType {
+        dict(vec![(
+            "",
+            dict(vec![
+                ("length", DataType::Integer(length)),
+                ("pieces root", bytes(pieces_root)),
+            ]),
+        )])
+    }
+
+    /// A torrent with a 5-byte file 'a' and a 40000-byte file 'dir/b', in pieces of 16 KiB.
+    fn v2_metainfo(hybrid: bool, piece_layer: &[u8]) -> DataType {
+        let file_tree: DataType = dict(vec![
+            ("a", v2_file(5, &[1; 32])),
+            ("dir", dict(vec![("b", v2_file(40000, &layer_root()))])),
+        ]);
+        let mut info: Vec<(&str, DataType)> = vec![
+            ("file tree", file_tree),
+            ("meta version", DataType::Integer(2)),
+            ("name", bytes(b"root")),
+            ("piece length", DataType::Integer(16384)),
+        ];
+        if hybrid {
+            let file = |length: i64, path: &[&[u8]]| {
+                let path: Vec<DataType> = path.iter().map(|segment| bytes(segment)).collect();
+                dict(vec![
+                    ("length", DataType::Integer(length)),
+                    ("path", DataType::List(path)),
+                ])
+            };
+            let mut padding: DataType = file(16379, &[b".pad", b"16379"]);
+            if let DataType::Dict(padding) = &mut padding {
+                padding.insert("attr".into(), bytes(b"p"));
+            }
+            let files: Vec<DataType> =
+                vec![file(5, &[b"a"]), padding, file(40000, &[b"dir", b"b"])];
+            info.push(("files", DataType::List(files)));
+            info.push(("pieces", bytes(&[b'p'; 80])));
+        }
+        dict(vec![
+            ("announce", bytes(b"http://a/")),
+            ("info", dict(info)),
+            (
+                "piece layers",
+                DataType::Dict(
+                    [(layer_root().as_slice().into(), bytes(piece_layer))]
+                        .into_iter()
+                        .collect(),
+                ),
+            ),
+        ])
+    }
+
+    fn three_hashes() -> Vec<u8> {
+        [[3; 32], [4; 32], [5; 32]].concat()
+    }
+
+    /// The pieces root of 'dir/b', whose pieces are single blocks, so the layer is padded with
+    /// zero hashes.
+    fn layer_root() -> [u8; 32] {
+        merkle_root(&[[3; 32], [4; 32], [5; 32]], 4, &[0; 32])
+    }
+
+    #[test]
+    fn test_v2_multi_file() {
+        let metainfo: DataType = v2_metainfo(false, &three_hashes());
+        let bytes: Vec<u8> = encoders::encode_to_vec(&metainfo);
+        let torrent: Torrent = bytes.as_slice().try_into().unwrap();
+
+        assert_eq!(torrent.get_version(), MetaVersion::V2);
+        assert!(torrent.is_multi_file());
+        let info: Vec<u8> = encoders::encode_to_vec(metainfo.get("info").unwrap());
+        let expected: [u8; 32] = Sha256::digest(&info).into();
+        assert_eq!(torrent.get_info_hash_v2(), Some(&expected));
+        assert_eq!(torrent.get_info_hash(), expected.first_chunk().unwrap());
+
+        let files = torrent.get_files();
+        assert_eq!(files[1].get_path(), ["dir", "b"]);
+        assert_eq!(files[1].get_begin(), 16384);
+        assert_eq!(files[1].get_pieces_root(), Some(&layer_root()));
+        let pieces = torrent.get_pieces();
+        let layout: Vec<(u32, u64, u32, [u8; 32])> = pieces
+            .iter()
+            .map(|piece| {
+                (
+                    piece.get_index(),
+                    piece.get_begin(),
+                    piece.get_length(),
+                    *piece.get_hash_v2().unwrap(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            layout,
+            [
+                (0, 0, 5, [1; 32]),
+                (1, 16384, 16384, [3; 32]),
+                (2, 32768, 16384, [4; 32]),
+                (3, 49152, 7232, [5; 32]),
+            ]
+        );
+        assert!(pieces.iter().all(|piece| piece.get_hash().is_none()));
+        let display: String = torrent.to_string();
+        assert!(display.contains(&format!("Info Hash v2: {}\n", hex::encode(expected))));
+        assert!(display.contains("Meta Version: v2\n"));
+    }
+
+    #[test]
+    fn test_v2_single_file() {
+        let metainfo: DataType = dict(vec![
+            ("announce", bytes(b"http://a/")),
+            (
+                "info",
+                dict(vec![
+                    ("file tree", dict(vec![("a", v2_file(5, &[1; 32]))])),
+                    ("meta version", DataType::Integer(2)),
+                    ("name", bytes(b"a")),
+                    ("piece length", DataType::Integer(16384)),
+                ]),
+            ),
+        ]);
+        let torrent: Torrent = (&metainfo).try_into().unwrap();
+        assert!(!torrent.is_multi_file());
+        assert_eq!(torrent.get_length(), 5);
+        assert_eq!(torrent.get_pieces().len(), 1);
+    }
+
+    #[test]
+    fn test_v2_invalid_piece_layer() {
+        let mismatched: Vec<u8> = [[3; 32], [5; 32], [4; 32]].concat();
+        let path: String = "dir/b".to_owned();
+        let cases: [(&[u8], TorrentError); 2] = [
+            (
+                &three_hashes()[..64],
+                TorrentError::PieceLayerLengthMismatch {
+                    path: path.clone(),
+                    expected: 3,
+                },
+            ),
+            (&mismatched, TorrentError::PieceLayerMismatch(path)),
+        ];
+        for (piece_layer, expected) in cases {
+            let metainfo: DataType = v2_metainfo(false, piece_layer);
+            assert_eq!(torrent_error(Torrent::try_from(&metainfo)), expected);
+        }
+        let metainfo: DataType = v2_metainfo(false, &[]);
+        assert!(matches!(
+            torrent_error(Torrent::try_from(&metainfo)),
+            TorrentError::InvalidPieceLayers(_)
+        ));
+    }
+
+    #[test]
+    fn test_v2_missing_piece_layer() {
+        let mut metainfo: DataType = v2_metainfo(false, &three_hashes());
+        let DataType::Dict(root) = &mut metainfo else {
+            unreachable!("the metainfo is a dict")
+        };
+        root.remove(b"piece layers".as_slice());
+        let mut torrent: Torrent = (&metainfo).try_into().unwrap();
+
+        let missing: Vec<&[String]> = torrent
+            .get_missing_piece_layers()
+            .iter()
+            .map(|file| file.get_path())
+            .collect();
+        assert_eq!(missing, [["dir", "b"]]);
+        let pieces = torrent.get_pieces();
+        assert_eq!(pieces.len(), 4);
+        assert_eq!(pieces[0].get_hash_v2(), Some(&[1; 32]));
+        assert!(pieces[1..]
+            .iter()
+            .all(|piece| piece.get_hash_v2().is_none()));
+
+        let hashes: [[u8; 32]; 3] = [[3; 32], [4; 32], [5; 32]];
+        assert!(torrent
+            .add_piece_layer(&layer_root(), &hashes[..2])
+            .is_err());
+        assert!(torrent.add_piece_layer(&[9; 32], &hashes).is_err());
+        torrent.add_piece_layer(&layer_root(), &hashes).unwrap();
+        assert!(torrent.get_missing_piece_layers().is_empty());
+        let pieces = torrent.get_pieces();
+        assert_eq!(pieces[3].get_hash_v2(), Some(&[5; 32]));
+        assert_eq!(
+            pieces[3].get_merkle_root(),
+            Some(&MerkleRoot::new([5; 32], 1))
+        );
+    }
+
+    #[test]
+    fn test_v2_piece_verification() {
+        let data: Vec<u8> = (0..40000).map(|i| i as u8).collect();
+        let blocks: Vec<[u8; 32]> = block_hashes(&data);
+        let pieces_root: [u8; 32] = merkle_root(&blocks, 4, &[0; 32]);
+        let metainfo: DataType = dict(vec![
+            ("announce", bytes(b"http://a/")),
+            (
+                "info",
+                dict(vec![
+                    ("file tree", dict(vec![("a", v2_file(40000, &pieces_root))])),
+                    ("meta version", DataType::Integer(2)),
+                    ("name", bytes(b"a")),
+                    ("piece length", DataType::Integer(65536)),
+                ]),
+            ),
+        ]);
+        let torrent: Torrent = (&metainfo).try_into().unwrap();
+        let pieces = torrent.get_pieces();
+        assert_eq!(pieces.len(), 1);
+        assert!(pieces[0].verify(&data));
+        assert!(!pieces[0].verify(&data[..39999]));
+    }
+
+    fn set_info_value(metainfo: &mut DataType, key: &str, value: DataType) {
+        let DataType::Dict(root) = metainfo else {
+            unreachable!("the metainfo is a dict")
+        };
+        let Some(DataType::Dict(info)) = root.get_mut(b"info".as_slice()) else {
+            unreachable!("the 'info' value is a dict")
+        };
+        info.insert(key.into(), value);
+    }
+
+    #[test]
+    fn test_v2_piece_length_too_small() {
+        let mut metainfo: DataType = v2_metainfo(false, &three_hashes());
+        set_info_value(&mut metainfo, "piece length", DataType::Integer(8192));
+        let result = Torrent::try_from(&metainfo);
+        assert_eq!(
+            torrent_error(result),
+            TorrentError::InvalidV2PieceLength(8192)
+        );
+    }
+
+    #[test]
+    fn test_v2_piece_length_not_power_of_two() {
+        let mut metainfo: DataType = v2_metainfo(true, &three_hashes());
+        set_info_value(&mut metainfo, "piece length", DataType::Integer(24576));
+        let result = Torrent::try_from(&metainfo);
+        assert_eq!(
+            torrent_error(result),
+            TorrentError::InvalidV2PieceLength(24576)
+        );
+    }
+
+    #[test]
+    fn test_hybrid() {
+        let metainfo: DataType = v2_metainfo(true, &three_hashes());
+        let bytes: Vec<u8> = encoders::encode_to_vec(&metainfo);
+        let torrent: Torrent = bytes.as_slice().try_into().unwrap();
+
+        assert_eq!(torrent.get_version(), MetaVersion::Hybrid);
+        let info: Vec<u8> = encoders::encode_to_vec(metainfo.get("info").unwrap());
+        let expected_v1: [u8; 20] = Sha1::digest(&info).into();
+        let expected_v2: [u8; 32] = Sha256::digest(&info).into();
+        assert_eq!(torrent.get_info_hash(), &expected_v1);
+        assert_eq!(torrent.get_info_hash_v2(), Some(&expected_v2));
+
+        let files = torrent.get_files();
+        assert_eq!(files.len(), 3);
+        assert_eq!(files[1].get_pieces_root(), None);
+        assert_eq!(files[2].get_pieces_root(), Some(&layer_root()));
+        let pieces = torrent.get_pieces();
+        assert_eq!(pieces.len(), 4);
+        assert!(pieces
+            .iter()
+            .all(|piece| piece.get_hash() == Some(&[b'p'; 20])));
+        assert_eq!(pieces[0].get_hash_v2(), Some(&[1; 32]));
+        assert_eq!(pieces[3].get_hash_v2(), Some(&[5; 32]));
+        let display: String = torrent.to_string();
+        assert!(display.contains(&format!("Info Hash: {}\n", hex::encode(expected_v1))));
+        assert!(display.contains(&format!("Info Hash v2: {}\n", hex::encode(expected_v2))));
+    }
+
+    #[test]
+    fn test_hybrid_mismatched_files() {
+        let mut metainfo: DataType = v2_metainfo(true, &three_hashes());
+        let file_tree: DataType = dict(vec![("a", v2_file(5, &[1; 32]))]);
+        set_info_value(&mut metainfo, "file tree", file_tree);
+        let result = Torrent::try_from(&metainfo);
+        assert_eq!(
+            torrent_error(result),
+            TorrentError::MissingFromFileTree("dir/b".to_owned())
+        );
+    }
+
+    #[test]
+    fn test_file_attributes() {
+        let torrent: Torrent = b"d8:announce9:http://a/4:infod5:filesld4:attr2:xh6:lengthi3e4:pathl1:ae4:sha120:ssssssssssssssssssssed4:attr1:p6:lengthi13e4:pathl4:.pad2:13eed4:attr1:l6:lengthi0e4:pathl4:linke12:symlink pathl1:aeee4:name4:root12:piece lengthi16e6:pieces20:aaaaaaaaaaaaaaaaaaaaee"
+            .as_slice()
+            .try_into()
+            .unwrap();
+        let files = torrent.get_files();
+        let attributes = files[0].get_attributes();
+        assert!(attributes.is_executable() && attributes.is_hidden());
+        assert_eq!(files[0].get_sha1(), Some(&[b's'; 20]));
+        assert!(files[1].get_attributes().is_padding());
+        assert!(files[2].get_attributes().is_symlink());
+        assert_eq!(
+            files[2].get_symlink_path(),
+            Some(["a".to_owned()].as_slice())
+        );
+        let display: String = torrent.to_string();
+        assert!(display.contains(&format!(
+            "Files:\n  a (3) [xh] SHA-1: {}\n  link (0) [l]\n",
+            hex::encode([b's'; 20])
+        )));
+    }
+
+    #[test]
+    fn test_invalid_file_attributes() {
+        let link_error = TorrentError::InvalidSymlinkPath("link".to_owned());
+        let invalid: [(&str, TorrentError); 3] = [
+            // A symlink without a target.
+            ("d5:filesld4:attr1:l6:lengthi0e4:pathl4:linkeee4:name4:root12:piece lengthi16e6:pieces0:e", link_error.clone()),
+            // A symlink out of the download directory.
+            ("d5:filesld4:attr1:l6:lengthi0e4:pathl4:linke12:symlink pathl2:..1:aeee4:name4:root12:piece lengthi16e6:pieces0:e", link_error),
+            // A truncated 'sha1'.
+            ("d6:lengthi3e4:name1:a12:piece lengthi16e6:pieces20:aaaaaaaaaaaaaaaaaaaa4:sha13:abce", TorrentError::InvalidFileSha1("a".to_owned())),
+        ];
+        for (info, expected) in invalid {
+            assert_eq!(torrent_error(parse_info(info)), expected, "{info}");
+        }
+    }
+
+    #[test]
+    fn test_multi_file_rejects_parent_dir_segment() {
+        let data: &[u8] = b"d8:announce9:http://a/4:infod5:filesld6:lengthi5e4:pathl2:..5:b.txteee4:name4:root12:piece lengthi16e6:pieces20:aaaaaaaaaaaaaaaaaaaaee";
+        let result: Result<Torrent> = data.try_into();
+        assert_eq!(torrent_error(result), TorrentError::InvalidPathSegment(0));
+    }
+
+    #[test]
+    fn test_input_length_limit() {
+        let limit: usize = decoders::DecodeOptions::default().max_input_length;
+        let data: Vec<u8> = vec![0; limit + 1];
+        let result: Result<Torrent> = data.as_slice().try_into();
+        assert!(matches!(
+            result,
+            Err(Error::DecodeError(
+                decoders::DecodeError::InputTooLarge { .. }
+            ))
+        ));
+    }
+
+    #[test]
+    fn test_rejects_absolute_name() {
+        let result = parse_info(
+            "d6:lengthi5e4:name4:/etc12:piece lengthi16e6:pieces20:aaaaaaaaaaaaaaaaaaaae",
+        );
+        assert_eq!(
+            torrent_error(result),
+            TorrentError::InvalidName("/etc".to_owned())
+        );
+    }
+
+    #[test]
+    fn test_rejects_parent_dir_name() {
+        let result = parse_info("d5:filesld6:lengthi5e4:pathl5:b.txteee4:name2:..12:piece lengthi16e6:pieces20:aaaaaaaaaaaaaaaaaaaae");
+        assert_eq!(
+            torrent_error(result),
+            TorrentError::InvalidName("..".to_owned())
+        );
+    }
+}
@@ -1,4 +1,8 @@
-use crate::bencode::decoders;
+use serde::{Deserialize, Serialize};
+use serde_bytes::ByteBuf;
+
+use crate::bencode::decoders::{self, DecodeOptions};
+use crate::bencode::serialization;
 use crate::torrent::Torrent;
 
 use crate::error::Error;
@@ -19,6 +23,16 @@ impl TrackerResponse {
     }
 }
 
+/// The bencoded body of a tracker response.
+#[derive(Deserialize, Serialize)]
+struct TrackerResponseBody {
+    #[serde(rename = "failure reason")]
+    failure_reason: Option<String>,
+    interval: Option<u32>,
+    /// Peers in the compact format: 4 bytes of IPv4 address followed by 2 bytes of port each.
+    peers: Option<ByteBuf>,
+}
+
 pub fn get(
     tracker_url: &str,
     info_hash: &[u8; 20],
@@ -31,36 +45,23 @@ pub fn get(
     let urlencoded_info_hash: String = urlencode_bytes(info_hash);
     let url = format!("{tracker_url}?info_hash={urlencoded_info_hash}&peer_id={peer_id}&port={port}&uploaded={uploaded}&downloaded={downloaded}&left={left}&compact=1");
     let res = reqwest::blocking::get(url).map_err(|err| Error::TrackerHttpError(err))?;
-    let bytes = res.bytes().map_err(|err| Error::TrackerHttpError(err))?;
-    let (body, _) = decoders::decode(bytes.as_ref()).map_err(|err| Error::DecodeError(err))?;
-    let body_dict = body
-        .as_dict()
-        .ok_or_else(|| Error::KeyNotFoundInTrackerResponse { key: "info".into() })?;
-    if body_dict.contains_key("failure reason") {
-        let reason = body_dict
-            .get("failure reason")
-            .ok_or_else(|| Error::KeyNotFoundInTrackerResponse {
-                key: "failure reason".into(),
-            })?
-            .as_str()
-            .ok_or_else(|| Error::TrackerFailureReasonIsNotUtf8)?;
+    let decoded = decoders::decode_from_reader(res, &DecodeOptions::default())
+        .map_err(Error::ReadDecodeError)?;
+    let body: TrackerResponseBody =
+        serialization::from_data_type(&decoded).map_err(Error::SerdeError)?;
+    if let Some(reason) = body.failure_reason {
         return Ok(TrackerResponse::failure(reason));
     }
-    let interval = body_dict
-        .get("interval")
+    let interval = body
+        .interval
         .ok_or_else(|| Error::KeyNotFoundInTrackerResponse {
             key: "interval".into(),
-        })?
-        .as_i64()
-        .ok_or_else(|| Error::TrackerIntervalIsNotInteger)? as u32;
-    let peers = body_dict
-        .get("peers")
+        })?;
+    let peers = body
+        .peers
         .ok_or_else(|| Error::KeyNotFoundInTrackerResponse {
             key: "peers".into(),
         })?
-        .as_byte_string()
-        .ok_or_else(|| Error::TrackerIntervalIsNotByteString)?
-        .get_data()
         .chunks_exact(6)
         .map(|chunk| {
             format!(
@@ -1,6 +1,6 @@
-use std::rc::Rc;
+use std::{borrow::Borrow, fmt, rc::Rc};
 
-#[derive(Clone, Debug, PartialEq)]
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct ByteString {
     data: Rc<[u8]>,
 }
@@ -15,4 +15,35 @@ impl ByteString {
     pub fn get_data(&self) -> &Rc<[u8]> {
         &self.data
     }
+
+    /// Returns the content as a string slice if it is valid UTF-8.
+    pub fn as_str(&self) -> Option<&str> {
+        std::str::from_utf8(&self.data).ok()
+    }
+}
+
+impl From<&[u8]> for ByteString {
+    fn from(value: &[u8]) -> Self {
+        ByteString { data: value.into() }
+    }
+}
+
+impl From<&str> for ByteString {
+    fn from(value: &str) -> Self {
+        value.as_bytes().into()
+    }
+}
+
+/// Formats the content as UTF-8, replacing invalid sequences.
+impl fmt::Display for ByteString {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", String::from_utf8_lossy(&self.data))
+    }
+}
+
+// Lets maps keyed by byte strings be queried with plain byte slices, e.g. `dict.get(b"info".as_slice())`.
+impl Borrow<[u8]> for ByteString {
+    fn borrow(&self) -> &[u8] {
+        &self.data
+    }
 }
@@ -7,7 +7,7 @@ pub enum DataType {
     Integer(i64),
     ByteString(ByteString),
     List(Vec<DataType>),
-    Dict(BTreeMap<String, DataType>),
+    Dict(BTreeMap<ByteString, DataType>),
 }
 
 impl DataType {
@@ -25,13 +25,18 @@ impl DataType {
         }
     }
 
-    pub fn as_dict(&self) -> Option<&BTreeMap<String, DataType>> {
+    pub fn as_dict(&self) -> Option<&BTreeMap<ByteString, DataType>> {
         match self {
             Self::Dict(dict) => Some(dict),
             _ => None,
         }
     }
 
+    /// Looks up a value by a UTF-8 key if this is a dict.
+    pub fn get(&self, key: &str) -> Option<&DataType> {
+        self.as_dict().and_then(|dict| dict.get(key.as_bytes()))
+    }
+
     pub fn as_byte_string(&self) -> Option<&ByteString> {
         match self {
             Self::ByteString(byte_str) => Some(byte_str),
@@ -54,7 +59,10 @@ impl From<DataType> for serde_json::Value {
             DataType::Dict(dict) => {
                 let mut map: serde_json::Map<String, serde_json::Value> = serde_json::Map::new();
                 for (key, value) in dict {
-                    map.insert(key, value.into());
+                    map.insert(
+                        String::from_utf8_lossy(key.get_data()).into_owned(),
+                        value.into(),
+                    );
                 }
                 serde_json::Value::Object(map)
             }
@@ -0,0 +1,109 @@
+use std::collections::BTreeMap;
+
+use crate::types::{ByteString, DataType};
+
+/// A decoded bencoded value that borrows its byte strings from the input buffer.
+///
+/// Decoding into it does not copy any byte strings, so it is the cheaper option for large inputs
+/// that are only inspected. Use `into_owned` to get a `DataType` that outlives the input.
+#[derive(Clone, Debug, PartialEq)]
+pub enum DataTypeRef<'a> {
+    Integer(i64),
+    ByteString(&'a [u8]),
+    List(Vec<DataTypeRef<'a>>),
+    Dict(BTreeMap<&'a [u8], DataTypeRef<'a>>),
+}
+
+impl<'a> DataTypeRef<'a> {
+    pub fn as_i64(&self) -> Option<i64> {
+        match self {
+            Self::Integer(value) => Some(*value),
+            _ => None,
+        }
+    }
+
+    pub fn as_bytes(&self) -> Option<&'a [u8]> {
+        match self {
+            Self::ByteString(bytes) => Some(bytes),
+            _ => None,
+        }
+    }
+
+    pub fn as_list(&self) -> Option<&[DataTypeRef<'a>]> {
+        match self {
+            Self::List(list) => Some(list),
+            _ => None,
+        }
+    }
+
+    pub fn as_dict(&self) -> Option<&BTreeMap<&'a [u8], DataTypeRef<'a>>> {
+        match self {
+            Self::Dict(dict) => Some(dict),
+            _ => None,
+        }
+    }
+
+    /// Looks up a value by a UTF-8 key if this is a dict.
+    pub fn get(&self, key: &str) -> Option<&DataTypeRef<'a>> {
+        self.as_dict().and_then(|dict| dict.get(key.as_bytes()))
+    }
+
+    /// Copies the borrowed byte strings into an owned value.
+    pub fn into_owned(self) -> DataType {
+        match self {
+            Self::Integer(value) => DataType::Integer(value),
+            Self::ByteString(bytes) => DataType::ByteString(ByteString::from(bytes)),
+            Self::List(list) => {
+                DataType::List(list.into_iter().map(DataTypeRef::into_owned).collect())
+            }
+            Self::Dict(dict) => DataType::Dict(
+                dict.into_iter()
+                    .map(|(key, value)| (ByteString::from(key), value.into_owned()))
+                    .collect(),
+            ),
+        }
+    }
+}
+
+impl From<DataTypeRef<'_>> for DataType {
+    fn from(value: DataTypeRef<'_>) -> Self {
+        value.into_owned()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_into_owned() {
+        let value = DataTypeRef::Dict(BTreeMap::from_iter([(
+            "list".as_bytes(),
+            DataTypeRef::List(vec![
+                DataTypeRef::Integer(1),
+                DataTypeRef::ByteString("abc".as_bytes()),
+            ]),
+        )]));
+        assert_eq!(
+            value.into_owned(),
+            DataType::Dict(BTreeMap::from_iter([(
+                "list".into(),
+                DataType::List(vec![
+                    DataType::Integer(1),
+                    DataType::ByteString("abc".into())
+                ])
+            )]))
+        );
+    }
+
+    #[test]
+    fn test_get() {
+        let value = DataTypeRef::Dict(BTreeMap::from_iter([(
+            "a".as_bytes(),
+            DataTypeRef::Integer(1),
+        )]));
+        assert_eq!(value.get("a").and_then(DataTypeRef::as_i64), Some(1));
+        assert_eq!(value.get("b"), None);
+        assert_eq!(DataTypeRef::Integer(1).get("a"), None);
+    }
+}
@@ -0,0 +1,220 @@
+use std::collections::BTreeMap;
+
+use serde_json::{Map, Value};
+use thiserror::Error;
+
+use crate::types::{ByteString, DataType};
+
+// A lossless mapping between bencoded values and JSON:
+// - integers are numbers, and byte strings that are valid UTF-8 are strings;
+// - other byte strings are `{"hex": "<hex encoded bytes>"}`;
+// - dicts are objects, unless they have a key that is not valid UTF-8, in which case they are
+//   `{"entries": [[<key>, <value>], ...]}` with the keys mapped like byte strings;
+// - dicts that would be mistaken for one of the above are wrapped in `{"dict": {...}}`.
+
+const HEX_MARKER: &str = "hex";
+const DICT_MARKER: &str = "dict";
+const ENTRIES_MARKER: &str = "entries";
+
+#[derive(Error, Debug, PartialEq)]
+pub enum JsonConversionError {
+    #[error("{path}: bencode has no {kind} values.")]
+    UnsupportedValue { path: String, kind: &'static str },
+    #[error("{path}: the number {number} is not a 64-bit integer.")]
+    InvalidNumber { path: String, number: String },
+    #[error("{path}: invalid hex string.")]
+    InvalidHex { path: String },
+    #[error("{path}: dict entries must be [key, value] pairs with byte string keys.")]
+    InvalidEntry { path: String },
+}
+
+/// Maps the value to JSON without losing any information, so that `from_json` can restore it.
+pub fn to_json_lossless(value: &DataType) -> Value {
+    match value {
+        DataType::Integer(number) => (*number).into(),
+        DataType::ByteString(byte_str) => byte_string_to_json(byte_str),
+        DataType::List(list) => Value::Array(list.iter().map(to_json_lossless).collect()),
+        DataType::Dict(dict) => {
+            if dict.keys().any(|key| key.as_str().is_none()) {
+                let entries: Vec<Value> = dict
+                    .iter()
+                    .map(|(key, value)| {
+                        Value::Array(vec![byte_string_to_json(key), to_json_lossless(value)])
+                    })
+                    .collect();
+                return marker(ENTRIES_MARKER, Value::Array(entries));
+            }
+            let map: Map<String, Value> = dict
+                .iter()
+                .map(|(key, value)| (key.to_string(), to_json_lossless(value)))
+                .collect();
+            let is_ambiguous: bool = map.len() == 1
+                && map
+                    .keys()
+                    .all(|key| [HEX_MARKER, DICT_MARKER, ENTRIES_MARKER].contains(&key.as_str()));
+            if is_ambiguous {
+                marker(DICT_MARKER, Value::Object(map))
+            } else {
+                Value::Object(map)
+            }
+        }
+    }
+}
+
+/// Maps JSON produced by `to_json_lossless` (or written by hand following the same rules) back
+/// to a bencodable value.
+pub fn from_json(value: &Value) -> Result<DataType, JsonConversionError> {
+    from_json_at(value, "$")
+}
+
+fn from_json_at(value: &Value, path: &str) -> Result<DataType, JsonConversionError> {
+    match value {
+        Value::Null => Err(JsonConversionError::UnsupportedValue {
+            path: path.to_owned(),
+            kind: "null",
+        }),
+        Value::Bool(_) => Err(JsonConversionError::UnsupportedValue {
+            path: path.to_owned(),
+            kind: "boolean",
+        }),
+        Value::Number(number) => number.as_i64().map(DataType::Integer).ok_or_else(|| {
+            JsonConversionError::InvalidNumber {
+                path: path.to_owned(),
+                number: number.to_string(),
+            }
+        }),
+        Value::String(string) => Ok(DataType::ByteString(string.as_str().into())),
+        Value::Array(list) => list
+            .iter()
+            .enumerate()
+            .map(|(index, element)| from_json_at(element, &format!("{path}[{index}]")))
+            .collect::<Result<Vec<DataType>, _>>()
+            .map(DataType::List),
+        Value::Object(map) => match single_entry(map) {
+            Some((HEX_MARKER, Value::String(hex))) => byte_string_from_hex(hex, path),
+            Some((DICT_MARKER, Value::Object(map))) => dict_from_object(map, path),
+            Some((ENTRIES_MARKER, Value::Array(entries))) => dict_from_entries(entries, path),
+            _ => dict_from_object(map, path),
+        },
+    }
+}
+
+fn byte_string_to_json(byte_str: &ByteString) -> Value {
+    match byte_str.as_str() {
+        Some(string) => Value::String(string.to_owned()),
+        None => marker(HEX_MARKER, Value::String(hex::encode(byte_str.get_data()))),
+    }
+}
+
+fn marker(name: &str, value: Value) -> Value {
+    Value::Object(Map::from_iter([(name.to_owned(), value)]))
+}
+
+fn single_entry(map: &Map<String, Value>) -> Option<(&str, &Value)> {
+    match map.len() {
+        1 => map.iter().next().map(|(key, value)| (key.as_str(), value)),
+        _ => None,
+    }
+}
+
+fn byte_string_from_hex(hex: &str, path: &str) -> Result<DataType, JsonConversionError> {
+    hex::decode(hex)
+        .map(|bytes| DataType::ByteString(bytes.as_slice().into()))
+        .map_err(|_| JsonConversionError::InvalidHex {
+            path: path.to_owned(),
+        })
+}
+
+fn dict_from_object(map: &Map<String, Value>, path: &str) -> Result<DataType, JsonConversionError> {
+    map.iter()
+        .map(|(key, value)| {
+            from_json_at(value, &format!("{path}.{key}"))
+                .map(|value| (ByteString::from(key.as_str()), value))
+        })
+        .collect::<Result<BTreeMap<ByteString, DataType>, _>>()
+        .map(DataType::Dict)
+}
+
+fn dict_from_entries(entries: &[Value], path: &str) -> Result<DataType, JsonConversionError> {
+    let mut dict: BTreeMap<ByteString, DataType> = BTreeMap::new();
+    for (index, entry) in entries.iter().enumerate() {
+        let entry_path: String = format!("{path}.{ENTRIES_MARKER}[{index}]");
+        let invalid_entry = || JsonConversionError::InvalidEntry {
+            path: entry_path.clone(),
+        };
+        let [key, value] = entry.as_array().map(Vec::as_slice).unwrap_or_default() else {
+            return Err(invalid_entry());
+        };
+        let key: ByteString = match from_json_at(key, &entry_path)? {
+            DataType::ByteString(key) => key,
+            _ => return Err(invalid_entry()),
+        };
+        dict.insert(key, from_json_at(value, &entry_path)?);
+    }
+    Ok(DataType::Dict(dict))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn round_trip(value: DataType) -> Value {
+        let json: Value = to_json_lossless(&value);
+        assert_eq!(from_json(&json).unwrap(), value);
+        json
+    }
+
+    fn dict(entries: &[(&[u8], DataType)]) -> DataType {
+        DataType::Dict(
+            entries
+                .iter()
+                .map(|(key, value)| (ByteString::from(*key), value.clone()))
+                .collect(),
+        )
+    }
+
+    #[test]
+    fn test_binary_strings_are_hex_encoded() {
+        let json = round_trip(DataType::List(vec![
+            DataType::ByteString([0xFF, 0x00].as_slice().into()),
+            DataType::ByteString("a".into()),
+        ]));
+        assert_eq!(json, serde_json::json!([{"hex": "ff00"}, "a"]));
+    }
+
+    #[test]
+    fn test_binary_keys_are_entries() {
+        let json = round_trip(dict(&[(&[0xFF], DataType::Integer(1))]));
+        assert_eq!(json, serde_json::json!({"entries": [[{"hex": "ff"}, 1]]}));
+    }
+
+    #[test]
+    fn test_dicts_looking_like_markers_are_wrapped() {
+        let json = round_trip(dict(&[(b"hex", DataType::ByteString("ff".into()))]));
+        assert_eq!(json, serde_json::json!({"dict": {"hex": "ff"}}));
+
+        let json = round_trip(dict(&[(b"dict", dict(&[]))]));
+        assert_eq!(json, serde_json::json!({"dict": {"dict": {}}}));
+
+        let json = round_trip(dict(&[
+            (b"hex", DataType::ByteString("ff".into())),
+            (b"a", DataType::Integer(1)),
+        ]));
+        assert_eq!(json, serde_json::json!({"a": 1, "hex": "ff"}));
+    }
+
+    #[test]
+    fn test_unsupported_json() {
+        let result = from_json(&serde_json::json!({"a": [1, 2.5]}));
+        assert_eq!(
+            result.unwrap_err(),
+            JsonConversionError::InvalidNumber {
+                path: "$.a[1]".to_owned(),
+                number: "2.5".to_owned()
+            }
+        );
+        assert!(from_json(&serde_json::json!(null)).is_err());
+        assert!(from_json(&serde_json::json!({"hex": "xyz"})).is_err());
+        assert!(from_json(&serde_json::json!({"entries": [[1, 2]]})).is_err());
+    }
+}
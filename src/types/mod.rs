@@ -1,6 +1,8 @@
 // region:      --- Public Modules
 pub(crate) mod byte_string;
 pub(crate) mod data_type;
+pub(crate) mod data_type_ref;
+pub(crate) mod json;
 // endregion:   --- Public Modules
 
 // region:      --- Modules
@@ -9,4 +11,6 @@ pub(crate) mod data_type;
 // region:      --- Flatten (private, crate, public)
 pub(crate) use byte_string::*;
 pub(crate) use data_type::*;
+pub(crate) use data_type_ref::*;
+pub(crate) use json::*;
 // endregion:   --- Flatten (private, crate, public)
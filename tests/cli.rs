@@ -0,0 +1,20 @@
+use std::process::{Command, Output};
+
+fn run(args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_codecrafters-bittorrent"))
+        .args(args)
+        .current_dir(env!("CARGO_MANIFEST_DIR"))
+        .output()
+        .unwrap()
+}
+
+#[test]
+fn test_magnet_create_prints_only_the_link() {
+    let output: Output = run(&["magnet_create", "sample.torrent"]);
+    assert!(output.status.success());
+    assert_eq!(
+        String::from_utf8(output.stdout).unwrap(),
+        "magnet:?xt=urn:btih:d69f91e6b2ae4c542468d1073a71d4ea13879a7f&dn=sample.txt\
+         &tr=http%3A%2F%2Fbittorrent-test-tracker.codecrafters.io%2Fannounce&xl=92063\n"
+    );
+}